@@ -1,4 +1,5 @@
 use anchor_lang::{prelude::*, solana_program};
+use anchor_lang::solana_program::{program::{invoke, invoke_signed}, system_instruction};
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
 pub fn mint_to_and_freeze<'a>(
@@ -104,6 +105,42 @@ pub fn approve_delegate<'a>(
     anchor_spl::token::approve(cpi_ctx, amount)
 }
 
+// Calls splToken's setAuthority instruction, handing the token account
+// to a new owner
+pub fn set_token_owner<'a>(
+    token_program: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    new_owner: Pubkey,
+) -> Result<()> {
+    let cpi_program = token_program;
+    let cpi_accounts = anchor_spl::token::SetAuthority {
+        account_or_mint: account,
+        current_authority: authority,
+    };
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    anchor_spl::token::set_authority(
+        cpi_ctx,
+        anchor_spl::token::spl_token::instruction::AuthorityType::AccountOwner,
+        Some(new_owner),
+    )
+}
+
+// Calls splToken's revoke instruction
+pub fn revoke_delegate<'a>(
+    token_program: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+) -> Result<()> {
+    let cpi_program = token_program;
+    let cpi_accounts = anchor_spl::token::Revoke {
+        source: account,
+        authority,
+    };
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    anchor_spl::token::revoke(cpi_ctx)
+}
+
 // Transfer from one token account to another using the Token Program
 pub fn token_transfer_with_seed<'a>(
     token_program: AccountInfo<'a>,
@@ -127,6 +164,25 @@ pub fn token_transfer_with_seed<'a>(
     anchor_spl::token::transfer(cpi_ctx, amount)
 }
 
+// Transfer from a token account whose authority is a PDA with arbitrary seeds
+pub fn token_transfer_with_signer<'a>(
+    token_program: AccountInfo<'a>,
+    from: AccountInfo<'a>,
+    to: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    amount: u64,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let cpi_accounts = anchor_spl::token::Transfer {
+        from,
+        to,
+        authority,
+    };
+    let signers = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signers);
+    anchor_spl::token::transfer(cpi_ctx, amount)
+}
+
 pub fn token_transfer<'a>(
     token_program: AccountInfo<'a>,
     from: AccountInfo<'a>,
@@ -168,7 +224,60 @@ pub fn burn<'a>(
 pub fn sync_native<'a>(token_program: &AccountInfo<'a>, account: AccountInfo<'a>) -> Result<()> {
     let ix = spl_token::instruction::sync_native(&token_program.key(), &account.key())?;
 
-    solana_program::program::invoke(&ix, &[account.clone()]).map_err(Into::into)
+    solana_program::program::invoke(&ix, &[account, token_program.clone()]).map_err(Into::into)
+}
+
+// Creates a program-owned account at a PDA. Unlike the system program's
+// create_account, this still works if someone has already sent lamports
+// to the address, so a deterministic PDA can't be blocked that way.
+pub fn create_pda_account<'a>(
+    payer: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        let ix = system_instruction::create_account(
+            payer.key,
+            account.key,
+            lamports,
+            space as u64,
+            &crate::ID,
+        );
+        return invoke_signed(&ix, &[payer, account, system_program], &[seeds]).map_err(Into::into);
+    }
+
+    let required = lamports.saturating_sub(account.lamports());
+    if required > 0 {
+        let ix = system_instruction::transfer(payer.key, account.key, required);
+        invoke(&ix, &[payer, account.clone(), system_program.clone()])?;
+    }
+
+    let ix = system_instruction::allocate(account.key, space as u64);
+    invoke_signed(&ix, &[account.clone(), system_program.clone()], &[seeds])?;
+
+    let ix = system_instruction::assign(account.key, &crate::ID);
+    invoke_signed(&ix, &[account, system_program], &[seeds]).map_err(Into::into)
+}
+
+// Grows a program-owned account to new_len, topping up its rent from
+// the payer. The bytes it grows by are zeroed.
+pub fn realloc_account<'a>(
+    account: &AccountInfo<'a>,
+    payer: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    new_len: usize,
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(new_len);
+    let required = lamports.saturating_sub(account.lamports());
+    if required > 0 {
+        let ix = system_instruction::transfer(payer.key, account.key, required);
+        invoke(&ix, &[payer, account.clone(), system_program])?;
+    }
+
+    account.realloc(new_len, true).map_err(Into::into)
 }
 
 pub fn erase_data<'a>(account: &AccountInfo<'a>) {
@@ -208,3 +317,28 @@ pub fn close_token_escrow_account<'a>(
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signers);
     anchor_spl::token::close_account(cpi_ctx)
 }
+
+// Creates the associated token account of "authority" for "mint",
+// paid for by "payer".
+pub fn create_associated_token_account<'a>(
+    associated_token_program: AccountInfo<'a>,
+    payer: AccountInfo<'a>,
+    associated_token: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    system_program: AccountInfo<'a>,
+    token_program: AccountInfo<'a>,
+    rent: AccountInfo<'a>,
+) -> Result<()> {
+    let cpi_accounts = anchor_spl::associated_token::Create {
+        payer,
+        associated_token,
+        authority,
+        mint,
+        system_program,
+        token_program,
+        rent,
+    };
+    let cpi_ctx = CpiContext::new(associated_token_program, cpi_accounts);
+    anchor_spl::associated_token::create(cpi_ctx)
+}
@@ -70,9 +70,336 @@ pub enum StrangemoodError {
     #[msg("Signer is not Mint Authority")]
     SignerIsNotMintAuthority,
 
-    // The cashier's split must be 1.0 to 0.0.
+    // The cashier's split must be 0 to 10_000 basis points.
     #[msg("Invalid Cashier Split")]
     CashierSplitIsInvalid,
 
     ListingIsSuspended,
+
+    // A rate, in basis points, was greater than 10_000 (100%).
+    #[msg("Invalid Basis Points")]
+    InvalidBasisPoints,
+
+    #[msg("Arithmetic Overflow")]
+    ArithmeticOverflow,
+
+    // The account still uses the old floating point layout,
+    // and needs to be migrated before it can be used.
+    #[msg("Account Needs Migration")]
+    AccountNeedsMigration,
+
+    #[msg("Account Already Migrated")]
+    AccountAlreadyMigrated,
+
+    // A purchase asked for a receipt, but didn't pass the receipt
+    // account, ["purchase_receipt", listing, purchaser, nonce], after
+    // the instruction's other accounts.
+    #[msg("Missing Purchase Receipt")]
+    MissingPurchaseReceipt,
+
+    // A listing's refund window can't be negative.
+    #[msg("Invalid Refund Window")]
+    RefundWindowIsInvalid,
+
+    // The trial's refund window has passed, so it can only be finished.
+    #[msg("Refund Window Has Expired")]
+    RefundWindowHasExpired,
+
+    // The trial can still be refunded, so only the purchaser
+    // can finish it before the refund window passes.
+    #[msg("Trial Is Still Refundable")]
+    TrialIsStillRefundable,
+
+    // The total price of a purchase was more than the buyer's
+    // max_total_price, likely because the listing's price changed.
+    #[msg("Price Exceeds Maximum")]
+    PriceExceedsMaximum,
+
+    // The charter's payment contribution was more than the
+    // buyer's max_payment_contribution.
+    #[msg("Contribution Exceeds Maximum")]
+    ContributionExceedsMaximum,
+
+    // Selling this many would go over the listing's max_supply.
+    #[msg("Listing is Sold Out")]
+    ListingIsSoldOut,
+
+    // Selling this many would go over the listing's max_per_wallet.
+    #[msg("Wallet Limit Exceeded")]
+    WalletLimitExceeded,
+
+    // A listing has a max_per_wallet, but the purchaser's
+    // WalletPurchases account wasn't passed.
+    #[msg("Missing Wallet Purchases")]
+    MissingWalletPurchases,
+
+    // A listing's max_supply can't be lower than what it's already sold.
+    #[msg("Invalid Max Supply")]
+    MaxSupplyIsInvalid,
+
+    // A sale has to end after it starts.
+    #[msg("Invalid Sale Schedule")]
+    SaleScheduleIsInvalid,
+
+    // A coupon needs exactly one of a discount amount or a
+    // discount rate, and the rate can't be more than 10_000.
+    #[msg("Invalid Coupon Discount")]
+    CouponDiscountIsInvalid,
+
+    // A purchase asked for a coupon, but didn't pass the coupon account
+    // after the instruction's other accounts.
+    #[msg("Missing Coupon")]
+    MissingCoupon,
+
+    CouponHasUnexpectedListing,
+
+    CouponHasUnexpectedPurchaser,
+
+    #[msg("Coupon Has Expired")]
+    CouponHasExpired,
+
+    #[msg("Coupon Has No Uses Left")]
+    CouponIsUsedUp,
+
+    // A listing's splits need between 1 and ListingSplits::MAX_RECIPIENTS
+    // recipients, whose shares add up to exactly 10_000.
+    #[msg("Invalid Listing Splits")]
+    ListingSplitsAreInvalid,
+
+    // A split recipient's token account wasn't passed after the
+    // instruction's other accounts, or isn't in the listing's payment mint.
+    #[msg("Missing Split Recipient")]
+    MissingSplitRecipient,
+
+    // The listing splits its revenue, but its ListingSplits
+    // wasn't passed after the instruction's other accounts.
+    #[msg("Missing Listing Splits")]
+    MissingListingSplits,
+
+    ListingSplitsHasUnexpectedListing,
+
+    // A bundle needs between 2 and Bundle::MAX_LISTINGS different
+    // listings, all in the bundle's charter, a weight for each listing,
+    // and a URI that fits.
+    #[msg("Invalid Bundle")]
+    BundleIsInvalid,
+
+    // Every listing's authority has to sign to put it in a bundle.
+    #[msg("Bundle Is Missing A Listing Authority")]
+    BundleIsMissingListingAuthority,
+
+    #[msg("Bundle Is Unavailable")]
+    BundleIsUnavailable,
+
+    // A bundle purchase needs each listing's accounts, in the bundle's
+    // order, after the instruction's other accounts.
+    #[msg("Missing Bundle Listing")]
+    MissingBundleListing,
+
+    BundleHasUnexpectedListing,
+
+    BundleHasUnexpectedAuthority,
+
+    BundleHasUnexpectedCharter,
+
+    // A listing can't require itself.
+    #[msg("Invalid Listing Requirement")]
+    ListingRequirementIsInvalid,
+
+    // The listing requires another listing, but that listing
+    // wasn't passed after the instruction's other accounts.
+    #[msg("Missing Required Listing")]
+    MissingRequiredListing,
+
+    // The listing requires another listing, and none of the purchaser's
+    // inventories that were passed hold that listing's token.
+    #[msg("Required Listing Is Not Owned")]
+    RequiredListingIsNotOwned,
+
+    // A subscription period can't be negative.
+    #[msg("Invalid Subscription Period")]
+    SubscriptionPeriodIsInvalid,
+
+    // Subscriptions can only be bought with purchase or purchase_with_cashier.
+    #[msg("Listing Is A Subscription")]
+    ListingIsSubscription,
+
+    // The listing is a subscription, but the inventory's Subscription
+    // wasn't passed after the instruction's other accounts.
+    #[msg("Missing Subscription")]
+    MissingSubscription,
+
+    SubscriptionHasUnexpectedListing,
+
+    SubscriptionHasUnexpectedInventory,
+
+    #[msg("Subscription Has Expired")]
+    SubscriptionHasExpired,
+
+    SubscriptionHasUnexpectedPurchaser,
+
+    // A subscription can only be renewed automatically once it's expired.
+    #[msg("Subscription Is Still Active")]
+    SubscriptionIsStillActive,
+
+    AutoRenewalHasUnexpectedSubscription,
+
+    AutoRenewalHasUnexpectedPayment,
+
+    AutoRenewalHasUnexpectedPurchaser,
+
+    // A resale royalty is in basis points, and can't be more than 100%.
+    #[msg("Resale Royalty Is Invalid")]
+    ResaleRoyaltyIsInvalid,
+
+    #[msg("Listing Is Not Resellable")]
+    ListingIsNotResellable,
+
+    // A resale offer can't offer more listing tokens than the inventory holds.
+    #[msg("Resale Offer Is Invalid")]
+    ResaleOfferIsInvalid,
+
+    #[msg("Resale Offer Has Insufficient Quantity")]
+    ResaleOfferHasInsufficientQuantity,
+
+    ResaleOfferHasUnexpectedListing,
+
+    ResaleOfferHasUnexpectedSeller,
+
+    ResaleOfferHasUnexpectedInventory,
+
+    ResaleOfferHasUnexpectedDeposit,
+
+    TokenAccountHasUnexpectedOwner,
+
+    // A gift needs a quantity, an expiry in the future, and exactly
+    // one of a recipient or a secret key.
+    #[msg("Gift Is Invalid")]
+    GiftIsInvalid,
+
+    GiftHasUnexpectedListing,
+
+    GiftHasUnexpectedPurchaser,
+
+    #[msg("Gift Has Unexpected Recipient")]
+    GiftHasUnexpectedRecipient,
+
+    #[msg("Gift Secret Is Invalid")]
+    GiftSecretIsInvalid,
+
+    #[msg("Gift Has Expired")]
+    GiftHasExpired,
+
+    // A gift can only be reclaimed by its purchaser once it's expired.
+    #[msg("Gift Has Not Expired")]
+    GiftHasNotExpired,
+
+    // Trials need the listing to be finished or refunded.
+    #[msg("Listing Has Open Trials")]
+    ListingHasOpenTrials,
+
+    // Gifts need the listing to be claimed or reclaimed.
+    #[msg("Listing Has Open Gifts")]
+    ListingHasOpenGifts,
+
+    // A uri can't be longer than the space its account allocated for it.
+    #[msg("Uri Is Too Long")]
+    UriIsTooLong,
+
+    // Stake is withdrawn over a number of epochs, so it can't be 0.
+    #[msg("Withdraw Period Is Invalid")]
+    WithdrawPeriodIsInvalid,
+
+    // Only the key an authority transfer was proposed to can accept it.
+    #[msg("Signer Is Not Pending Authority")]
+    SignerIsNotPendingAuthority,
+
+    #[msg("No Pending Authority")]
+    NoPendingAuthority,
+
+    // A governance's threshold has to be above 0 and at most 100%, and
+    // its voting period has to be positive.
+    #[msg("Governance Config Is Invalid")]
+    GovernanceConfigIsInvalid,
+
+    GovernanceHasUnexpectedCharter,
+
+    ProposalHasUnexpectedGovernance,
+
+    VoteRecordHasUnexpectedProposal,
+
+    VoteRecordHasUnexpectedVoter,
+
+    VoteRecordHasUnexpectedEscrow,
+
+    #[msg("Vote Amount Is Invalid")]
+    VoteAmountIsInvalid,
+
+    #[msg("Voting Has Ended")]
+    VotingHasEnded,
+
+    #[msg("Voting Has Not Ended")]
+    VotingHasNotEnded,
+
+    #[msg("Proposal Has Been Executed")]
+    ProposalHasBeenExecuted,
+
+    // Either too few votes were cast to reach quorum, or too few were in favor.
+    #[msg("Proposal Has Not Passed")]
+    ProposalHasNotPassed,
+
+    // The account a proposal's action changes wasn't in remaining_accounts.
+    #[msg("Missing Proposal Account")]
+    MissingProposalAccount,
+
+    #[msg("Change Delay Is Invalid")]
+    ChangeDelayIsInvalid,
+
+    // A payment contribution can't be above the charter's cap, or 100%.
+    #[msg("Payment Contribution Is Invalid")]
+    PaymentContributionIsInvalid,
+
+    // A vote contribution can't be above the charter's cap, or 100%.
+    #[msg("Vote Contribution Is Invalid")]
+    VoteContributionIsInvalid,
+
+    // Contribution caps can only be lowered.
+    #[msg("Contribution Cap Is Invalid")]
+    ContributionCapIsInvalid,
+
+    // Purchases divide by a treasury's scalar, so it can't be 0.
+    #[msg("Scalar Is Invalid")]
+    ScalarIsInvalid,
+
+    // A grant needs an amount, and a vesting schedule that ends after it starts.
+    #[msg("Grant Is Invalid")]
+    GrantIsInvalid,
+
+    #[msg("Grant Has Nothing To Claim")]
+    GrantHasNothingToClaim,
+
+    GrantHasUnexpectedTreasury,
+
+    GrantHasUnexpectedCharter,
+
+    // Listings migrated from the legacy layout may have trials that
+    // open_trials doesn't count yet, so they can't be closed until
+    // their legacy_trials_until.
+    #[msg("Listing Has Uncounted Trials")]
+    ListingHasUncountedTrials,
+
+    GrantHasUnexpectedPayer,
+
+    // A passed proposal has to be executed before its executable_until.
+    #[msg("Proposal Has Expired")]
+    ProposalHasExpired,
+
+    // Trials need a refund window, so they can always be refunded
+    // or finished. Set one with set_listing_refund_window.
+    #[msg("Listing Has No Refund Window")]
+    ListingHasNoRefundWindow,
+
+    // An amount can't be split between no one.
+    #[msg("Invalid Splits")]
+    InvalidSplits,
 }
@@ -0,0 +1,679 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{GrantVesting, ProposalAction, SplitRecipient};
+use crate::{Splits, SplitsWithCashier, SplitsWithSeller, VoteSplits};
+
+// Events are emitted by every instruction that changes state, so that
+// an indexer can reconstruct sales, refunds, and governance distributions
+// from the program logs alone. Rates are in basis points.
+
+#[event]
+pub struct ListingInitialized {
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub payment_deposit: Pubkey,
+    pub vote_deposit: Pubkey,
+    pub price: u64,
+    pub is_refundable: bool,
+    pub is_consumable: bool,
+    pub is_available: bool,
+    pub cashier_split: u64,
+    pub refund_window: i64,
+    pub max_supply: u64,
+    pub max_per_wallet: u64,
+    pub uri: String,
+}
+
+#[event]
+pub struct ListingPriceSet {
+    pub listing: Pubkey,
+    pub price: u64,
+}
+
+#[event]
+pub struct ListingUriSet {
+    pub listing: Pubkey,
+    pub uri: String,
+}
+
+#[event]
+pub struct ListingAvailabilitySet {
+    pub listing: Pubkey,
+    pub is_available: bool,
+}
+
+#[event]
+pub struct ListingRefundWindowSet {
+    pub listing: Pubkey,
+    pub refund_window: i64,
+}
+
+#[event]
+pub struct ListingSaleSet {
+    pub listing: Pubkey,
+    pub sale_price: u64,
+    pub sale_starts_at: i64,
+    pub sale_ends_at: i64,
+}
+
+#[event]
+pub struct ListingSupplyLimitsSet {
+    pub listing: Pubkey,
+    pub max_supply: u64,
+    pub max_per_wallet: u64,
+}
+
+#[event]
+pub struct ListingSplitsSet {
+    pub listing: Pubkey,
+    pub recipients: Vec<SplitRecipient>,
+}
+
+#[event]
+pub struct ListingSplitsClosed {
+    pub listing: Pubkey,
+}
+
+#[event]
+pub struct ListingRequirementSet {
+    pub listing: Pubkey,
+    pub requires: Option<Pubkey>,
+}
+
+#[event]
+pub struct ListingSubscriptionSet {
+    pub listing: Pubkey,
+    pub subscription_period: i64,
+    pub renewal_price: u64,
+}
+
+#[event]
+pub struct ListingDepositsSet {
+    pub listing: Pubkey,
+    pub payment_deposit: Pubkey,
+    pub vote_deposit: Pubkey,
+}
+
+#[event]
+pub struct ListingAuthoritySet {
+    pub listing: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ListingAuthorityProposed {
+    pub listing: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct ListingAuthorityTransferCancelled {
+    pub listing: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct ListingCharterSet {
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub vote_deposit: Pubkey,
+}
+
+#[event]
+pub struct ListingSuspensionSet {
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub is_suspended: bool,
+}
+
+#[event]
+pub struct ListingClosed {
+    pub listing: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ListingMigrated {
+    pub listing: Pubkey,
+    pub cashier_split: u64,
+}
+
+#[event]
+pub struct Purchased {
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub receipt: Option<Pubkey>,
+    pub quantity: u64,
+    pub price: u64,
+    pub splits: Splits,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct PurchasedWithCashier {
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub cashier: Pubkey,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub receipt: Option<Pubkey>,
+    pub quantity: u64,
+    pub price: u64,
+    pub splits: SplitsWithCashier,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct TrialStarted {
+    pub receipt: Pubkey,
+    pub listing: Pubkey,
+    pub cashier: Option<Pubkey>,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub escrow: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+    pub started_at: i64,
+    pub refund_window: i64,
+}
+
+#[event]
+pub struct TrialFinished {
+    pub receipt: Pubkey,
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub purchaser: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+    pub splits: Splits,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct TrialFinishedWithCashier {
+    pub receipt: Pubkey,
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub cashier: Pubkey,
+    pub purchaser: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+    pub splits: SplitsWithCashier,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct TrialRefunded {
+    pub receipt: Pubkey,
+    pub listing: Pubkey,
+    pub cashier: Option<Pubkey>,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub quantity: u64,
+
+    // The amount returned from the escrow
+    pub amount: u64,
+}
+
+#[event]
+pub struct BundleInitialized {
+    pub bundle: Pubkey,
+    pub authority: Pubkey,
+    pub charter: Pubkey,
+    pub listings: Vec<Pubkey>,
+    pub weights: Vec<u64>,
+    pub price: u64,
+    pub uri: String,
+}
+
+#[event]
+pub struct BundleAvailabilitySet {
+    pub bundle: Pubkey,
+    pub is_available: bool,
+}
+
+// Emitted once per bundle purchase, after a Purchased
+// event for each of the bundle's listings.
+#[event]
+pub struct BundlePurchased {
+    pub bundle: Pubkey,
+    pub purchaser: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+}
+
+#[event]
+pub struct CouponInitialized {
+    pub coupon: Pubkey,
+    pub listing: Pubkey,
+    pub discount_amount: u64,
+    pub discount_rate: u64,
+    pub max_uses: u64,
+    pub expires_at: i64,
+    pub purchaser: Option<Pubkey>,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub coupon: Pubkey,
+    pub listing: Pubkey,
+    pub purchaser: Pubkey,
+    pub uses: u64,
+}
+
+#[event]
+pub struct CouponClosed {
+    pub coupon: Pubkey,
+    pub listing: Pubkey,
+}
+
+#[event]
+pub struct SubscriptionExtended {
+    pub subscription: Pubkey,
+    pub listing: Pubkey,
+    pub inventory: Pubkey,
+    pub purchaser: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct AutoRenewalEnabled {
+    pub auto_renewal: Pubkey,
+    pub subscription: Pubkey,
+    pub purchaser: Pubkey,
+    pub payment: Pubkey,
+    pub allowance: u64,
+}
+
+#[event]
+pub struct AutoRenewalCancelled {
+    pub auto_renewal: Pubkey,
+    pub subscription: Pubkey,
+
+    // The AutoRenewals still renewing from the same payment account. If 0,
+    // the payment account's delegate was revoked.
+    pub renewals: u64,
+}
+
+#[event]
+pub struct SubscriptionRenewed {
+    pub subscription: Pubkey,
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub purchaser: Pubkey,
+    pub price: u64,
+    pub expires_at: i64,
+    pub splits: Splits,
+    pub votes: VoteSplits,
+}
+
+// Emitted instead of SubscriptionRenewed when the listing can't be
+// renewed right now, or the payment account's allowance or balance
+// can't cover it. The auto-renewal is kept, so it can be tried again.
+#[event]
+pub struct AutoRenewalFailed {
+    pub auto_renewal: Pubkey,
+    pub subscription: Pubkey,
+    pub price: u64,
+}
+
+#[event]
+pub struct ListingResaleSet {
+    pub listing: Pubkey,
+    pub is_resellable: bool,
+    pub resale_royalty: u64,
+}
+
+#[event]
+pub struct ResaleOfferSet {
+    pub resale_offer: Pubkey,
+    pub listing: Pubkey,
+    pub seller: Pubkey,
+    pub inventory: Pubkey,
+    pub deposit: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct ResaleOfferCancelled {
+    pub resale_offer: Pubkey,
+    pub listing: Pubkey,
+}
+
+#[event]
+pub struct Resold {
+    pub resale_offer: Pubkey,
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub seller: Pubkey,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+    pub splits: SplitsWithSeller,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct GiftPurchased {
+    pub gift: Pubkey,
+    pub listing: Pubkey,
+    pub charter: Pubkey,
+    pub purchaser: Pubkey,
+    pub recipient: Option<Pubkey>,
+    pub quantity: u64,
+    pub price: u64,
+    pub expires_at: i64,
+    pub splits: Splits,
+    pub votes: VoteSplits,
+}
+
+#[event]
+pub struct GiftClaimed {
+    pub gift: Pubkey,
+    pub listing: Pubkey,
+    pub recipient: Pubkey,
+    pub inventory: Pubkey,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct GiftReclaimed {
+    pub gift: Pubkey,
+    pub listing: Pubkey,
+    pub purchaser: Pubkey,
+    pub inventory: Pubkey,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct Consumed {
+    pub listing: Pubkey,
+    pub inventory: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CharterInitialized {
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub reserve: Pubkey,
+    pub expansion_rate: u64,
+    pub payment_contribution: u64,
+    pub vote_contribution: u64,
+    pub withdraw_period: u64,
+    pub stake_withdraw_amount: u64,
+    pub uri: String,
+}
+
+#[event]
+pub struct CharterContributionCapsSet {
+    pub charter: Pubkey,
+    pub max_payment_contribution: u64,
+    pub max_vote_contribution: u64,
+}
+
+#[event]
+pub struct CharterChangeQueued {
+    pub charter: Pubkey,
+    pub expansion_rate: u64,
+    pub payment_contribution: u64,
+    pub vote_contribution: u64,
+    pub change_delay: i64,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct CharterAuthoritySet {
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct CharterAuthorityProposed {
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct CharterAuthorityTransferCancelled {
+    pub charter: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct CharterUriSet {
+    pub charter: Pubkey,
+    pub uri: String,
+}
+
+#[event]
+pub struct CharterWithdrawPeriodSet {
+    pub charter: Pubkey,
+    pub withdraw_period: u64,
+}
+
+#[event]
+pub struct CharterStakeWithdrawAmountSet {
+    pub charter: Pubkey,
+    pub stake_withdraw_amount: u64,
+}
+
+#[event]
+pub struct CharterReserveSet {
+    pub charter: Pubkey,
+    pub reserve: Pubkey,
+}
+
+#[event]
+pub struct ReceiptMigrated {
+    pub receipt: Pubkey,
+    pub started_at: i64,
+    pub refund_window: i64,
+}
+
+#[event]
+pub struct CharterMigrated {
+    pub charter: Pubkey,
+    pub expansion_rate: u64,
+    pub payment_contribution: u64,
+    pub vote_contribution: u64,
+}
+
+#[event]
+pub struct CharterTreasuryInitialized {
+    pub treasury: Pubkey,
+    pub charter: Pubkey,
+    pub deposit: Pubkey,
+    pub mint: Pubkey,
+    pub scalar: u64,
+}
+
+#[event]
+pub struct CharterTreasuryScalarSet {
+    pub treasury: Pubkey,
+    pub scalar: u64,
+}
+
+#[event]
+pub struct CharterTreasuryDepositSet {
+    pub treasury: Pubkey,
+    pub deposit: Pubkey,
+}
+
+#[event]
+pub struct CharterTreasuryMigrated {
+    pub treasury: Pubkey,
+    pub scalar: u64,
+}
+
+#[event]
+pub struct CashierInitialized {
+    pub cashier: Pubkey,
+    pub charter: Pubkey,
+    pub stake: Pubkey,
+    pub authority: Pubkey,
+    pub uri: String,
+}
+
+#[event]
+pub struct CashierUriSet {
+    pub cashier: Pubkey,
+    pub uri: String,
+}
+
+#[event]
+pub struct CashierAuthoritySet {
+    pub cashier: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct CashierAuthorityProposed {
+    pub cashier: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct CashierAuthorityTransferCancelled {
+    pub cashier: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct CashierTreasuryInitialized {
+    pub cashier_treasury: Pubkey,
+    pub cashier: Pubkey,
+    pub escrow: Pubkey,
+    pub deposit: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct CashierStakeBurned {
+    pub charter: Pubkey,
+    pub cashier: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CashierTreasuryWithdrawn {
+    pub cashier: Pubkey,
+    pub cashier_treasury: Pubkey,
+    pub amount: u64,
+    pub epoch: u64,
+}
+
+#[event]
+pub struct CashierStakeWithdrawn {
+    pub cashier: Pubkey,
+    pub amount: u64,
+    pub epoch: u64,
+}
+
+#[event]
+pub struct GovernanceInitialized {
+    pub governance: Pubkey,
+    pub charter: Pubkey,
+    pub quorum: u64,
+    pub threshold: u64,
+    pub voting_period: i64,
+}
+
+#[event]
+pub struct GovernanceConfigSet {
+    pub governance: Pubkey,
+    pub quorum: u64,
+    pub threshold: u64,
+    pub voting_period: i64,
+}
+
+#[event]
+pub struct ProposalInitialized {
+    pub proposal: Pubkey,
+    pub governance: Pubkey,
+    pub proposer: Pubkey,
+    pub index: u64,
+    pub action: ProposalAction,
+    pub voting_ends_at: i64,
+    pub executable_until: i64,
+}
+
+#[event]
+pub struct VoteCast {
+    pub vote: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub approve: bool,
+}
+
+#[event]
+pub struct VoteWithdrawn {
+    pub vote: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub is_retracted: bool,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal: Pubkey,
+    pub governance: Pubkey,
+    pub action: ProposalAction,
+}
+
+#[event]
+pub struct ReserveSpent {
+    pub charter: Pubkey,
+    pub reserve: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GrantInitialized {
+    pub grant: Pubkey,
+    pub charter: Pubkey,
+    pub treasury: Pubkey,
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub vesting: Option<GrantVesting>,
+}
+
+#[event]
+pub struct GrantClaimed {
+    pub grant: Pubkey,
+    pub recipient: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+
+    // The total claimed so far
+    pub claimed: u64,
+}
+
+#[event]
+pub struct GrantCancelled {
+    pub grant: Pubkey,
+
+    // The grant's new total, which is what had vested, or for a grant
+    // without a vesting schedule, what had been claimed
+    pub amount: u64,
+}
+
+#[event]
+pub struct GrantClosed {
+    pub grant: Pubkey,
+    pub claimed: u64,
+}
@@ -1,13 +1,18 @@
 use anchor_lang::{declare_id, prelude::*, System, account, Accounts};
+use anchor_lang::solana_program::{program::invoke, system_instruction};
+use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
 use cpi::{mint_to, token_transfer, token_transfer_with_seed};
-use state::{CashierTreasury, Charter, Cashier, CharterTreasury, Listing, Receipt};
+use state::{AutoRenewal, AutoRenewalPayment, Bundle, CashierTreasury, Charter, Cashier, CharterTreasury, Coupon, Gift, Governance, Grant, GrantVesting, Listing, ListingSplits, PendingCharterChange, Proposal, ProposalAction, PurchaseReceipt, Receipt, ResaleOffer, SplitRecipient, Subscription, VoteRecord, WalletPurchases, ACCOUNT_VERSION};
 use std::cmp;
 
 pub mod state;
 pub mod error;
 pub mod cpi;
+pub mod math;
+pub mod migrate;
+pub mod events;
 
 use crate::error::StrangemoodError;
 
@@ -15,19 +20,18 @@ declare_id!("sm3L2zgBxMgz34U5f2zifjMDFYEZNEc1SNC6Ur8CXWx");
 
 fn distribute_governance_tokens<'a>(
     contributed: u64, 
-    scalar: f64, 
-    contribution_rate: f64, 
+    expansion_rate: u64, 
+    scalar: u64, 
+    contribution_rate: u64, 
     token_program: Program<'a, Token>, 
     charter_mint: Account<'a, Mint>,
     charter_mint_authority: AccountInfo<'a>,
     charter_mint_authority_bump: u8,
     listing_deposit: Account<'a, TokenAccount>,
     charter_deposit: Account<'a, TokenAccount>,
-) -> Result<()> {
-    let votes = contributed as f64 * scalar;
-    let deposit_rate = 1.0 - contribution_rate;
-    let deposit_amount = (deposit_rate * votes as f64) as u64;
-    let contribution_amount = (votes as u64).checked_sub(deposit_amount).unwrap();
+) -> Result<VoteSplits> {
+    let votes = math::scale(contributed, expansion_rate, scalar)?;
+    let (deposit_amount, contribution_amount) = math::split(votes, contribution_rate)?;
 
     // Mint votes to lister
     mint_to(
@@ -49,15 +53,835 @@ fn distribute_governance_tokens<'a>(
         contribution_amount,
     )?;
 
+    Ok(VoteSplits { to_lister_amount: deposit_amount, to_charter_amount: contribution_amount })
+}
+
+// Creates a PurchaseReceipt at the PDA ["purchase_receipt", listing, purchaser, nonce].
+//
+// The receipt account is passed in the remaining accounts, so that
+// purchases that don't want a receipt don't need to pass it at all.
+fn write_purchase_receipt<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    purchaser: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    receipt: PurchaseReceipt,
+) -> Result<Pubkey> {
+    let nonce = receipt.nonce.to_le_bytes();
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"purchase_receipt", receipt.listing.as_ref(), receipt.purchaser.as_ref(), &nonce],
+        &crate::ID,
+    );
+    let receipt_info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == address)
+        .ok_or(StrangemoodError::MissingPurchaseReceipt)?;
+
+    // The purchaser pays rent for their own receipt
+    let seeds: &[&[u8]] = &[b"purchase_receipt", receipt.listing.as_ref(), receipt.purchaser.as_ref(), &nonce, &[bump]];
+    cpi::create_pda_account(
+        purchaser,
+        receipt_info.clone(),
+        system_program,
+        PurchaseReceipt::SPACE,
+        seeds,
+    )?;
+
+    let mut data = receipt_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    receipt.try_serialize(&mut writer)?;
+
+    Ok(address)
+}
+
+// Creates a grant for a governed charter, whose authority can't sign
+// init_grant. The treasury, its deposit, and the grant's address,
+// ["grant", charter, nonce], are passed in the remaining accounts.
+fn init_grant_for_proposal<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    charter: Pubkey,
+    treasury: Pubkey,
+    recipient: Pubkey,
+    nonce: u64,
+    amount: u64,
+    vesting: Option<GrantVesting>,
+) -> Result<Pubkey> {
+    Grant::check_terms(amount, vesting)?;
+
+    let find = |key: Pubkey| {
+        remaining_accounts
+            .iter()
+            .find(|a| a.key() == key)
+            .ok_or(StrangemoodError::MissingProposalAccount)
+    };
+
+    let treasury_account = Account::<CharterTreasury>::try_from(find(treasury)?)?;
+    if treasury_account.charter != charter {
+        return Err(error!(StrangemoodError::CharterTreasuryHasUnexpectedCharter));
+    }
+    if treasury_account.version != ACCOUNT_VERSION {
+        return Err(error!(StrangemoodError::AccountNeedsMigration));
+    }
+
+    // The treasury's grant authority has to own the deposit, or the grant
+    // could never be claimed
+    let deposit = Account::<TokenAccount>::try_from(find(treasury_account.deposit)?)?;
+    let (grant_authority, _) = Pubkey::find_program_address(
+        &[b"grant_authority", treasury.as_ref()],
+        &crate::ID,
+    );
+    if deposit.owner != grant_authority {
+        return Err(error!(StrangemoodError::TokenAccountHasUnexpectedOwner));
+    }
+    if deposit.mint != treasury_account.mint {
+        return Err(error!(StrangemoodError::TokenAccountHasUnexpectedMint));
+    }
+
+    let nonce_bytes = nonce.to_le_bytes();
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"grant", charter.as_ref(), &nonce_bytes],
+        &crate::ID,
+    );
+    let grant_info = find(address)?;
+
+    let grant = Grant {
+        is_initialized: true,
+        charter,
+        treasury,
+        mint: treasury_account.mint,
+        recipient,
+        amount,
+        claimed: 0,
+        vesting,
+        nonce,
+        payer: payer.key(),
+    };
+
+    let seeds: &[&[u8]] = &[b"grant", charter.as_ref(), &nonce_bytes, &[bump]];
+    cpi::create_pda_account(payer, grant_info.clone(), system_program, Grant::SPACE, seeds)?;
+
+    let mut data = grant_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    grant.try_serialize(&mut writer)?;
+
+    emit!(events::GrantInitialized {
+        grant: address,
+        charter,
+        treasury,
+        mint: grant.mint,
+        recipient,
+        amount,
+        vesting,
+    });
+    Ok(address)
+}
+
+// Every purchase and trial entry point runs this, so a listing that's
+// unavailable or suspended can't take payment through any of them.
+fn check_purchasable(listing: &Listing, is_trial: bool) -> Result<()> {
+    if !listing.is_available {
+        return Err(error!(StrangemoodError::ListingIsUnavailable));
+    }
+    if listing.is_suspended {
+        return Err(error!(StrangemoodError::ListingIsSuspended));
+    }
+    if is_trial && !listing.is_refundable {
+        return Err(error!(StrangemoodError::ListingIsNotRefundable));
+    }
+    if is_trial && listing.refund_window == 0 {
+        return Err(error!(StrangemoodError::ListingHasNoRefundWindow));
+    }
+    if is_trial && listing.subscription_period != 0 {
+        return Err(error!(StrangemoodError::ListingIsSubscription));
+    }
+    Ok(())
+}
+
+// A listing that requires another listing, like DLC that requires its base
+// game, can only be bought by a purchaser whose inventory holds the parent
+// listing's token. The parent listing, and an inventory of the purchaser's
+// that holds its token, must be passed in the remaining accounts.
+fn check_requirement(
+    listing: &Listing,
+    remaining_accounts: &[AccountInfo],
+    purchaser: &Pubkey,
+) -> Result<()> {
+    let parent_key = match listing.requires {
+        Some(parent) => parent,
+        None => return Ok(()),
+    };
+
+    let parent_info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == parent_key)
+        .ok_or(StrangemoodError::MissingRequiredListing)?;
+    let parent = Account::<Listing>::try_from(parent_info)?;
+
+    let owns_parent = remaining_accounts.iter().any(|info| {
+        match Account::<TokenAccount>::try_from(info) {
+            Ok(inventory) => inventory.mint == parent.mint && inventory.owner == *purchaser && inventory.amount > 0,
+            Err(_) => false,
+        }
+    });
+    if !owns_parent {
+        return Err(error!(StrangemoodError::RequiredListingIsNotOwned));
+    }
+    Ok(())
+}
+
+// The price of "amount" listing tokens at "price" each. Buyers pass the most
+// they're willing to pay, so the lister can't raise the price between when
+// a buyer signs and when the transaction lands.
+fn total_price(price: u64, amount: u64, max_total_price: u64) -> Result<u64> {
+    let total = price
+        .checked_mul(amount)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+    if total > max_total_price {
+        return Err(error!(StrangemoodError::PriceExceedsMaximum));
+    }
+    Ok(total)
+}
+
+// Caps can't be raised, and can't be lowered below the rates that are
+// already set or queued.
+fn set_contribution_caps(charter: &mut Charter, max_payment_contribution: u64, max_vote_contribution: u64) -> Result<()> {
+    if max_payment_contribution > charter.max_payment_contribution.unwrap_or(math::BASIS_POINTS)
+        || max_vote_contribution > charter.max_vote_contribution.unwrap_or(math::BASIS_POINTS)
+    {
+        return Err(error!(StrangemoodError::ContributionCapIsInvalid));
+    }
+
+    charter.max_payment_contribution = Some(max_payment_contribution);
+    charter.max_vote_contribution = Some(max_vote_contribution);
+
+    charter.check_contributions(charter.payment_contribution, charter.vote_contribution)?;
+    if let Some(change) = charter.pending_change {
+        charter.check_contributions(change.payment_contribution, change.vote_contribution)?;
+    }
+    Ok(())
+}
+
+fn emit_charter_change(charter: Pubkey, change: PendingCharterChange) {
+    emit!(events::CharterChangeQueued {
+        charter,
+        expansion_rate: change.expansion_rate,
+        payment_contribution: change.payment_contribution,
+        vote_contribution: change.vote_contribution,
+        change_delay: change.change_delay,
+        effective_at: change.effective_at,
+    });
+}
+
+// Optionally protects a buyer from the charter raising its payment
+// contribution between when they sign and when the transaction lands.
+fn check_payment_contribution(charter: &Charter, max_payment_contribution: Option<u64>) -> Result<()> {
+    if let Some(max) = max_payment_contribution {
+        if charter.payment_contribution > max {
+            return Err(error!(StrangemoodError::ContributionExceedsMaximum));
+        }
+    }
+    Ok(())
+}
+
+// Lets a wallet that only holds SOL pay for a listing that's priced in
+// wrapped SOL. If the payment account is a wrapped SOL account without
+// enough in it, the difference is moved over from the purchaser's lamports
+// and synced, so the purchaser doesn't have to wrap SOL beforehand.
+fn fund_native_payment<'info>(
+    total: u64,
+    payment: &Account<'info, TokenAccount>,
+    purchaser: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    if !payment.is_native() || payment.amount >= total {
+        return Ok(());
+    }
+
+    let shortfall = total - payment.amount;
+    invoke(
+        &system_instruction::transfer(purchaser.key, &payment.key(), shortfall),
+        &[purchaser, payment.to_account_info(), system_program],
+    )?;
+
+    cpi::sync_native(&token_program, payment.to_account_info())
+}
+
+// Burns a trial's listing tokens out of the inventory, returns everything
+// in the escrow to the purchaser, and closes the escrow.
+//
+// Returns the amount that was refunded.
+fn refund_escrow<'info>(
+    quantity: u64,
+    token_program: &Program<'info, Token>,
+    listing_mint: &Account<'info, Mint>,
+    listing_mint_authority: &AccountInfo<'info>,
+    listing_mint_authority_bump: u8,
+    inventory: &Account<'info, TokenAccount>,
+    inventory_delegate: &AccountInfo<'info>,
+    inventory_delegate_bump: u8,
+    escrow: &Account<'info, TokenAccount>,
+    escrow_authority: &AccountInfo<'info>,
+    escrow_authority_bump: u8,
+    return_deposit: &Account<'info, TokenAccount>,
+    purchaser: &AccountInfo<'info>,
+) -> Result<u64> {
+    cpi::thaw_account(
+        token_program,
+        listing_mint,
+        inventory,
+        listing_mint_authority,
+        listing_mint_authority_bump,
+    )?;
+
+    cpi::burn(
+        token_program.to_account_info(),
+        listing_mint.to_account_info(),
+        inventory.to_account_info(),
+        inventory_delegate.clone(),
+        inventory_delegate_bump,
+        quantity,
+    )?;
+
+    cpi::freeze_account(
+        token_program,
+        listing_mint,
+        inventory,
+        listing_mint_authority,
+        listing_mint_authority_bump,
+    )?;
+
+    // Transfer all the funds in the escrow back to the user 
+    let refunded = escrow.amount;
+    token_transfer_with_seed(
+        token_program.to_account_info(),
+        escrow.to_account_info(),
+        return_deposit.to_account_info(),
+        escrow_authority.clone(),
+        refunded,
+        b"token_authority",
+        escrow_authority_bump
+    )?;
+
+    // Close the escrow
+    cpi::close_token_escrow_account(
+        token_program.to_account_info(),
+        escrow.to_account_info(),
+        purchaser.clone(),
+        escrow_authority.clone(),
+        escrow_authority_bump
+    )?;
+
+    Ok(refunded)
+}
+
+// Mints a gift's listing tokens into "inventory", whose owner approves the
+// inventory's delegate like a purchase does, and freezes it again.
+fn deliver_gift<'info>(
+    quantity: u64,
+    token_program: &Program<'info, Token>,
+    listing_mint: &Account<'info, Mint>,
+    listing_mint_authority: &AccountInfo<'info>,
+    listing_mint_authority_bump: u8,
+    inventory: &mut Account<'info, TokenAccount>,
+    inventory_delegate: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+) -> Result<()> {
+    cpi::thaw_account(
+        token_program,
+        listing_mint,
+        inventory,
+        listing_mint_authority,
+        listing_mint_authority_bump,
+    )?;
+
+    let delegated_amount = inventory
+        .amount
+        .checked_add(quantity)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+    cpi::approve_delegate(
+        token_program.to_account_info(),
+        inventory.to_account_info(),
+        inventory_delegate.clone(),
+        owner.clone(),
+        delegated_amount,
+    )?;
+
+    mint_to(
+        token_program,
+        listing_mint,
+        inventory,
+        listing_mint_authority,
+        listing_mint_authority_bump,
+        quantity,
+    )?;
+
+    // The inventory was thawed above, so reload it, or
+    // freeze_account would think it's still frozen.
+    inventory.reload()?;
+    cpi::freeze_account(
+        token_program,
+        listing_mint,
+        inventory,
+        listing_mint_authority,
+        listing_mint_authority_bump,
+    )
+}
+
+// Counts "amount" listing tokens against the listing's max_supply and max_per_wallet.
+//
+// If the listing has a max_per_wallet, the purchaser's WalletPurchases PDA,
+// ["wallet_purchases", listing, purchaser], must be passed in the remaining
+// accounts. It's created the first time the purchaser buys the listing.
+fn record_sale<'info>(
+    listing: &mut Account<'info, Listing>,
+    amount: u64,
+    remaining_accounts: &[AccountInfo<'info>],
+    purchaser: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    let supply = listing
+        .supply
+        .checked_add(amount)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+    if listing.max_supply != 0 && supply > listing.max_supply {
+        return Err(error!(StrangemoodError::ListingIsSoldOut));
+    }
+    listing.supply = supply;
+
+    if listing.max_per_wallet == 0 {
+        return Ok(());
+    }
+
+    let listing_key = listing.key();
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"wallet_purchases", listing_key.as_ref(), purchaser.key.as_ref()],
+        &crate::ID,
+    );
+    let wallet_info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == address)
+        .ok_or(StrangemoodError::MissingWalletPurchases)?;
+
+    let mut wallet = if wallet_info.owner != &crate::ID {
+        let seeds: &[&[u8]] = &[b"wallet_purchases", listing_key.as_ref(), purchaser.key.as_ref(), &[bump]];
+        cpi::create_pda_account(
+            purchaser.clone(),
+            wallet_info.clone(),
+            system_program,
+            WalletPurchases::SPACE,
+            seeds,
+        )?;
+        WalletPurchases {
+            is_initialized: true,
+            listing: listing_key,
+            purchaser: purchaser.key(),
+            quantity: 0,
+        }
+    } else {
+        WalletPurchases::try_deserialize(&mut &wallet_info.try_borrow_data()?[..])?
+    };
+
+    wallet.quantity = wallet
+        .quantity
+        .checked_add(amount)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+    if wallet.quantity > listing.max_per_wallet {
+        return Err(error!(StrangemoodError::WalletLimitExceeded));
+    }
+
+    let mut data = wallet_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    wallet.try_serialize(&mut writer)
+}
+
+// Finds an inventory's Subscription, ["subscription", inventory], in the
+// remaining accounts. It doesn't exist yet if this is the first purchase.
+fn find_subscription<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    inventory: &Pubkey,
+) -> Result<(&'a AccountInfo<'info>, u8)> {
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"subscription", inventory.as_ref()],
+        &crate::ID,
+    );
+    let info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == address)
+        .ok_or(StrangemoodError::MissingSubscription)?;
+    Ok((info, bump))
+}
+
+// Renewing a subscription that already exists costs the renewal price.
+fn subscription_price(
+    listing: &Listing,
+    price: u64,
+    remaining_accounts: &[AccountInfo],
+    inventory: &Pubkey,
+) -> Result<u64> {
+    if listing.subscription_period == 0 {
+        return Ok(price);
+    }
+
+    let (info, _) = find_subscription(remaining_accounts, inventory)?;
+    if info.owner == &crate::ID && listing.renewal_price != 0 {
+        return Ok(listing.renewal_price);
+    }
+    Ok(price)
+}
+
+// Adds a period to the inventory's Subscription for each listing token
+// bought, creating the Subscription on the first purchase.
+fn record_subscription<'info>(
+    listing: &Account<'info, Listing>,
+    inventory: Pubkey,
+    amount: u64,
+    remaining_accounts: &[AccountInfo<'info>],
+    purchaser: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    if listing.subscription_period == 0 {
+        return Ok(());
+    }
+
+    let (info, bump) = find_subscription(remaining_accounts, &inventory)?;
+    let mut subscription = if info.owner != &crate::ID {
+        let seeds: &[&[u8]] = &[b"subscription", inventory.as_ref(), &[bump]];
+        cpi::create_pda_account(
+            purchaser.clone(),
+            info.clone(),
+            system_program,
+            Subscription::SPACE,
+            seeds,
+        )?;
+        Subscription {
+            is_initialized: true,
+            listing: listing.key(),
+            inventory,
+            purchaser: purchaser.key(),
+            expires_at: 0,
+        }
+    } else {
+        Subscription::try_deserialize(&mut &info.try_borrow_data()?[..])?
+    };
+    if subscription.listing != listing.key() {
+        return Err(error!(StrangemoodError::SubscriptionHasUnexpectedListing));
+    }
+
+    subscription.extend(Clock::get()?.unix_timestamp, listing.subscription_period, amount)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    subscription.try_serialize(&mut writer)?;
+
+    emit!(events::SubscriptionExtended {
+        subscription: info.key(),
+        listing: listing.key(),
+        inventory,
+        purchaser: subscription.purchaser,
+        expires_at: subscription.expires_at,
+    });
+    Ok(())
+}
+
+// Applies a coupon to the price of 1 listing token, and counts a use for
+// each of the amount listing tokens bought at that price.
+//
+// The coupon account is found in the remaining accounts by its address,
+// so it can sit alongside a PurchaseReceipt or WalletPurchases.
+fn redeem_coupon<'info>(
+    listing: &Pubkey,
+    coupon: Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+    purchaser: &Pubkey,
+    price: u64,
+    amount: u64,
+    now: i64,
+) -> Result<u64> {
+    let coupon_info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == coupon)
+        .ok_or(StrangemoodError::MissingCoupon)?;
+    if coupon_info.owner != &crate::ID {
+        return Err(error!(anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram));
+    }
+
+    let mut account = Coupon::try_deserialize(&mut &coupon_info.try_borrow_data()?[..])?;
+    if account.listing != *listing {
+        return Err(error!(StrangemoodError::CouponHasUnexpectedListing));
+    }
+    if let Some(bound) = account.purchaser {
+        if bound != *purchaser {
+            return Err(error!(StrangemoodError::CouponHasUnexpectedPurchaser));
+        }
+    }
+    if account.expires_at != 0 && now > account.expires_at {
+        return Err(error!(StrangemoodError::CouponHasExpired));
+    }
+    let uses = account
+        .uses
+        .checked_add(amount)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+    if account.max_uses != 0 && uses > account.max_uses {
+        return Err(error!(StrangemoodError::CouponIsUsedUp));
+    }
+
+    account.uses = uses;
+    let discounted = account.discounted_price(price)?;
+
+    let mut data = coupon_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    account.try_serialize(&mut writer)?;
+
+    emit!(events::CouponRedeemed {
+        coupon,
+        listing: *listing,
+        purchaser: *purchaser,
+        uses: account.uses,
+    });
+
+    Ok(discounted)
+}
+
+// Gives a refunded trial's tokens back to the listing's supply, and to
+// the purchaser's WalletPurchases if it's passed in the remaining accounts.
+fn record_refund<'info>(
+    listing: &mut Account<'info, Listing>,
+    quantity: u64,
+    remaining_accounts: &[AccountInfo<'info>],
+    purchaser: &Pubkey,
+) -> Result<()> {
+    listing.supply = listing.supply.saturating_sub(quantity);
+
+    let (address, _) = Pubkey::find_program_address(
+        &[b"wallet_purchases", listing.key().as_ref(), purchaser.as_ref()],
+        &crate::ID,
+    );
+    let wallet_info = match remaining_accounts.iter().find(|a| a.key() == address) {
+        Some(info) if info.owner == &crate::ID => info,
+        _ => return Ok(()),
+    };
+
+    let mut wallet = WalletPurchases::try_deserialize(&mut &wallet_info.try_borrow_data()?[..])?;
+    wallet.quantity = wallet.quantity.saturating_sub(quantity);
+
+    let mut data = wallet_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data[..];
+    wallet.try_serialize(&mut writer)
+}
+
+// The token accounts that share the lister's part of a sale, with their
+// shares in basis points. Without splits, that's just the listing's
+// payment_deposit.
+//
+// With splits, the listing's ListingSplits, ["listing_splits", listing],
+// and each recipient's token account must be passed in the remaining accounts.
+fn lister_deposits<'info>(
+    listing_key: Pubkey,
+    listing: &Listing,
+    listing_deposit: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<Vec<(AccountInfo<'info>, u64)>> {
+    if !listing.has_splits {
+        return Ok(vec![(listing_deposit, math::BASIS_POINTS)]);
+    }
+
+    let (address, _) = Pubkey::find_program_address(
+        &[b"listing_splits", listing_key.as_ref()],
+        &crate::ID,
+    );
+    let splits_info = remaining_accounts
+        .iter()
+        .find(|a| a.key() == address && a.owner == &crate::ID)
+        .ok_or(StrangemoodError::MissingListingSplits)?;
+    let splits = ListingSplits::try_deserialize(&mut &splits_info.try_borrow_data()?[..])?;
+
+    let mut deposits = Vec::with_capacity(splits.recipients.len());
+    for recipient in splits.recipients.iter() {
+        let deposit = remaining_accounts
+            .iter()
+            .find(|a| a.key() == recipient.deposit)
+            .ok_or(StrangemoodError::MissingSplitRecipient)?;
+        deposits.push((deposit.clone(), recipient.share));
+    }
+    Ok(deposits)
+}
+
+// A listing's split recipients are checked when they're set, so that a
+// sale can't fail later because of a bad recipient. Each recipient's token
+// account is passed in the remaining accounts, in the listing's payment mint.
+fn check_split_recipients<'info>(
+    recipients: &[SplitRecipient],
+    remaining_accounts: &[AccountInfo<'info>],
+    payment_mint: Pubkey,
+) -> Result<()> {
+    if recipients.is_empty() || recipients.len() > ListingSplits::MAX_RECIPIENTS {
+        return Err(error!(StrangemoodError::ListingSplitsAreInvalid));
+    }
+
+    let mut total: u64 = 0;
+    for recipient in recipients {
+        total = total
+            .checked_add(recipient.share)
+            .ok_or(StrangemoodError::ListingSplitsAreInvalid)?;
+
+        let info = remaining_accounts
+            .iter()
+            .find(|a| a.key() == recipient.deposit)
+            .ok_or(StrangemoodError::MissingSplitRecipient)?;
+        let deposit = Account::<TokenAccount>::try_from(info)?;
+        if deposit.mint != payment_mint {
+            return Err(error!(StrangemoodError::TokenAccountHasUnexpectedMint));
+        }
+    }
+    if total != math::BASIS_POINTS {
+        return Err(error!(StrangemoodError::ListingSplitsAreInvalid));
+    }
+    Ok(())
+}
+
+// One listing's accounts in a bundle purchase, passed in the remaining
+// accounts in this order, once for each of the bundle's listings:
+//
+// listing, listing_mint, listing_mint_authority, listings_payment_deposit,
+// listings_vote_deposit, inventory, inventory_delegate
+struct BundleItem<'info> {
+    listing: Account<'info, Listing>,
+    listing_mint: Account<'info, Mint>,
+    listing_mint_authority: AccountInfo<'info>,
+    listing_mint_authority_bump: u8,
+    listings_payment_deposit: Account<'info, TokenAccount>,
+    listings_vote_deposit: Account<'info, TokenAccount>,
+    inventory: Account<'info, TokenAccount>,
+    inventory_delegate: AccountInfo<'info>,
+}
+
+impl<'info> BundleItem<'info> {
+    const ACCOUNTS: usize = 7;
+
+    // Checks the same things that the Purchase accounts struct does.
+    fn load(
+        accounts: &[AccountInfo<'info>],
+        listing_key: Pubkey,
+        charter: &Account<'info, Charter>,
+        payment_mint: Pubkey,
+        listing_mint_authority_bump: u8,
+        inventory_delegate_bump: u8,
+    ) -> Result<BundleItem<'info>> {
+        if accounts[0].key() != listing_key {
+            return Err(error!(StrangemoodError::BundleHasUnexpectedListing));
+        }
+        let listing = Account::<Listing>::try_from(&accounts[0])?;
+        if listing.charter != charter.key() {
+            return Err(error!(StrangemoodError::ListingHasUnexpectedCharter));
+        }
+        if listing.version != ACCOUNT_VERSION {
+            return Err(error!(StrangemoodError::AccountNeedsMigration));
+        }
+
+        let listing_mint = Account::<Mint>::try_from(&accounts[1])?;
+        if listing_mint.key() != listing.mint {
+            return Err(error!(StrangemoodError::ListingHasUnexpectedMint));
+        }
+        let listing_mint_authority = accounts[2].clone();
+        check_pda(
+            &[b"mint_authority", listing_mint.key().as_ref(), &[listing_mint_authority_bump]],
+            &listing_mint_authority,
+        )?;
+
+        let listings_payment_deposit = Account::<TokenAccount>::try_from(&accounts[3])?;
+        let listings_vote_deposit = Account::<TokenAccount>::try_from(&accounts[4])?;
+        if listings_payment_deposit.key() != listing.payment_deposit
+            || listings_vote_deposit.key() != listing.vote_deposit
+        {
+            return Err(error!(StrangemoodError::ListingHasUnexpectedDeposit));
+        }
+        if listings_payment_deposit.mint != payment_mint {
+            return Err(error!(StrangemoodError::TokenAccountHasUnexpectedMint));
+        }
+
+        let inventory = Account::<TokenAccount>::try_from(&accounts[5])?;
+        let inventory_delegate = accounts[6].clone();
+        check_pda(
+            &[b"token_authority", inventory.key().as_ref(), &[inventory_delegate_bump]],
+            &inventory_delegate,
+        )?;
+
+        Ok(BundleItem {
+            listing,
+            listing_mint,
+            listing_mint_authority,
+            listing_mint_authority_bump,
+            listings_payment_deposit,
+            listings_vote_deposit,
+            inventory,
+            inventory_delegate,
+        })
+    }
+}
+
+// Like the "seeds" and "bump" constraints, for accounts
+// that aren't in an accounts struct.
+fn check_pda(seeds: &[&[u8]], account: &AccountInfo) -> Result<()> {
+    let address = Pubkey::create_program_address(seeds, &crate::ID)
+        .map_err(|_| error!(anchor_lang::error::ErrorCode::ConstraintSeeds))?;
+    if address != account.key() {
+        return Err(error!(anchor_lang::error::ErrorCode::ConstraintSeeds));
+    }
     Ok(())
 }
 
+// The voting tokens minted for a sale
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct VoteSplits {
+    pub to_lister_amount: u64,
+    pub to_charter_amount: u64,
+}
 
-struct Splits { 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct Splits { 
     pub to_charter_amount: u64,
     pub to_lister_amount: u64,
 }
 
+// Pays "amount" to the lister, or to the listing's split recipients, by
+// their shares. Without a bump, the authority signs the transaction, like
+// a purchaser. With one, the authority is the PDA ["token_authority", from]
+// that controls an escrow, and the program signs for it.
+fn pay_lister_deposits<'info>(
+    amount: u64,
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
+    authority: AccountInfo<'info>,
+    bump: Option<u8>,
+) -> Result<()> {
+    let shares: Vec<u64> = lister_deposits.iter().map(|(_, share)| *share).collect();
+    let amounts = math::split_shares(amount, &shares)?;
+    for ((deposit, _), amount) in lister_deposits.iter().zip(amounts) {
+        match bump {
+            Some(bump) => token_transfer_with_seed(
+                token_program.to_account_info(),
+                from.to_account_info(),
+                deposit.clone(),
+                authority.clone(),
+                amount,
+                b"token_authority",
+                bump,
+            )?,
+            None => token_transfer(
+                token_program.to_account_info(),
+                from.to_account_info(),
+                deposit.clone(),
+                authority.clone(),
+                amount,
+            )?,
+        }
+    }
+    Ok(())
+}
+
 fn transfer_funds<'info>(
     total: u64,
     listing: &Listing,
@@ -65,13 +889,10 @@ fn transfer_funds<'info>(
     token_program: Program<'info, Token>,
     from: Account<'info, TokenAccount>,
     charter_deposit: Account<'info, TokenAccount>,
-    listing_deposit: Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
     purchaser: Signer<'info>,
 ) -> Result<Splits> {
-    let deposit_rate = 1.0 - charter.payment_contribution;
-    let total_as_f64 = total as f64;
-    let to_lister_amount = (deposit_rate * total_as_f64) as u64;
-    let to_charter_amount = total.checked_sub(to_lister_amount).unwrap();
+    let (to_lister_amount, to_charter_amount) = math::split(total, charter.payment_contribution)?;
 
     // Distribute payment to the charter
     token_transfer( 
@@ -82,19 +903,21 @@ fn transfer_funds<'info>(
         to_charter_amount,
     )?;
 
-    // Distribute payment to the lister
-    token_transfer(
-    token_program.to_account_info(),
-    from.to_account_info(),
-        listing_deposit.to_account_info(),
-        purchaser.to_account_info(),
+    // Distribute payment to the lister, or to the listing's split recipients
+    pay_lister_deposits(
         to_lister_amount,
+        &token_program,
+        &from,
+        lister_deposits,
+        purchaser.to_account_info(),
+        None,
     )?;
     
     Ok(Splits { to_charter_amount, to_lister_amount })
 }
 
-struct SplitsWithCashier { 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SplitsWithCashier { 
     pub to_charter_amount: u64,
     pub to_lister_amount: u64,
     pub to_cashier_amount: u64,
@@ -107,21 +930,15 @@ fn transfer_funds_with_cashier<'info>(
     token_program: Program<'info, Token>,
     from: Account<'info, TokenAccount>,
     charter_deposit: Account<'info, TokenAccount>,
-    listing_deposit: Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
     cashier_deposit: Account<'info, TokenAccount>,
     purchaser: Signer<'info>,
 ) -> Result<SplitsWithCashier> {
-    let deposit_rate = 1.0 - charter.payment_contribution;
-    let total_as_f64 = total as f64;
-    let deposit_amount = (deposit_rate * total_as_f64 as f64) as u64;
-    let to_charter_amount = total.checked_sub(deposit_amount).unwrap();
+    let (deposit_amount, to_charter_amount) = math::split(total, charter.payment_contribution)?;
 
     // Then split the deposit pool between the lister, and the cashier.
     // (charter, (lister, cashier))
-    let to_cashier_rate = listing.cashier_split;
-    let to_lister_rate = 1.0 - to_cashier_rate;
-    let to_lister_amount = (deposit_amount as f64 * to_lister_rate) as u64;
-    let to_cashier_amount = deposit_amount.checked_sub(to_lister_amount).unwrap();
+    let (to_lister_amount, to_cashier_amount) = math::split(deposit_amount, listing.cashier_split)?;
 
     // Distribute payment to the charter
     token_transfer( 
@@ -132,13 +949,14 @@ fn transfer_funds_with_cashier<'info>(
         to_charter_amount,
     )?;
 
-    // Distribute payment to the lister
-    token_transfer(
-    token_program.to_account_info(),
-    from.to_account_info(),
-        listing_deposit.to_account_info(),
-        purchaser.to_account_info(),
+    // Distribute payment to the lister, or to the listing's split recipients
+    pay_lister_deposits(
         to_lister_amount,
+        &token_program,
+        &from,
+        lister_deposits,
+        purchaser.to_account_info(),
+        None,
     )?;
 
         // Distribute payment to cashier
@@ -162,22 +980,16 @@ fn transfer_funds_from_escrow_with_cashier<'info>(
     token_program: Program<'info, Token>,
     from: Account<'info, TokenAccount>,
     charter_deposit: Account<'info, TokenAccount>,
-    listing_deposit: Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
     cashier_deposit: Account<'info, TokenAccount>,
     authority: AccountInfo<'info>,
     bump: u8,
 ) -> Result<SplitsWithCashier> {
-    let deposit_rate = 1.0 - charter.payment_contribution;
-    let total_as_f64 = total as f64;
-    let deposit_amount = (deposit_rate * total_as_f64 as f64) as u64;
-    let to_charter_amount = total.checked_sub(deposit_amount).unwrap();
+    let (deposit_amount, to_charter_amount) = math::split(total, charter.payment_contribution)?;
 
     // Then split the deposit pool between the lister, and the cashier.
     // (charter, (lister, cashier))
-    let to_cashier_rate = listing.cashier_split;
-    let to_lister_rate = 1.0 - to_cashier_rate;
-    let to_lister_amount = (deposit_amount as f64 * to_lister_rate) as u64;
-    let to_cashier_amount = deposit_amount.checked_sub(to_lister_amount).unwrap();
+    let (to_lister_amount, to_cashier_amount) = math::split(deposit_amount, listing.cashier_split)?;
 
     // Distribute payment to the charter
     token_transfer_with_seed( 
@@ -190,15 +1002,14 @@ fn transfer_funds_from_escrow_with_cashier<'info>(
         bump
     )?;
 
-    // Distribute payment to the lister
-    token_transfer_with_seed(
-    token_program.to_account_info(),
-    from.to_account_info(),
-        listing_deposit.to_account_info(),
-        authority.to_account_info(),
+    // Distribute payment to the lister, or to the listing's split recipients
+    pay_lister_deposits(
         to_lister_amount,
-        b"token_authority",
-        bump
+        &token_program,
+        &from,
+        lister_deposits,
+        authority.to_account_info(),
+        Some(bump),
     )?;
 
     // Distribute payment to cashier
@@ -223,14 +1034,11 @@ fn transfer_funds_from_escrow<'info>(
     token_program: Program<'info, Token>,
     from: Account<'info, TokenAccount>,
     charter_deposit: Account<'info, TokenAccount>,
-    listing_deposit: Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
     authority: AccountInfo<'info>,
     bump: u8,
 ) -> Result<Splits> {
-    let deposit_rate = 1.0 - charter.payment_contribution;
-    let total_as_f64 = total as f64;
-    let to_lister_amount = (deposit_rate * total_as_f64 as f64) as u64;
-    let to_charter_amount = total.checked_sub(to_lister_amount).unwrap();
+    let (to_lister_amount, to_charter_amount) = math::split(total, charter.payment_contribution)?;
 
     // Distribute payment to the charter
     token_transfer_with_seed( 
@@ -243,28 +1051,85 @@ fn transfer_funds_from_escrow<'info>(
         bump
     )?;
 
-    // Distribute payment to the lister
-    token_transfer_with_seed(
-    token_program.to_account_info(),
-    from.to_account_info(),
-        listing_deposit.to_account_info(),
-        authority.to_account_info(),
+    // Distribute payment to the lister, or to the listing's split recipients
+    pay_lister_deposits(
         to_lister_amount,
-        b"token_authority",
-        bump
+        &token_program,
+        &from,
+        lister_deposits,
+        authority.to_account_info(),
+        Some(bump),
     )?;
 
     Ok(Splits { to_charter_amount, to_lister_amount })
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SplitsWithSeller {
+    pub to_charter_amount: u64,
+    pub to_lister_amount: u64,
+    pub to_seller_amount: u64,
+}
+
+// Like transfer_funds_with_cashier, but for resales, where the lister's
+// royalty comes out of what the seller would otherwise get.
+fn transfer_funds_with_seller<'info>(
+    total: u64,
+    listing: &Listing,
+    charter: &Charter,
+    token_program: Program<'info, Token>,
+    from: Account<'info, TokenAccount>,
+    charter_deposit: Account<'info, TokenAccount>,
+    lister_deposits: &[(AccountInfo<'info>, u64)],
+    seller_deposit: Account<'info, TokenAccount>,
+    purchaser: Signer<'info>,
+) -> Result<SplitsWithSeller> {
+    let (deposit_amount, to_charter_amount) = math::split(total, charter.payment_contribution)?;
+
+    // Then split the deposit pool between the seller, and the lister's royalty.
+    // (charter, (seller, lister))
+    let (to_seller_amount, to_lister_amount) = math::split(deposit_amount, listing.resale_royalty)?;
+
+    // Distribute payment to the charter
+    token_transfer(
+        token_program.to_account_info(),
+        from.to_account_info(),
+        charter_deposit.to_account_info(),
+        purchaser.to_account_info(),
+        to_charter_amount,
+    )?;
+
+    // Distribute the royalty to the lister, or to the listing's split recipients
+    pay_lister_deposits(
+        to_lister_amount,
+        &token_program,
+        &from,
+        lister_deposits,
+        purchaser.to_account_info(),
+        None,
+    )?;
+
+    // Distribute payment to the seller
+    token_transfer(
+        token_program.to_account_info(),
+        from.to_account_info(),
+        seller_deposit.to_account_info(),
+        purchaser.to_account_info(),
+        to_seller_amount,
+    )?;
+
+    Ok(SplitsWithSeller { to_charter_amount, to_lister_amount, to_seller_amount })
+}
+
 
 #[program]
 pub mod strangemood {
     use anchor_lang::{prelude::Context, solana_program::{program_option::COption}};
 
-    use crate::{error::StrangemoodError, cpi::{token_transfer, mint_to_and_freeze, token_transfer_with_seed, burn, close_token_escrow_account, close_native_account, approve_delegate, thaw_account, freeze_account}};
+    use crate::{error::StrangemoodError, cpi::{token_transfer, mint_to_and_freeze, token_transfer_with_seed, burn, close_token_escrow_account, close_native_account, approve_delegate, revoke_delegate, thaw_account, freeze_account}};
 
     use super::*;
+    use crate::events::*;
 
     pub fn init_listing(
         ctx: Context<InitListing>,
@@ -274,12 +1139,18 @@ pub mod strangemood {
         refundable: bool,
         consumable: bool,
         available: bool,
-        cashier_split: f64,
+        cashier_split: u64,
+        refund_window: i64,
+        max_supply: u64,
+        max_per_wallet: u64,
         uri: String,
     ) -> Result<()> {
-        if cashier_split < 0.0 || cashier_split > 1.0 {
+        if cashier_split > math::BASIS_POINTS {
             return Err(error!(StrangemoodError::CashierSplitIsInvalid));
         }
+        if refund_window < 0 {
+            return Err(error!(StrangemoodError::RefundWindowIsInvalid));
+        }
 
         let listing = &mut ctx.accounts.listing;
         listing.is_initialized = true;
@@ -295,43 +1166,113 @@ pub mod strangemood {
         listing.is_available = available;
         listing.is_suspended = false;
         listing.cashier_split = cashier_split;
+        listing.refund_window = refund_window;
+        listing.max_supply = max_supply;
+        listing.max_per_wallet = max_per_wallet;
+        listing.supply = 0;
+        listing.version = ACCOUNT_VERSION;
+        listing.legacy_trials_until = 0;
+
+        emit!(ListingInitialized {
+            listing: listing.key(),
+            charter: listing.charter,
+            authority: listing.authority,
+            mint: listing.mint,
+            payment_deposit: listing.payment_deposit,
+            vote_deposit: listing.vote_deposit,
+            price: listing.price,
+            is_refundable: listing.is_refundable,
+            is_consumable: listing.is_consumable,
+            is_available: listing.is_available,
+            cashier_split: listing.cashier_split,
+            refund_window: listing.refund_window,
+            max_supply: listing.max_supply,
+            max_per_wallet: listing.max_per_wallet,
+            uri: listing.uri.clone(),
+        });
 
         Ok(())
     }
 
-    pub fn purchase(ctx: Context<Purchase>,   
+    // If receipt_nonce is set, a PurchaseReceipt is written to the PDA
+    // ["purchase_receipt", listing, purchaser, receipt_nonce], which must be
+    // passed in the remaining accounts.
+    //
+    // If coupon is set, that Coupon must also be passed in the remaining
+    // accounts, and its discount applies to every token in the purchase.
+    pub fn purchase<'info>(ctx: Context<'_, '_, '_, 'info, Purchase<'info>>,   
         listing_mint_authority_bump: u8,
         charter_mint_authority_bump: u8,
         _inventory_delegate_bump: u8,
-        amount: u64
+        amount: u64,
+        max_total_price: u64,
+        max_payment_contribution: Option<u64>,
+        receipt_nonce: Option<u64>,
+        coupon: Option<Pubkey>,
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
-        let charter = ctx.accounts.charter.clone().into_inner();
+        // Rates that are queued, but haven't taken effect, don't apply yet
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
+
+        check_purchasable(&listing, false)?;
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.purchaser.key())?;
+        check_payment_contribution(&charter, max_payment_contribution)?;
+        record_sale(
+            &mut ctx.accounts.listing,
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
 
-        if !listing.is_available {
-            return Err(StrangemoodError::ListingIsUnavailable.into());
+        // Distribute payment
+        let now = Clock::get()?.unix_timestamp;
+        let mut price = subscription_price(
+            &listing,
+            listing.price_at(now),
+            ctx.remaining_accounts,
+            &ctx.accounts.inventory.key(),
+        )?;
+        if let Some(coupon) = coupon {
+            price = redeem_coupon(
+                &ctx.accounts.listing.key(),
+                coupon,
+                ctx.remaining_accounts,
+                &ctx.accounts.purchaser.key(),
+                price,
+                amount,
+                now,
+            )?;
         }
-        if listing.is_suspended {
-            return Err(StrangemoodError::ListingIsSuspended.into());
-        }
-
-        // Distribute payment
-        let total: u64 = listing.price.checked_mul(amount).unwrap();
+        let total = total_price(price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
         let splits = transfer_funds(total,
             &listing,
             &charter,
             ctx.accounts.token_program.clone(),
             *ctx.accounts.payment.clone(),
             *ctx.accounts.charter_treasury_deposit.clone(),
-            *ctx.accounts.listings_payment_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
             ctx.accounts.purchaser.clone()
         )?;
 
         // Distribute votes 
         let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
-        distribute_governance_tokens(
+        let votes = distribute_governance_tokens(
             splits.to_charter_amount,
-            charter.expansion_rate / charter_treasury.scalar,
+            charter.expansion_rate,
+            charter_treasury.scalar,
             charter.vote_contribution,
              ctx.accounts.token_program.clone(),
              *ctx.accounts.charter_mint.clone(),
@@ -378,43 +1319,129 @@ pub mod strangemood {
             listing_mint_authority_bump
         )?;
 
+        record_subscription(
+            &ctx.accounts.listing,
+            ctx.accounts.inventory.key(),
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        // Write a durable receipt, if the purchaser asked for one.
+        let receipt = match receipt_nonce {
+            Some(nonce) => {
+                let clock = Clock::get()?;
+                Some(write_purchase_receipt(
+                    ctx.remaining_accounts,
+                    ctx.accounts.purchaser.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    PurchaseReceipt {
+                        is_initialized: true,
+                        listing: ctx.accounts.listing.key(),
+                        inventory: ctx.accounts.inventory.key(),
+                        purchaser: ctx.accounts.purchaser.key(),
+                        cashier: None,
+                        quantity: amount,
+                        price,
+                        slot: clock.slot,
+                        timestamp: clock.unix_timestamp,
+                        nonce,
+                    },
+                )?)
+            }
+            None => None,
+        };
+
+        emit!(Purchased {
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            purchaser: ctx.accounts.purchaser.key(),
+            inventory: ctx.accounts.inventory.key(),
+            receipt,
+            quantity: amount,
+            price,
+            splits,
+            votes,
+        });
+
         Ok(())
     }
 
-    pub fn purchase_with_cashier(ctx: Context<PurchaseWithCashier>,   
+    // See purchase for how receipt_nonce and coupon are used.
+    pub fn purchase_with_cashier<'info>(ctx: Context<'_, '_, '_, 'info, PurchaseWithCashier<'info>>,   
         listing_mint_authority_bump: u8,
         charter_mint_authority_bump: u8,
         _inventory_delegate_bump: u8,
-        amount: u64
+        amount: u64,
+        max_total_price: u64,
+        max_payment_contribution: Option<u64>,
+        receipt_nonce: Option<u64>,
+        coupon: Option<Pubkey>,
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
-        let charter = ctx.accounts.charter.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
 
-        if !listing.is_available {
-            return Err(StrangemoodError::ListingIsUnavailable.into());
-        }
-        if listing.is_suspended {
-            return Err(StrangemoodError::ListingIsSuspended.into());
-        }
+        check_purchasable(&listing, false)?;
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.purchaser.key())?;
+        check_payment_contribution(&charter, max_payment_contribution)?;
+        record_sale(
+            &mut ctx.accounts.listing,
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
 
         // Distribute payment
-        let total: u64 = listing.price.checked_mul(amount).unwrap();
+        let now = Clock::get()?.unix_timestamp;
+        let mut price = subscription_price(
+            &listing,
+            listing.price_at(now),
+            ctx.remaining_accounts,
+            &ctx.accounts.inventory.key(),
+        )?;
+        if let Some(coupon) = coupon {
+            price = redeem_coupon(
+                &ctx.accounts.listing.key(),
+                coupon,
+                ctx.remaining_accounts,
+                &ctx.accounts.purchaser.key(),
+                price,
+                amount,
+                now,
+            )?;
+        }
+        let total = total_price(price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
         let splits = transfer_funds_with_cashier(total,
             &listing,
             &charter,
             ctx.accounts.token_program.clone(),
             *ctx.accounts.payment.clone(),
             *ctx.accounts.charter_treasury_deposit.clone(),
-            *ctx.accounts.listings_payment_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
             *ctx.accounts.cashier_treasury_escrow.clone(),
             ctx.accounts.purchaser.clone()
         )?;
 
         // Distribute votes 
         let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
-        distribute_governance_tokens(
+        let votes = distribute_governance_tokens(
             splits.to_charter_amount,
-            charter.expansion_rate / charter_treasury.scalar,
+            charter.expansion_rate,
+            charter_treasury.scalar,
             charter.vote_contribution,
                 ctx.accounts.token_program.clone(),
              *ctx.accounts.charter_mint.clone(),
@@ -463,30 +1490,263 @@ pub mod strangemood {
             listing_mint_authority_bump
         )?;
 
+        record_subscription(
+            &ctx.accounts.listing,
+            ctx.accounts.inventory.key(),
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        // Write a durable receipt, if the purchaser asked for one.
+        let receipt = match receipt_nonce {
+            Some(nonce) => {
+                let clock = Clock::get()?;
+                Some(write_purchase_receipt(
+                    ctx.remaining_accounts,
+                    ctx.accounts.purchaser.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    PurchaseReceipt {
+                        is_initialized: true,
+                        listing: ctx.accounts.listing.key(),
+                        inventory: ctx.accounts.inventory.key(),
+                        purchaser: ctx.accounts.purchaser.key(),
+                        cashier: Some(ctx.accounts.cashier.key()),
+                        quantity: amount,
+                        price,
+                        slot: clock.slot,
+                        timestamp: clock.unix_timestamp,
+                        nonce,
+                    },
+                )?)
+            }
+            None => None,
+        };
+
+        emit!(PurchasedWithCashier {
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            cashier: ctx.accounts.cashier.key(),
+            purchaser: ctx.accounts.purchaser.key(),
+            inventory: ctx.accounts.inventory.key(),
+            receipt,
+            quantity: amount,
+            price,
+            splits,
+            votes,
+        });
+
+        Ok(())
+    }
+
+    // Buys "amount" of every listing in a bundle, at the bundle's price.
+    //
+    // Each listing's accounts are passed after the instruction's other
+    // accounts, in the bundle's order (see BundleItem). Any WalletPurchases,
+    // ListingSplits, or split recipients that the listings need come after those.
+    //
+    // The payment is split between the listings in proportion to the
+    // bundle's weights, and then each listing's part is paid out like a purchase.
+    pub fn purchase_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, PurchaseBundle<'info>>,
+        charter_mint_authority_bump: u8,
+        listing_mint_authority_bumps: Vec<u8>,
+        inventory_delegate_bumps: Vec<u8>,
+        amount: u64,
+        max_total_price: u64,
+        max_payment_contribution: Option<u64>,
+    ) -> Result<()> {
+        let bundle = ctx.accounts.bundle.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
+
+        if !bundle.is_available {
+            return Err(error!(StrangemoodError::BundleIsUnavailable));
+        }
+        check_payment_contribution(&charter, max_payment_contribution)?;
+
+        let count = bundle.listings.len();
+        if listing_mint_authority_bumps.len() != count
+            || inventory_delegate_bumps.len() != count
+            || ctx.remaining_accounts.len() < count * BundleItem::ACCOUNTS
+        {
+            return Err(error!(StrangemoodError::MissingBundleListing));
+        }
+        let (item_accounts, other_accounts) = ctx.remaining_accounts.split_at(count * BundleItem::ACCOUNTS);
+
+        let mut items = Vec::with_capacity(count);
+        for (i, accounts) in item_accounts.chunks(BundleItem::ACCOUNTS).enumerate() {
+            items.push(BundleItem::load(
+                accounts,
+                bundle.listings[i],
+                &ctx.accounts.charter,
+                ctx.accounts.charter_treasury.mint,
+                listing_mint_authority_bumps[i],
+                inventory_delegate_bumps[i],
+            )?);
+        }
+
+        // Split the bundle's price between its listings
+        let parts = math::split_weighted(bundle.price, &bundle.weights)?;
+
+        let total = total_price(bundle.price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
+        for (mut item, price) in items.into_iter().zip(parts) {
+            check_purchasable(&item.listing, false)?;
+            if item.listing.subscription_period != 0 {
+                return Err(error!(StrangemoodError::ListingIsSubscription));
+            }
+
+            // DLC can be bought in a bundle with the listing it requires
+            let requires_bundled = matches!(item.listing.requires, Some(parent) if bundle.listings.contains(&parent));
+            if !requires_bundled {
+                check_requirement(&item.listing, other_accounts, &ctx.accounts.purchaser.key())?;
+            }
+            record_sale(
+                &mut item.listing,
+                amount,
+                other_accounts,
+                ctx.accounts.purchaser.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            )?;
+
+            // Distribute payment
+            let listing = item.listing.clone().into_inner();
+            let splits = transfer_funds(
+                price.checked_mul(amount).ok_or(StrangemoodError::ArithmeticOverflow)?,
+                &listing,
+                &charter,
+                ctx.accounts.token_program.clone(),
+                *ctx.accounts.payment.clone(),
+                *ctx.accounts.charter_treasury_deposit.clone(),
+                &lister_deposits(
+                    item.listing.key(),
+                    &listing,
+                    item.listings_payment_deposit.to_account_info(),
+                    other_accounts,
+                )?,
+                ctx.accounts.purchaser.clone()
+            )?;
+
+            // Distribute votes
+            let votes = distribute_governance_tokens(
+                splits.to_charter_amount,
+                charter.expansion_rate,
+                charter_treasury.scalar,
+                charter.vote_contribution,
+                ctx.accounts.token_program.clone(),
+                *ctx.accounts.charter_mint.clone(),
+                ctx.accounts.charter_mint_authority.to_account_info(),
+                charter_mint_authority_bump,
+                item.listings_vote_deposit.clone(),
+                *ctx.accounts.charter_reserve.clone(),
+            )?;
+
+            thaw_account(
+                &ctx.accounts.token_program,
+                &item.listing_mint,
+                &item.inventory,
+                &item.listing_mint_authority,
+                item.listing_mint_authority_bump
+            )?;
+
+            // Approve the delegate over the inventory
+            let delegated_amount = item.inventory.amount
+                .checked_add(amount)
+                .ok_or(StrangemoodError::ArithmeticOverflow)?;
+            approve_delegate(
+                ctx.accounts.token_program.to_account_info(),
+                item.inventory.to_account_info(),
+                item.inventory_delegate.clone(),
+                ctx.accounts.purchaser.to_account_info(),
+                delegated_amount
+            )?;
+
+            // Distribute listing token
+            mint_to(
+                &ctx.accounts.token_program,
+                &item.listing_mint,
+                &item.inventory,
+                &item.listing_mint_authority,
+                item.listing_mint_authority_bump,
+                amount,
+            )?;
+
+            freeze_account(
+                &ctx.accounts.token_program,
+                &item.listing_mint,
+                &item.inventory,
+                &item.listing_mint_authority,
+                item.listing_mint_authority_bump
+            )?;
+
+            // The listing isn't in the accounts struct, so write its new supply back by hand
+            item.listing.exit(&crate::ID)?;
+
+            emit!(Purchased {
+                listing: item.listing.key(),
+                charter: ctx.accounts.charter.key(),
+                purchaser: ctx.accounts.purchaser.key(),
+                inventory: item.inventory.key(),
+                receipt: None,
+                quantity: amount,
+                price,
+                splits,
+                votes,
+            });
+        }
+
+        emit!(BundlePurchased {
+            bundle: ctx.accounts.bundle.key(),
+            purchaser: ctx.accounts.purchaser.key(),
+            quantity: amount,
+            price: bundle.price,
+        });
+
         Ok(())
     }
 
-    pub fn start_trial(
-        ctx: Context<StartTrial>,
+    pub fn start_trial<'info>(
+        ctx: Context<'_, '_, '_, 'info, StartTrial<'info>>,
         listing_mint_authority_bump: u8,
         _escrow_authority_bump: u8,
         _inventory_delegate_bump: u8,
         amount: u64,
+        max_total_price: u64,
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
 
-        if !listing.is_available {
-            return Err(error!(StrangemoodError::ListingIsUnavailable));
-        }
-        if !listing.is_refundable {
-            return Err(error!(StrangemoodError::ListingIsNotRefundable));
-        }
-        if listing.is_suspended {
-            return Err(StrangemoodError::ListingIsSuspended.into());
-        }
+        check_purchasable(&listing, true)?;
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.purchaser.key())?;
+        record_sale(
+            &mut ctx.accounts.listing,
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
 
         // Move funds into an escrow, rather than the lister's deposit.
-        let total = amount.checked_mul(listing.price).unwrap();
+        let price = listing.price_at(Clock::get()?.unix_timestamp);
+        let total = total_price(price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
         token_transfer(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.payment.to_account_info(),
@@ -540,30 +1800,60 @@ pub mod strangemood {
         receipt.quantity = amount;
         receipt.inventory = ctx.accounts.inventory.key();
         receipt.cashier = None;
-        receipt.price = listing.price;
+        receipt.price = price;
         receipt.escrow = ctx.accounts.escrow.key();
+        receipt.started_at = Clock::get()?.unix_timestamp;
+        receipt.refund_window = listing.refund_window;
+
+        emit!(TrialStarted {
+            receipt: receipt.key(),
+            listing: receipt.listing,
+            cashier: receipt.cashier,
+            purchaser: receipt.purchaser,
+            inventory: receipt.inventory,
+            escrow: receipt.escrow,
+            quantity: receipt.quantity,
+            price: receipt.price,
+            started_at: receipt.started_at,
+            refund_window: receipt.refund_window,
+        });
 
         Ok(())
     }
 
-    pub fn start_trial_with_cashier(
-        ctx: Context<StartTrialWithCashier>,
+    pub fn start_trial_with_cashier<'info>(
+        ctx: Context<'_, '_, '_, 'info, StartTrialWithCashier<'info>>,
         listing_mint_authority_bump: u8,
         _escrow_authority_bump: u8,
         _inventory_delegate_bump: u8,
         amount: u64,
+        max_total_price: u64,
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
 
-        if !listing.is_available {
-            return Err(error!(StrangemoodError::ListingIsUnavailable));
-        }
-        if !listing.is_refundable {
-            return Err(error!(StrangemoodError::ListingIsNotRefundable));
-        }
+        check_purchasable(&listing, true)?;
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.purchaser.key())?;
+        record_sale(
+            &mut ctx.accounts.listing,
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
 
         // Move funds into an escrow, rather than the lister's deposit.
-        let total = amount.checked_mul(listing.price).unwrap();
+        let price = listing.price_at(Clock::get()?.unix_timestamp);
+        let total = total_price(price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
         token_transfer(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.payment.to_account_info(),
@@ -581,6 +1871,17 @@ pub mod strangemood {
             listing_mint_authority_bump
         )?;
 
+        // Approve the delegate over the inventory, so the
+        // tokens can be burned if the trial is refunded.
+        let delegated_amount = ctx.accounts.inventory.amount.checked_add(amount).unwrap();
+        approve_delegate(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.inventory.to_account_info(),
+            ctx.accounts.inventory_delegate.to_account_info(),
+            ctx.accounts.purchaser.to_account_info(),
+            delegated_amount
+        )?;
+
         // Mint the token, which can be burned later upon refund.
         mint_to(
             &ctx.accounts.token_program,
@@ -607,25 +1908,58 @@ pub mod strangemood {
         receipt.inventory = ctx.accounts.inventory.key();
         receipt.cashier = Option::Some(ctx.accounts.cashier.key());
         receipt.escrow = ctx.accounts.escrow.key();
-        receipt.price = listing.price;
+        receipt.price = price;
         receipt.quantity = amount;
+        receipt.started_at = Clock::get()?.unix_timestamp;
+        receipt.refund_window = listing.refund_window;
+
+        emit!(TrialStarted {
+            receipt: receipt.key(),
+            listing: receipt.listing,
+            cashier: receipt.cashier,
+            purchaser: receipt.purchaser,
+            inventory: receipt.inventory,
+            escrow: receipt.escrow,
+            quantity: receipt.quantity,
+            price: receipt.price,
+            started_at: receipt.started_at,
+            refund_window: receipt.refund_window,
+        });
 
         Ok(())
     }
 
-    pub fn finish_trial(
-        ctx: Context<FinishTrial>,
+    // If the listing splits its revenue, see lister_deposits for the
+    // accounts that finish_trial needs after its other accounts.
+    pub fn finish_trial<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinishTrial<'info>>,
         charter_mint_authority_bump: u8,
         receipt_escrow_authority_bump: u8
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
-        let charter = ctx.accounts.charter.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
         let receipt = ctx.accounts.receipt.clone().into_inner();
 
         if receipt.cashier != None {
             return Err(error!(StrangemoodError::ReceiptHasCashier));
         }
 
+        // A suspended listing doesn't get paid. Its trials stay in escrow
+        // until the purchaser refunds them or the charter lifts the suspension.
+        if listing.is_suspended {
+            return Err(error!(StrangemoodError::ListingIsSuspended));
+        }
+
+        // Until the refund window passes, only the purchaser can give up
+        // their refund. After that, anyone can crank the trial to completion.
+        if receipt.needs_migration() {
+            return Err(error!(StrangemoodError::AccountNeedsMigration));
+        }
+        let now = Clock::get()?.unix_timestamp;
+        if receipt.is_refundable_at(now) && !ctx.accounts.purchaser.is_signer {
+            return Err(error!(StrangemoodError::TrialIsStillRefundable));
+        }
+
         let total: u64 = receipt.price.checked_mul(receipt.quantity).unwrap();
         let splits = transfer_funds_from_escrow(
             total, 
@@ -634,15 +1968,21 @@ pub mod strangemood {
             ctx.accounts.token_program.clone(),
             *ctx.accounts.receipt_escrow.clone(),
             *ctx.accounts.charter_treasury_deposit.clone(),
-            *ctx.accounts.listings_payment_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
             ctx.accounts.receipt_escrow_authority.clone(),  
             receipt_escrow_authority_bump
         )?;
         
         let treasury = ctx.accounts.charter_treasury.clone().into_inner();
-        distribute_governance_tokens(
+        let votes = distribute_governance_tokens(
             splits.to_charter_amount,
-            charter.expansion_rate / treasury.scalar,
+            charter.expansion_rate,
+            treasury.scalar,
             charter.vote_contribution,
              ctx.accounts.token_program.clone(),
              *ctx.accounts.charter_mint.clone(),
@@ -661,6 +2001,20 @@ pub mod strangemood {
             receipt_escrow_authority_bump
         )?;
 
+        emit!(TrialFinished {
+            receipt: ctx.accounts.receipt.key(),
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            purchaser: receipt.purchaser,
+            quantity: receipt.quantity,
+            price: receipt.price,
+            splits,
+            votes,
+        });
+
+        // Saturating, since trials started before this was tracked aren't counted
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials.saturating_sub(1);
+
         // Close the receipt.
         close_native_account(
             &ctx.accounts.receipt.to_account_info(),
@@ -670,19 +2024,35 @@ pub mod strangemood {
         Ok(())
     }
 
-    pub fn finish_trial_with_cashier(
-        ctx: Context<FinishTrialWithCashier>,
+    pub fn finish_trial_with_cashier<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinishTrialWithCashier<'info>>,
         charter_mint_authority_bump: u8,
         receipt_escrow_authority_bump: u8
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
-        let charter = ctx.accounts.charter.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
         let receipt = ctx.accounts.receipt.clone().into_inner();
 
         if receipt.cashier == None {
             return Err(error!(StrangemoodError::ReceiptDoesNotHaveCashier));
         }
 
+        // A suspended listing doesn't get paid. Its trials stay in escrow
+        // until the purchaser refunds them or the charter lifts the suspension.
+        if listing.is_suspended {
+            return Err(error!(StrangemoodError::ListingIsSuspended));
+        }
+
+        // Until the refund window passes, only the purchaser can give up
+        // their refund. After that, anyone can crank the trial to completion.
+        if receipt.needs_migration() {
+            return Err(error!(StrangemoodError::AccountNeedsMigration));
+        }
+        let now = Clock::get()?.unix_timestamp;
+        if receipt.is_refundable_at(now) && !ctx.accounts.purchaser.is_signer {
+            return Err(error!(StrangemoodError::TrialIsStillRefundable));
+        }
+
         let total: u64 = receipt.price.checked_mul(receipt.quantity).unwrap();
         let splits = transfer_funds_from_escrow_with_cashier(
             total, 
@@ -691,16 +2061,22 @@ pub mod strangemood {
             ctx.accounts.token_program.clone(),
             *ctx.accounts.receipt_escrow.clone(),
             *ctx.accounts.charter_treasury_deposit.clone(),
-            *ctx.accounts.listings_payment_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
             *ctx.accounts.cashier_treasury_escrow.clone(),
             ctx.accounts.receipt_escrow_authority.clone(),
             receipt_escrow_authority_bump
         )?;
         
         let treasury = ctx.accounts.charter_treasury.clone().into_inner();
-        distribute_governance_tokens(
+        let votes = distribute_governance_tokens(
             splits.to_charter_amount,
-            charter.expansion_rate / treasury.scalar,
+            charter.expansion_rate,
+            treasury.scalar,
             charter.vote_contribution,
              ctx.accounts.token_program.clone(),
              *ctx.accounts.charter_mint.clone(),
@@ -719,6 +2095,20 @@ pub mod strangemood {
             receipt_escrow_authority_bump
         )?;
 
+        emit!(TrialFinishedWithCashier {
+            receipt: ctx.accounts.receipt.key(),
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            cashier: ctx.accounts.cashier.key(),
+            purchaser: receipt.purchaser,
+            quantity: receipt.quantity,
+            price: receipt.price,
+            splits,
+            votes,
+        });
+
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials.saturating_sub(1);
+
         // Close the receipt.
         close_native_account(
             &ctx.accounts.receipt.to_account_info(),
@@ -728,58 +2118,126 @@ pub mod strangemood {
         Ok(())
     }
 
-    pub fn refund_trial(
-        ctx: Context<RefundTrial>,
+    pub fn refund_trial<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundTrial<'info>>,
         listing_mint_authority_bump: u8,
         inventory_delegate_bump: u8,
         escrow_authority_bump:u8,
     ) -> Result<()> {
         let receipt = ctx.accounts.receipt.clone().into_inner();
 
-        thaw_account(
+        if receipt.cashier != None {
+            return Err(error!(StrangemoodError::ReceiptHasCashier));
+        }
+        // If the charter suspends a listing, its trials can be
+        // refunded even after the refund window has passed.
+        if receipt.needs_migration() {
+            return Err(error!(StrangemoodError::AccountNeedsMigration));
+        }
+        let is_suspended = ctx.accounts.listing.is_suspended;
+        if !is_suspended && !receipt.is_refundable_at(Clock::get()?.unix_timestamp) {
+            return Err(error!(StrangemoodError::RefundWindowHasExpired));
+        }
+
+        let refunded = refund_escrow(
+            receipt.quantity,
             &ctx.accounts.token_program,
             &ctx.accounts.listing_mint,
-            &ctx.accounts.inventory,
-            &ctx.accounts.listing_mint_authority.to_account_info(),
+            &ctx.accounts.listing_mint_authority,
             listing_mint_authority_bump,
-        )?;
-
-        burn(
-            ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.listing_mint.to_account_info(),
-            ctx.accounts.inventory.to_account_info(),
-            ctx.accounts.inventory_delegate.to_account_info(),
+            &ctx.accounts.inventory,
+            &ctx.accounts.inventory_delegate,
             inventory_delegate_bump,
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_authority,
+            escrow_authority_bump,
+            &ctx.accounts.return_deposit,
+            &ctx.accounts.purchaser.to_account_info(),
+        )?;
+        record_refund(
+            &mut ctx.accounts.listing,
             receipt.quantity,
+            ctx.remaining_accounts,
+            &receipt.purchaser,
         )?;
 
-        freeze_account(
+        emit!(TrialRefunded {
+            receipt: ctx.accounts.receipt.key(),
+            listing: receipt.listing,
+            cashier: None,
+            purchaser: receipt.purchaser,
+            inventory: receipt.inventory,
+            quantity: receipt.quantity,
+            amount: refunded,
+        });
+
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials.saturating_sub(1);
+
+        // Close the receipt account
+        close_native_account(
+            &ctx.accounts.receipt.to_account_info(),
+            &ctx.accounts.purchaser.to_account_info(),
+        );
+
+        Ok(())
+    }
+
+    pub fn refund_trial_with_cashier<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundTrialWithCashier<'info>>,
+        listing_mint_authority_bump: u8,
+        inventory_delegate_bump: u8,
+        escrow_authority_bump:u8,
+    ) -> Result<()> {
+        let receipt = ctx.accounts.receipt.clone().into_inner();
+
+        if receipt.cashier == None {
+            return Err(error!(StrangemoodError::ReceiptDoesNotHaveCashier));
+        }
+        // If the charter suspends a listing, its trials can be
+        // refunded even after the refund window has passed.
+        if receipt.needs_migration() {
+            return Err(error!(StrangemoodError::AccountNeedsMigration));
+        }
+        let is_suspended = ctx.accounts.listing.is_suspended;
+        if !is_suspended && !receipt.is_refundable_at(Clock::get()?.unix_timestamp) {
+            return Err(error!(StrangemoodError::RefundWindowHasExpired));
+        }
+
+        // The cashier gets nothing from a refunded sale; their cut
+        // only leaves the escrow when the trial is finished.
+        let refunded = refund_escrow(
+            receipt.quantity,
             &ctx.accounts.token_program,
             &ctx.accounts.listing_mint,
-            &ctx.accounts.inventory,
-            &ctx.accounts.listing_mint_authority.to_account_info(),
+            &ctx.accounts.listing_mint_authority,
             listing_mint_authority_bump,
+            &ctx.accounts.inventory,
+            &ctx.accounts.inventory_delegate,
+            inventory_delegate_bump,
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_authority,
+            escrow_authority_bump,
+            &ctx.accounts.return_deposit,
+            &ctx.accounts.purchaser.to_account_info(),
         )?;
-
-        // Transfer all the funds in the escrow back to the user 
-        token_transfer_with_seed(            
-            ctx.accounts.token_program.to_account_info(), 
-            ctx.accounts.escrow.to_account_info(), 
-            ctx.accounts.return_deposit.to_account_info(), 
-            ctx.accounts.escrow_authority.to_account_info(), 
-            ctx.accounts.escrow.amount,
-            b"token_authority",
-            escrow_authority_bump
+        record_refund(
+            &mut ctx.accounts.listing,
+            receipt.quantity,
+            ctx.remaining_accounts,
+            &receipt.purchaser,
         )?;
 
-        // Close the escrow
-        close_token_escrow_account(
-ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.escrow.to_account_info(),
-            ctx.accounts.purchaser.to_account_info(),
-    ctx.accounts.escrow_authority.to_account_info(),
-            escrow_authority_bump
-        )?;
+        emit!(TrialRefunded {
+            receipt: ctx.accounts.receipt.key(),
+            listing: receipt.listing,
+            cashier: receipt.cashier,
+            purchaser: receipt.purchaser,
+            inventory: receipt.inventory,
+            quantity: receipt.quantity,
+            amount: refunded,
+        });
+
+        ctx.accounts.listing.open_trials = ctx.accounts.listing.open_trials.saturating_sub(1);
 
         // Close the receipt account
         close_native_account(
@@ -787,7 +2245,6 @@ ctx.accounts.token_program.to_account_info(),
             &ctx.accounts.purchaser.to_account_info(),
         );
 
-
         Ok(())
     }
 
@@ -828,14 +2285,20 @@ ctx.accounts.token_program.to_account_info(),
             mint_authority_bump,
         )?;
 
+        emit!(Consumed {
+            listing: ctx.accounts.listing.key(),
+            inventory: ctx.accounts.inventory.key(),
+            amount,
+        });
+
         Ok(())
     }
 
     pub fn init_charter(
         ctx: Context<InitCharter>,
-        expansion_rate: f64,
-        payment_contribution: f64,
-        vote_contribution: f64,
+        expansion_rate: u64,
+        payment_contribution: u64,
+        vote_contribution: u64,
         withdraw_period: u64,
         stake_withdraw_amount: u64,
         uri: String,
@@ -851,8 +2314,15 @@ ctx.accounts.token_program.to_account_info(),
             // tokens, since purchases would fail.
             return Err(StrangemoodError::SignerIsNotMintAuthority.into())
         }
+        if withdraw_period == 0 {
+            return Err(error!(StrangemoodError::WithdrawPeriodIsInvalid));
+        }
+        if uri.len() > Charter::MAX_URI_LENGTH {
+            return Err(error!(StrangemoodError::UriIsTooLong));
+        }
         
         let charter = &mut ctx.accounts.charter;
+        charter.check_contributions(payment_contribution, vote_contribution)?;
         charter.is_initialized = true;
         charter.authority = ctx.accounts.authority.key();
         charter.expansion_rate = expansion_rate;
@@ -863,17 +2333,41 @@ ctx.accounts.token_program.to_account_info(),
         charter.reserve = ctx.accounts.reserve.key();
         charter.mint = ctx.accounts.mint.key();
         charter.uri = uri;
+        charter.version = ACCOUNT_VERSION;
+
+        emit!(CharterInitialized {
+            charter: charter.key(),
+            authority: charter.authority,
+            mint: charter.mint,
+            reserve: charter.reserve,
+            expansion_rate: charter.expansion_rate,
+            payment_contribution: charter.payment_contribution,
+            vote_contribution: charter.vote_contribution,
+            withdraw_period: charter.withdraw_period,
+            stake_withdraw_amount: charter.stake_withdraw_amount,
+            uri: charter.uri.clone(),
+        });
 
         Ok(())
     }
 
     pub fn set_listing_price(ctx: Context<SetListing>, price: u64) -> Result<()> {
         ctx.accounts.listing.price = price;
+
+        emit!(ListingPriceSet {
+            listing: ctx.accounts.listing.key(),
+            price,
+        });
         Ok(())
     }
 
     pub fn set_listing_uri(ctx: Context<SetListing>, uri: String) -> Result<()> {
-        ctx.accounts.listing.uri = uri;
+        ctx.accounts.listing.uri = uri.clone();
+
+        emit!(ListingUriSet {
+            listing: ctx.accounts.listing.key(),
+            uri,
+        });
         Ok(())
     }
 
@@ -883,225 +2377,2946 @@ ctx.accounts.token_program.to_account_info(),
     ) -> Result<()> {
 
         ctx.accounts.listing.is_available = is_available;
-        Ok(())
-    }
 
-    pub fn set_listing_deposits(ctx: Context<SetListingDeposit>) -> Result<()> {
-        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
-        ctx.accounts.listing.payment_deposit = ctx.accounts.payment_deposit.key();
+        emit!(ListingAvailabilitySet {
+            listing: ctx.accounts.listing.key(),
+            is_available,
+        });
         Ok(())
     }
 
-    pub fn set_listing_authority(ctx: Context<SetListingAuthority>) -> Result<()> {
-        ctx.accounts.listing.authority = ctx.accounts.new_authority.key();
-        Ok(())
-    }
+    pub fn set_listing_refund_window(
+        ctx: Context<SetListing>,
+        refund_window: i64,
+    ) -> Result<()> {
+        if refund_window < 0 {
+            return Err(error!(StrangemoodError::RefundWindowIsInvalid));
+        }
 
-    // Migrate a listing to a different charter
-    pub fn set_listing_charter(ctx: Context<SetListingCharter>) -> Result<()> {
-        ctx.accounts.listing.charter = ctx.accounts.charter.key();
-        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
+        // Trials that have already started keep the window they started with.
+        ctx.accounts.listing.refund_window = refund_window;
+
+        emit!(ListingRefundWindowSet {
+            listing: ctx.accounts.listing.key(),
+            refund_window,
+        });
         Ok(())
     }
 
-    pub fn set_charter_expansion_rate(
-        ctx: Context<SetCharter>,
-        expansion_rate: f64,
+    // Schedules a sale, replacing any existing one. The sale starts and
+    // ends on its own, based on the cluster's clock.
+    //
+    // To cancel a sale, set all three to 0.
+    pub fn set_listing_sale(
+        ctx: Context<SetListing>,
+        sale_price: u64,
+        sale_starts_at: i64,
+        sale_ends_at: i64,
     ) -> Result<()> {
-        ctx.accounts.charter.expansion_rate = expansion_rate;
+        let is_cancel = sale_price == 0 && sale_starts_at == 0 && sale_ends_at == 0;
+        if !is_cancel && sale_ends_at <= sale_starts_at {
+            return Err(error!(StrangemoodError::SaleScheduleIsInvalid));
+        }
+
+        let listing = &mut ctx.accounts.listing;
+        listing.sale_price = sale_price;
+        listing.sale_starts_at = sale_starts_at;
+        listing.sale_ends_at = sale_ends_at;
+
+        emit!(ListingSaleSet {
+            listing: listing.key(),
+            sale_price,
+            sale_starts_at,
+            sale_ends_at,
+        });
         Ok(())
     }
 
-    pub fn set_charter_contribution_rate(
-        ctx: Context<SetCharter>,
-        payment_contribution: f64,
-        vote_contribution: f64
+    // Either limit can be 0 to remove it.
+    pub fn set_listing_supply_limits(
+        ctx: Context<SetListing>,
+        max_supply: u64,
+        max_per_wallet: u64,
     ) -> Result<()> {
-        ctx.accounts.charter.payment_contribution = payment_contribution;
-        ctx.accounts.charter.vote_contribution = vote_contribution;
+        let listing = &mut ctx.accounts.listing;
+        if max_supply != 0 && max_supply < listing.supply {
+            return Err(error!(StrangemoodError::MaxSupplyIsInvalid));
+        }
 
-        Ok(())
-    }
+        listing.max_supply = max_supply;
+        listing.max_per_wallet = max_per_wallet;
 
-    // Migrates the charter to a different authority, like a new governance program
-    pub fn set_charter_authority(ctx: Context<SetCharterAuthority>) -> Result<()> {
-        ctx.accounts.charter.authority = ctx.accounts.new_authority.key();
+        emit!(ListingSupplyLimitsSet {
+            listing: listing.key(),
+            max_supply,
+            max_per_wallet,
+        });
         Ok(())
     }
 
-    pub fn set_charter_reserve(ctx: Context<SetCharterReserve>) -> Result<()> {
-        ctx.accounts.charter.reserve = ctx.accounts.reserve.key();
-        Ok(())
-    }
+    // Closes a listing and returns its rent to the authority. Refuses while
+    // the listing has open trials or gifts, since those need the listing to
+    // settle or close, and refuses listings migrated from the legacy
+    // layout, whose open trials weren't counted.
+    //
+    // If the listing splits its revenue, its ListingSplits,
+    // ["listing_splits", listing], must be passed in the remaining accounts,
+    // and is closed too.
+    //
+    // The listing's mint is left as is. Its mint authority is a PDA of the
+    // mint, so no more tokens can be minted, and existing inventories stay
+    // frozen and valid. The listing's address can't be reused, since
+    // init_listing always creates a new mint.
+    //
+    // Other accounts that point at the listing are left as they are:
+    //   - Coupons can't be redeemed, or closed, since close_coupon needs
+    //     the listing. Close them first, or their rent is stuck.
+    //   - ResaleOffers can't be filled, but the seller can still cancel them.
+    //   - Subscriptions can't be checked or renewed. Their AutoRenewals
+    //     fail to renew, but the purchaser can still cancel them.
+    //   - Bundles that include the listing can't be bought. Their authority
+    //     can still make them unavailable.
+    pub fn close_listing<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseListing<'info>>,
+    ) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        if Clock::get()?.unix_timestamp < listing.legacy_trials_until {
+            return Err(error!(StrangemoodError::ListingHasUncountedTrials));
+        }
+        if listing.open_trials != 0 {
+            return Err(error!(StrangemoodError::ListingHasOpenTrials));
+        }
+        if listing.open_gifts != 0 {
+            return Err(error!(StrangemoodError::ListingHasOpenGifts));
+        }
 
-    pub fn init_charter_treasury(ctx: Context<InitCharterTreasury>, scalar: f64) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.is_initialized = true;
-        treasury.charter = ctx.accounts.charter.key();
-        treasury.deposit = ctx.accounts.deposit.key(); 
-        treasury.mint = ctx.accounts.mint.key();
-        treasury.scalar = scalar; 
+        if listing.has_splits {
+            let (address, _) = Pubkey::find_program_address(
+                &[b"listing_splits", listing.key().as_ref()],
+                &crate::ID,
+            );
+            let splits_info = ctx
+                .remaining_accounts
+                .iter()
+                .find(|a| a.key() == address && a.owner == &crate::ID)
+                .ok_or(StrangemoodError::MissingListingSplits)?;
+            close_native_account(splits_info, &ctx.accounts.authority.to_account_info());
+
+            emit!(ListingSplitsClosed {
+                listing: listing.key(),
+            });
+        }
 
+        emit!(ListingClosed {
+            listing: listing.key(),
+            authority: ctx.accounts.authority.key(),
+        });
         Ok(())
     }
 
-    pub fn set_charter_treasury_scalar(ctx: Context<SetCharterTreasuryExpansionScalar>, scalar: f64) -> Result<()> {
-        
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.scalar = scalar; 
+    // Makes a listing require another listing, like DLC that requires
+    // its base game. Set to None to remove the requirement.
+    pub fn set_listing_requirement(
+        ctx: Context<SetListing>,
+        requires: Option<Pubkey>,
+    ) -> Result<()> {
+        let listing = &mut ctx.accounts.listing;
+        if requires == Some(listing.key()) {
+            return Err(error!(StrangemoodError::ListingRequirementIsInvalid));
+        }
+        listing.requires = requires;
 
+        emit!(ListingRequirementSet {
+            listing: listing.key(),
+            requires,
+        });
         Ok(())
     }
 
-    pub fn set_charter_treasury_deposit(ctx: Context<SetCharterTreasuryDeposit>) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.deposit = ctx.accounts.deposit.key(); 
-
-        Ok(())
-    }
+    // Buys "amount" listing tokens for someone else, without needing their
+    // inventory. The payment is split like a purchase, and the tokens are
+    // held in a Gift at the PDA ["gift", purchaser, nonce] until they're
+    // claimed with claim_gift.
+    //
+    // The gift is locked to either a recipient's wallet, or to the public
+    // key of a keypair whose secret key the purchaser sends the recipient.
+    // If it isn't claimed by expires_at, the purchaser can reclaim it for
+    // themselves.
+    //
+    // Gifts count towards the purchaser's max_per_wallet, not the
+    // recipient's. Subscriptions can't be gifted.
+    pub fn purchase_gift<'info>(
+        ctx: Context<'_, '_, '_, 'info, PurchaseGift<'info>>,
+        charter_mint_authority_bump: u8,
+        nonce: u64,
+        recipient: Option<Pubkey>,
+        secret_key: Option<Pubkey>,
+        expires_at: i64,
+        amount: u64,
+        max_total_price: u64,
+        max_payment_contribution: Option<u64>,
+    ) -> Result<()> {
+        let listing = ctx.accounts.listing.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
+        let now = Clock::get()?.unix_timestamp;
 
-    pub fn init_cashier(ctx: Context<InitCashier>, _stake_authority_bump: u8, uri: String) -> Result<()> {
-        let cashier = &mut ctx.accounts.cashier;
-        cashier.is_initialized = true;
-        cashier.charter = ctx.accounts.charter.key();
-        cashier.stake = ctx.accounts.stake.key();
-        cashier.authority = ctx.accounts.authority.key();
-        cashier.last_withdraw_at = ctx.accounts.clock.epoch;
-        cashier.uri = uri;
+        if recipient.is_some() == secret_key.is_some() || amount == 0 || expires_at <= now {
+            return Err(error!(StrangemoodError::GiftIsInvalid));
+        }
+        check_purchasable(&listing, false)?;
+        if listing.subscription_period != 0 {
+            return Err(error!(StrangemoodError::ListingIsSubscription));
+        }
+        check_payment_contribution(&charter, max_payment_contribution)?;
+        record_sale(
+            &mut ctx.accounts.listing,
+            amount,
+            ctx.remaining_accounts,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
 
-        Ok(())
-    }
+        // Distribute payment
+        let price = listing.price_at(now);
+        let total = total_price(price, amount, max_total_price)?;
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
+        let splits = transfer_funds(total,
+            &listing,
+            &charter,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.payment.clone(),
+            *ctx.accounts.charter_treasury_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
+            ctx.accounts.purchaser.clone()
+        )?;
 
-    pub fn init_cashier_treasury(ctx: Context<InitCashierTreasury>, _escrow_authority_bump: u8) -> Result<()> {
-        let treasury = &mut ctx.accounts.cashier_treasury; 
-        
-        treasury.is_initialized = true; 
-        treasury.cashier = ctx.accounts.cashier.key();
-        treasury.deposit = ctx.accounts.deposit.key();
-        treasury.escrow = ctx.accounts.escrow.key();
-        treasury.mint = ctx.accounts.mint.key();
-        treasury.last_withdraw_at = ctx.accounts.clock.epoch;
+        // Distribute votes
+        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
+        let votes = distribute_governance_tokens(
+            splits.to_charter_amount,
+            charter.expansion_rate,
+            charter_treasury.scalar,
+            charter.vote_contribution,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.charter_mint.clone(),
+            ctx.accounts.charter_mint_authority.to_account_info(),
+            charter_mint_authority_bump,
+            *ctx.accounts.listings_vote_deposit.clone(),
+            *ctx.accounts.charter_reserve.clone(),
+        )?;
 
+        ctx.accounts.listing.open_gifts = ctx.accounts.listing.open_gifts
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        let gift = &mut ctx.accounts.gift;
+        gift.is_initialized = true;
+        gift.listing = ctx.accounts.listing.key();
+        gift.purchaser = ctx.accounts.purchaser.key();
+        gift.recipient = recipient;
+        gift.secret_key = secret_key;
+        gift.quantity = amount;
+        gift.expires_at = expires_at;
+        gift.nonce = nonce;
+
+        emit!(GiftPurchased {
+            gift: gift.key(),
+            listing: gift.listing,
+            charter: ctx.accounts.charter.key(),
+            purchaser: gift.purchaser,
+            recipient,
+            quantity: amount,
+            price,
+            expires_at,
+            splits,
+            votes,
+        });
         Ok(())
     }
 
-    pub fn burn_cashier_stake(ctx: Context<BurnCashierStake>,  mint_authority_bump: u8, amount: u64) -> Result<()> {   
-        burn(
-            ctx.accounts.token_program.to_account_info(), 
-    ctx.accounts.mint.to_account_info(),
-ctx.accounts.stake.to_account_info(),
-ctx.accounts.stake_authority.to_account_info(),
-        mint_authority_bump,
-        amount
+    // Mints a gift's listing tokens into the recipient's inventory, and
+    // closes the gift. The inventory must already exist, so clients create
+    // the recipient's associated token account in the same transaction.
+    //
+    // A gift locked with a secret key is claimed by passing that key, as a
+    // signer, in the remaining accounts.
+    //
+    // If the listing requires another listing, see check_requirement
+    // for the accounts that this needs after its other accounts.
+    pub fn claim_gift<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimGift<'info>>,
+        listing_mint_authority_bump: u8,
+        _inventory_delegate_bump: u8,
+    ) -> Result<()> {
+        let gift = ctx.accounts.gift.clone().into_inner();
+        let listing = ctx.accounts.listing.clone().into_inner();
+
+        if Clock::get()?.unix_timestamp >= gift.expires_at {
+            return Err(error!(StrangemoodError::GiftHasExpired));
+        }
+        let signers: Vec<Pubkey> = ctx
+            .remaining_accounts
+            .iter()
+            .filter(|a| a.is_signer)
+            .map(|a| a.key())
+            .collect();
+        gift.check_claim(&ctx.accounts.recipient.key(), &signers)?;
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.recipient.key())?;
+
+        // A recipient may never have held the listing's token, so
+        // their associated token account is created if it's missing
+        if ctx.accounts.inventory.data_is_empty() {
+            cpi::create_associated_token_account(
+                ctx.accounts.associated_token_program.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.inventory.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+            )?;
+        }
+        let mut inventory = Account::<TokenAccount>::try_from(&ctx.accounts.inventory)?;
+        if inventory.mint != ctx.accounts.listing_mint.key() {
+            return Err(error!(StrangemoodError::TokenAccountHasUnexpectedMint));
+        }
+        if inventory.owner != ctx.accounts.recipient.key() {
+            return Err(error!(StrangemoodError::TokenAccountHasUnexpectedOwner));
+        }
+
+        deliver_gift(
+            gift.quantity,
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+            &mut inventory,
+            &ctx.accounts.inventory_delegate.to_account_info(),
+            &ctx.accounts.recipient.to_account_info(),
         )?;
 
+        ctx.accounts.listing.open_gifts = ctx.accounts.listing.open_gifts.saturating_sub(1);
+
+        emit!(GiftClaimed {
+            gift: ctx.accounts.gift.key(),
+            listing: gift.listing,
+            recipient: ctx.accounts.recipient.key(),
+            inventory: ctx.accounts.inventory.key(),
+            quantity: gift.quantity,
+        });
         Ok(())
     }
 
-    // A decentralized crank that moves money from the the cashier's escrow to their deposit.
-    pub fn withdraw_cashier_treasury(ctx: Context<WithdrawCashierTreasury>, _mint_authority_bump: u8, cashier_escrow_bump: u8) -> Result<()> {
-        let charter = ctx.accounts.charter.clone().into_inner();
-        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
-        let stake = ctx.accounts.stake.clone().into_inner();
-        let clock = ctx.accounts.clock.clone();
-        let cashier_treasury = &mut ctx.accounts.cashier_treasury;
+    // Lets the purchaser of a gift that wasn't claimed in time mint its
+    // listing tokens into their own inventory instead.
+    pub fn reclaim_gift(
+        ctx: Context<ReclaimGift>,
+        listing_mint_authority_bump: u8,
+        _inventory_delegate_bump: u8,
+    ) -> Result<()> {
+        let gift = ctx.accounts.gift.clone().into_inner();
 
-        // Calculate the amount to transfer
-        let amount_per_period = stake.amount as f64 * charter_treasury.scalar;
-        let amount_per_epoch = amount_per_period as f64 / charter.withdraw_period as f64;
-        let epochs_passed = clock.epoch.checked_sub(cashier_treasury.last_withdraw_at).unwrap();
-        let amount_to_transfer = amount_per_epoch * epochs_passed as f64;
+        if Clock::get()?.unix_timestamp < gift.expires_at {
+            return Err(error!(StrangemoodError::GiftHasNotExpired));
+        }
 
-        // Transfer what we can
-        token_transfer_with_seed(
-            ctx.accounts.token_program.to_account_info(), 
-        ctx.accounts.escrow.to_account_info(),
-         ctx.accounts.deposit.to_account_info(),
-        ctx.accounts.escrow_authority.to_account_info(),
-            cmp::min(amount_to_transfer as u64, ctx.accounts.escrow.amount),
-            b"cashier.escrow", 
-            cashier_escrow_bump
+        deliver_gift(
+            gift.quantity,
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+            &mut ctx.accounts.inventory,
+            &ctx.accounts.inventory_delegate.to_account_info(),
+            &ctx.accounts.purchaser.to_account_info(),
         )?;
 
-        // Update cashier treasury's last epoch
-        cashier_treasury.last_withdraw_at = clock.epoch;
+        ctx.accounts.listing.open_gifts = ctx.accounts.listing.open_gifts.saturating_sub(1);
 
+        emit!(GiftReclaimed {
+            gift: ctx.accounts.gift.key(),
+            listing: gift.listing,
+            purchaser: gift.purchaser,
+            inventory: ctx.accounts.inventory.key(),
+            quantity: gift.quantity,
+        });
         Ok(())
     }
 
-    // A decentralized crank that moves money from the the cashier's escrow to their deposit.
-    pub fn withdraw_cashier_stake(ctx: Context<WithdrawCashierStake>, stake_authority_bump: u8) -> Result<()> {
-        let charter = ctx.accounts.charter.clone().into_inner();
-        let clock = ctx.accounts.clock.clone();
-        let cashier = &mut ctx.accounts.cashier;
+    // Lets owners of a listing's token resell it through a ResaleOffer, and
+    // sets the lister's royalty on resales. Offers that already exist can't
+    // be filled while is_resellable is false.
+    pub fn set_listing_resale(
+        ctx: Context<SetListing>,
+        is_resellable: bool,
+        resale_royalty: u64,
+    ) -> Result<()> {
+        if resale_royalty > math::BASIS_POINTS {
+            return Err(error!(StrangemoodError::ResaleRoyaltyIsInvalid));
+        }
 
-        // Calculate the amount to transfer
-        let amount_per_period = charter.stake_withdraw_amount;
-        let amount_per_epoch = amount_per_period as f64 / charter.withdraw_period as f64;
-        let epochs_passed = clock.epoch.checked_sub(cashier.last_withdraw_at).unwrap();
-        let amount_to_transfer = amount_per_epoch * epochs_passed as f64;
+        let listing = &mut ctx.accounts.listing;
+        listing.is_resellable = is_resellable;
+        listing.resale_royalty = resale_royalty;
+
+        emit!(ListingResaleSet {
+            listing: listing.key(),
+            is_resellable,
+            resale_royalty,
+        });
+        Ok(())
+    }
 
-        // Transfer what we can
+    // Offers "quantity" listing tokens from the seller's inventory for
+    // resale, at "price" each. To change an offer, cancel it and make
+    // a new one.
+    pub fn init_resale_offer(
+        ctx: Context<InitResaleOffer>,
+        price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        if !ctx.accounts.listing.is_resellable {
+            return Err(error!(StrangemoodError::ListingIsNotResellable));
+        }
+        if quantity == 0 || quantity > ctx.accounts.inventory.amount {
+            return Err(error!(StrangemoodError::ResaleOfferIsInvalid));
+        }
+
+        let resale_offer = &mut ctx.accounts.resale_offer;
+        resale_offer.is_initialized = true;
+        resale_offer.listing = ctx.accounts.listing.key();
+        resale_offer.seller = ctx.accounts.seller.key();
+        resale_offer.inventory = ctx.accounts.inventory.key();
+        resale_offer.deposit = ctx.accounts.deposit.key();
+        resale_offer.price = price;
+        resale_offer.quantity = quantity;
+
+        emit!(ResaleOfferSet {
+            resale_offer: resale_offer.key(),
+            listing: resale_offer.listing,
+            seller: resale_offer.seller,
+            inventory: resale_offer.inventory,
+            deposit: resale_offer.deposit,
+            price,
+            quantity,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_resale_offer(ctx: Context<CancelResaleOffer>) -> Result<()> {
+        emit!(ResaleOfferCancelled {
+            resale_offer: ctx.accounts.resale_offer.key(),
+            listing: ctx.accounts.resale_offer.listing,
+        });
+        Ok(())
+    }
+
+    // Buys "quantity" listing tokens from a ResaleOffer. The program thaws
+    // the seller's inventory, moves the tokens with the seller's inventory
+    // delegate, and freezes both inventories again.
+    //
+    // The charter takes its payment_contribution of the price, and the
+    // lister takes its resale_royalty of the rest, both through the same
+    // splits as a purchase. Resales don't count towards a listing's supply.
+    //
+    // If the listing requires another listing, or splits its revenue, see
+    // check_requirement and lister_deposits for the accounts that this
+    // needs after its other accounts.
+    pub fn purchase_resale<'info>(
+        ctx: Context<'_, '_, '_, 'info, PurchaseResale<'info>>,
+        listing_mint_authority_bump: u8,
+        charter_mint_authority_bump: u8,
+        seller_inventory_delegate_bump: u8,
+        _inventory_delegate_bump: u8,
+        quantity: u64,
+        max_total_price: u64,
+        max_payment_contribution: Option<u64>,
+    ) -> Result<()> {
+        let listing = ctx.accounts.listing.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
+
+        if !listing.is_resellable {
+            return Err(error!(StrangemoodError::ListingIsNotResellable));
+        }
+        if listing.is_suspended {
+            return Err(error!(StrangemoodError::ListingIsSuspended));
+        }
+        check_requirement(&listing, ctx.remaining_accounts, &ctx.accounts.purchaser.key())?;
+        check_payment_contribution(&charter, max_payment_contribution)?;
+
+        if quantity == 0 || quantity > ctx.accounts.resale_offer.quantity {
+            return Err(error!(StrangemoodError::ResaleOfferHasInsufficientQuantity));
+        }
+        let price = ctx.accounts.resale_offer.price;
+        let total = total_price(price, quantity, max_total_price)?;
+
+        fund_native_payment(
+            total,
+            &ctx.accounts.payment,
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
+
+        // Distribute payment
+        let splits = transfer_funds_with_seller(
+            total,
+            &listing,
+            &charter,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.payment.clone(),
+            *ctx.accounts.charter_treasury_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
+            *ctx.accounts.seller_deposit.clone(),
+            ctx.accounts.purchaser.clone(),
+        )?;
+
+        // Distribute votes
+        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
+        let votes = distribute_governance_tokens(
+            splits.to_charter_amount,
+            charter.expansion_rate,
+            charter_treasury.scalar,
+            charter.vote_contribution,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.charter_mint.clone(),
+            ctx.accounts.charter_mint_authority.to_account_info(),
+            charter_mint_authority_bump,
+            *ctx.accounts.listings_vote_deposit.clone(),
+            *ctx.accounts.charter_reserve.clone(),
+        )?;
+
+        thaw_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.seller_inventory,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+        )?;
+        thaw_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.inventory,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+        )?;
+
+        // Approve the delegate over the purchaser's inventory, like a purchase does
+        let delegated_amount = ctx.accounts.inventory.amount
+            .checked_add(quantity)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        approve_delegate(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.inventory.to_account_info(),
+            ctx.accounts.inventory_delegate.to_account_info(),
+            ctx.accounts.purchaser.to_account_info(),
+            delegated_amount,
+        )?;
+
+        // Move the listing tokens
         token_transfer_with_seed(
-            ctx.accounts.token_program.to_account_info(), 
-        ctx.accounts.stake.to_account_info(),
-            ctx.accounts.deposit.to_account_info(),
-        ctx.accounts.stake_authority.to_account_info(),
-            cmp::min(amount_to_transfer as u64, ctx.accounts.stake.amount),
-            b"token_authority", 
-            stake_authority_bump
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.seller_inventory.to_account_info(),
+            ctx.accounts.inventory.to_account_info(),
+            ctx.accounts.seller_inventory_delegate.to_account_info(),
+            quantity,
+            b"token_authority",
+            seller_inventory_delegate_bump,
         )?;
 
-        // Update cashier treasury's last epoch
-        cashier.last_withdraw_at = clock.epoch;
+        // Both inventories were thawed above, so reload them, or
+        // freeze_account would think they're still frozen.
+        ctx.accounts.seller_inventory.reload()?;
+        ctx.accounts.inventory.reload()?;
+        freeze_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.seller_inventory,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+        )?;
+        freeze_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.listing_mint,
+            &ctx.accounts.inventory,
+            &ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_authority_bump,
+        )?;
 
+        let resale_offer = &mut ctx.accounts.resale_offer;
+        resale_offer.quantity -= quantity;
+
+        emit!(Resold {
+            resale_offer: resale_offer.key(),
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            seller: resale_offer.seller,
+            purchaser: ctx.accounts.purchaser.key(),
+            inventory: ctx.accounts.inventory.key(),
+            quantity,
+            price,
+            splits,
+            votes,
+        });
+
+        // Close the offer once it's filled
+        if resale_offer.quantity == 0 {
+            close_native_account(
+                &ctx.accounts.resale_offer.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+            );
+        }
         Ok(())
     }
 
-    pub fn set_listing_suspension(ctx: Context<SetListingSuspension>, suspended: bool) -> Result<()> {
+    // Makes a listing a subscription, where each listing token bought adds
+    // subscription_period seconds to the inventory's Subscription. Set
+    // subscription_period to 0 to stop selling it as a subscription.
+    //
+    // Subscriptions that were already bought keep their expiry.
+    pub fn set_listing_subscription(
+        ctx: Context<SetListing>,
+        subscription_period: i64,
+        renewal_price: u64,
+    ) -> Result<()> {
+        if subscription_period < 0 {
+            return Err(error!(StrangemoodError::SubscriptionPeriodIsInvalid));
+        }
+
         let listing = &mut ctx.accounts.listing;
-        listing.is_suspended = suspended;
+        listing.subscription_period = subscription_period;
+        listing.renewal_price = renewal_price;
+
+        emit!(ListingSubscriptionSet {
+            listing: listing.key(),
+            subscription_period,
+            renewal_price,
+        });
+        Ok(())
+    }
 
+    // Fails unless the subscription is active. Apps can simulate this
+    // to check a subscription without trusting an RPC's clock.
+    pub fn check_subscription(ctx: Context<CheckSubscription>) -> Result<()> {
+        if !ctx.accounts.subscription.is_active_at(Clock::get()?.unix_timestamp) {
+            return Err(error!(StrangemoodError::SubscriptionHasExpired));
+        }
         Ok(())
-    } 
-}
+    }
 
-#[derive(Accounts)]
-#[instruction(listing_mint_authority_bump: u8, escrow_authority_bump:u8, inventory_delegate_bump: u8)]
-pub struct StartTrial<'info> {
+    // Opts a subscription in to renewing automatically from "payment", by
+    // approving the PDA ["token_authority", payment] to spend up to
+    // "allowance" from it.
+    //
+    // A token account only has one delegate, so the allowance is shared by
+    // every subscription that renews from the same payment account. Those
+    // subscriptions are counted by the payment account's AutoRenewalPayment,
+    // which is created by the first of them.
+    pub fn enable_auto_renew(
+        ctx: Context<EnableAutoRenew>,
+        _payment_delegate_bump: u8,
+        allowance: u64,
+    ) -> Result<()> {
+        approve_delegate(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.payment.to_account_info(),
+            ctx.accounts.payment_delegate.to_account_info(),
+            ctx.accounts.purchaser.to_account_info(),
+            allowance,
+        )?;
+
+        let payment_key = ctx.accounts.payment.key();
+        let payment_info = ctx.accounts.auto_renewal_payment.to_account_info();
+        let mut auto_renewal_payment = if payment_info.owner != &crate::ID {
+            let (_, bump) = Pubkey::find_program_address(
+                &[b"auto_renewal_payment", payment_key.as_ref()],
+                &crate::ID,
+            );
+            let seeds: &[&[u8]] = &[b"auto_renewal_payment", payment_key.as_ref(), &[bump]];
+            cpi::create_pda_account(
+                ctx.accounts.purchaser.to_account_info(),
+                payment_info.clone(),
+                ctx.accounts.system_program.to_account_info(),
+                AutoRenewalPayment::SPACE,
+                seeds,
+            )?;
+            AutoRenewalPayment {
+                is_initialized: true,
+                payment: payment_key,
+                purchaser: ctx.accounts.purchaser.key(),
+                renewals: 0,
+            }
+        } else {
+            AutoRenewalPayment::try_deserialize(&mut &payment_info.try_borrow_data()?[..])?
+        };
+        auto_renewal_payment.renewals = auto_renewal_payment
+            .renewals
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        let mut data = payment_info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data[..];
+        auto_renewal_payment.try_serialize(&mut writer)?;
+
+        let auto_renewal = &mut ctx.accounts.auto_renewal;
+        auto_renewal.is_initialized = true;
+        auto_renewal.subscription = ctx.accounts.subscription.key();
+        auto_renewal.purchaser = ctx.accounts.purchaser.key();
+        auto_renewal.payment = ctx.accounts.payment.key();
+
+        emit!(AutoRenewalEnabled {
+            auto_renewal: auto_renewal.key(),
+            subscription: auto_renewal.subscription,
+            purchaser: auto_renewal.purchaser,
+            payment: auto_renewal.payment,
+            allowance,
+        });
+        Ok(())
+    }
+
+    // Stops a subscription from renewing automatically. If no other
+    // subscription renews from the same payment account, this also revokes
+    // the payment account's delegate and closes its AutoRenewalPayment.
+    pub fn cancel_auto_renew(ctx: Context<CancelAutoRenew>) -> Result<()> {
+        let auto_renewal_payment = &mut ctx.accounts.auto_renewal_payment;
+        auto_renewal_payment.renewals = auto_renewal_payment.renewals.saturating_sub(1);
+
+        if auto_renewal_payment.renewals == 0 {
+            revoke_delegate(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.payment.to_account_info(),
+                ctx.accounts.purchaser.to_account_info(),
+            )?;
+            close_native_account(
+                &auto_renewal_payment.to_account_info(),
+                &ctx.accounts.purchaser.to_account_info(),
+            );
+        }
+
+        emit!(AutoRenewalCancelled {
+            auto_renewal: ctx.accounts.auto_renewal.key(),
+            subscription: ctx.accounts.subscription.key(),
+            renewals: auto_renewal_payment.renewals,
+        });
+        Ok(())
+    }
+
+    // Renews an expired subscription for one period, paid from its
+    // AutoRenewal's payment account and split like a purchase. Anyone
+    // can run this. Renewals don't mint another listing token.
+    //
+    // If the listing can't be renewed right now, because it's unavailable,
+    // suspended or not a subscription, or the payment account's allowance
+    // or balance can't cover the period, this succeeds without charging
+    // anything, and emits AutoRenewalFailed. All of those can change, so
+    // the AutoRenewal is kept, and the crank can try again later. Only the
+    // purchaser can close it, with cancel_auto_renew.
+    //
+    // If the listing splits its revenue, see lister_deposits for the
+    // accounts that this needs after its other accounts.
+    pub fn renew_subscription<'info>(
+        ctx: Context<'_, '_, '_, 'info, RenewSubscription<'info>>,
+        charter_mint_authority_bump: u8,
+        payment_delegate_bump: u8,
+    ) -> Result<()> {
+        let listing = ctx.accounts.listing.clone().into_inner();
+        let charter = ctx.accounts.charter.active_at(Clock::get()?.unix_timestamp);
+
+        let now = Clock::get()?.unix_timestamp;
+        if ctx.accounts.subscription.is_active_at(now) {
+            return Err(error!(StrangemoodError::SubscriptionIsStillActive));
+        }
+
+        let price = if listing.renewal_price != 0 {
+            listing.renewal_price
+        } else {
+            listing.price_at(now)
+        };
+
+        // Rather than failing, so the crank can tell why it didn't renew
+        let can_renew = check_purchasable(&listing, false).is_ok() && listing.subscription_period != 0;
+        let payment = &ctx.accounts.payment;
+        let can_pay = payment.delegate == COption::Some(ctx.accounts.payment_delegate.key())
+            && payment.delegated_amount >= price
+            && payment.amount >= price;
+        if !can_renew || !can_pay {
+            emit!(AutoRenewalFailed {
+                auto_renewal: ctx.accounts.auto_renewal.key(),
+                subscription: ctx.accounts.subscription.key(),
+                price,
+            });
+            return Ok(());
+        }
+
+        // Distribute payment
+        let splits = transfer_funds_from_escrow(
+            price,
+            &listing,
+            &charter,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.payment.clone(),
+            *ctx.accounts.charter_treasury_deposit.clone(),
+            &lister_deposits(
+                ctx.accounts.listing.key(),
+                &listing,
+                ctx.accounts.listings_payment_deposit.to_account_info(),
+                ctx.remaining_accounts,
+            )?,
+            ctx.accounts.payment_delegate.clone(),
+            payment_delegate_bump
+        )?;
+
+        // Distribute votes
+        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
+        let votes = distribute_governance_tokens(
+            splits.to_charter_amount,
+            charter.expansion_rate,
+            charter_treasury.scalar,
+            charter.vote_contribution,
+            ctx.accounts.token_program.clone(),
+            *ctx.accounts.charter_mint.clone(),
+            ctx.accounts.charter_mint_authority.to_account_info(),
+            charter_mint_authority_bump,
+            *ctx.accounts.listings_vote_deposit.clone(),
+            *ctx.accounts.charter_reserve.clone(),
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.extend(now, listing.subscription_period, 1)?;
+
+        emit!(SubscriptionRenewed {
+            subscription: subscription.key(),
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            purchaser: subscription.purchaser,
+            price,
+            expires_at: subscription.expires_at,
+            splits,
+            votes,
+        });
+        Ok(())
+    }
+
+    // Creates a Bundle at the PDA ["bundle", authority, nonce]. The listings
+    // are passed after the instruction's other accounts, and every listing's
+    // authority must sign, either as the bundle's authority or as another
+    // account after the listings.
+    //
+    // The price is split between the listings in proportion to weights,
+    // which has one weight per listing. If every weight is 0, it's split evenly.
+    pub fn init_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitBundle<'info>>,
+        nonce: u64,
+        listings: Vec<Pubkey>,
+        weights: Vec<u64>,
+        price: u64,
+        uri: String,
+    ) -> Result<()> {
+        if listings.len() < 2
+            || listings.len() > Bundle::MAX_LISTINGS
+            || weights.len() != listings.len()
+            || uri.len() > Bundle::MAX_URI_LENGTH
+        {
+            return Err(error!(StrangemoodError::BundleIsInvalid));
+        }
+
+        let authority = ctx.accounts.authority.key();
+        let mut has_authority = false;
+        for (i, key) in listings.iter().enumerate() {
+            if listings[..i].contains(key) {
+                return Err(error!(StrangemoodError::BundleIsInvalid));
+            }
+
+            let info = ctx
+                .remaining_accounts
+                .iter()
+                .find(|a| a.key() == *key)
+                .ok_or(StrangemoodError::MissingBundleListing)?;
+            let listing = Account::<Listing>::try_from(info)?;
+            if listing.charter != ctx.accounts.charter.key() {
+                return Err(error!(StrangemoodError::ListingHasUnexpectedCharter));
+            }
+
+            let is_signed = listing.authority == authority
+                || ctx
+                    .remaining_accounts
+                    .iter()
+                    .any(|a| a.key() == listing.authority && a.is_signer);
+            if !is_signed {
+                return Err(error!(StrangemoodError::BundleIsMissingListingAuthority));
+            }
+            has_authority = has_authority || listing.authority == authority;
+        }
+
+        // The bundle's authority has to own at least one of its listings
+        if !has_authority {
+            return Err(error!(StrangemoodError::BundleHasUnexpectedAuthority));
+        }
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.is_initialized = true;
+        bundle.authority = authority;
+        bundle.charter = ctx.accounts.charter.key();
+        bundle.listings = listings.clone();
+        bundle.weights = weights.clone();
+        bundle.price = price;
+        bundle.is_available = true;
+        bundle.nonce = nonce;
+        bundle.uri = uri.clone();
+
+        emit!(BundleInitialized {
+            bundle: bundle.key(),
+            authority,
+            charter: bundle.charter,
+            listings,
+            weights,
+            price,
+            uri,
+        });
+        Ok(())
+    }
+
+    pub fn set_bundle_availability(ctx: Context<SetBundle>, is_available: bool) -> Result<()> {
+        ctx.accounts.bundle.is_available = is_available;
+
+        emit!(BundleAvailabilitySet {
+            bundle: ctx.accounts.bundle.key(),
+            is_available,
+        });
+        Ok(())
+    }
+
+    // Creates a Coupon at the PDA ["coupon", listing, nonce]. Set exactly
+    // one of discount_amount or discount_rate.
+    pub fn init_coupon(
+        ctx: Context<InitCoupon>,
+        nonce: u64,
+        discount_amount: u64,
+        discount_rate: u64,
+        max_uses: u64,
+        expires_at: i64,
+        purchaser: Option<Pubkey>,
+    ) -> Result<()> {
+        if (discount_amount == 0) == (discount_rate == 0) || discount_rate > math::BASIS_POINTS {
+            return Err(error!(StrangemoodError::CouponDiscountIsInvalid));
+        }
+
+        let coupon = &mut ctx.accounts.coupon;
+        coupon.is_initialized = true;
+        coupon.listing = ctx.accounts.listing.key();
+        coupon.discount_amount = discount_amount;
+        coupon.discount_rate = discount_rate;
+        coupon.max_uses = max_uses;
+        coupon.uses = 0;
+        coupon.expires_at = expires_at;
+        coupon.purchaser = purchaser;
+        coupon.nonce = nonce;
+
+        emit!(CouponInitialized {
+            coupon: coupon.key(),
+            listing: coupon.listing,
+            discount_amount,
+            discount_rate,
+            max_uses,
+            expires_at,
+            purchaser,
+        });
+        Ok(())
+    }
+
+    // Closes a coupon so it can't be redeemed, and returns its rent
+    // to the listing's authority.
+    pub fn close_coupon(ctx: Context<CloseCoupon>) -> Result<()> {
+        emit!(CouponClosed {
+            coupon: ctx.accounts.coupon.key(),
+            listing: ctx.accounts.listing.key(),
+        });
+        Ok(())
+    }
+
+    // Splits the lister's revenue between several token accounts, which
+    // must be passed after the instruction's other accounts.
+    pub fn init_listing_splits<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitListingSplits<'info>>,
+        recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
+        check_split_recipients(&recipients, ctx.remaining_accounts, ctx.accounts.payment_deposit.mint)?;
+
+        let splits = &mut ctx.accounts.listing_splits;
+        splits.is_initialized = true;
+        splits.listing = ctx.accounts.listing.key();
+        splits.recipients = recipients.clone();
+        ctx.accounts.listing.has_splits = true;
+
+        emit!(ListingSplitsSet {
+            listing: ctx.accounts.listing.key(),
+            recipients,
+        });
+        Ok(())
+    }
+
+    // Replaces a listing's split recipients. See init_listing_splits.
+    pub fn set_listing_splits<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetListingSplits<'info>>,
+        recipients: Vec<SplitRecipient>,
+    ) -> Result<()> {
+        check_split_recipients(&recipients, ctx.remaining_accounts, ctx.accounts.payment_deposit.mint)?;
+
+        ctx.accounts.listing_splits.recipients = recipients.clone();
+
+        emit!(ListingSplitsSet {
+            listing: ctx.accounts.listing.key(),
+            recipients,
+        });
+        Ok(())
+    }
+
+    // Stops splitting a listing's revenue, so that it all goes to the
+    // listing's payment_deposit again.
+    pub fn close_listing_splits(ctx: Context<CloseListingSplits>) -> Result<()> {
+        ctx.accounts.listing.has_splits = false;
+
+        emit!(ListingSplitsClosed {
+            listing: ctx.accounts.listing.key(),
+        });
+        Ok(())
+    }
+
+    pub fn set_listing_deposits(ctx: Context<SetListingDeposit>) -> Result<()> {
+        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
+        ctx.accounts.listing.payment_deposit = ctx.accounts.payment_deposit.key();
+
+        emit!(ListingDepositsSet {
+            listing: ctx.accounts.listing.key(),
+            payment_deposit: ctx.accounts.payment_deposit.key(),
+            vote_deposit: ctx.accounts.vote_deposit.key(),
+        });
+        Ok(())
+    }
+
+    // Hands the listing to a different authority in two steps, so a typo'd
+    // key can't strand it. The new authority has to accept, and until it
+    // does, the current authority keeps control and can cancel.
+    pub fn propose_listing_authority(ctx: Context<ProposeListingAuthority>) -> Result<()> {
+        ctx.accounts.listing.pending_authority = Some(ctx.accounts.new_authority.key());
+
+        emit!(ListingAuthorityProposed {
+            listing: ctx.accounts.listing.key(),
+            authority: ctx.accounts.authority.key(),
+            pending_authority: ctx.accounts.new_authority.key(),
+        });
+        Ok(())
+    }
+
+    pub fn accept_listing_authority(ctx: Context<AcceptListingAuthority>) -> Result<()> {
+        let listing = &mut ctx.accounts.listing;
+        listing.authority = ctx.accounts.new_authority.key();
+        listing.pending_authority = None;
+
+        emit!(ListingAuthoritySet {
+            listing: listing.key(),
+            authority: listing.authority,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_listing_authority_transfer(ctx: Context<SetListing>) -> Result<()> {
+        let pending_authority = ctx
+            .accounts
+            .listing
+            .pending_authority
+            .take()
+            .ok_or(StrangemoodError::NoPendingAuthority)?;
+
+        emit!(ListingAuthorityTransferCancelled {
+            listing: ctx.accounts.listing.key(),
+            pending_authority,
+        });
+        Ok(())
+    }
+
+    // Migrate a listing to a different charter
+    pub fn set_listing_charter(ctx: Context<SetListingCharter>) -> Result<()> {
+        ctx.accounts.listing.charter = ctx.accounts.charter.key();
+        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
+
+        emit!(ListingCharterSet {
+            listing: ctx.accounts.listing.key(),
+            charter: ctx.accounts.charter.key(),
+            vote_deposit: ctx.accounts.vote_deposit.key(),
+        });
+        Ok(())
+    }
+
+    // Rate changes are queued, and take effect after the charter's change_delay.
+    pub fn set_charter_expansion_rate(
+        ctx: Context<SetCharter>,
+        expansion_rate: u64,
+    ) -> Result<()> {
+        let change = ctx
+            .accounts
+            .charter
+            .queue_change(Clock::get()?.unix_timestamp, |c| c.expansion_rate = expansion_rate)?;
+
+        emit_charter_change(ctx.accounts.charter.key(), change);
+        Ok(())
+    }
+
+    pub fn set_charter_contribution_rate(
+        ctx: Context<SetCharter>,
+        payment_contribution: u64,
+        vote_contribution: u64
+    ) -> Result<()> {
+        let change = ctx.accounts.charter.queue_change(Clock::get()?.unix_timestamp, |c| {
+            c.payment_contribution = payment_contribution;
+            c.vote_contribution = vote_contribution;
+        })?;
+
+        emit_charter_change(ctx.accounts.charter.key(), change);
+        Ok(())
+    }
+
+    // Lowers the caps on the contribution rates. Unlike the rates themselves,
+    // this takes effect right away, since it can only ever help listers.
+    pub fn set_charter_contribution_caps(
+        ctx: Context<SetCharter>,
+        max_payment_contribution: u64,
+        max_vote_contribution: u64,
+    ) -> Result<()> {
+        let charter = &mut ctx.accounts.charter;
+        set_contribution_caps(charter, max_payment_contribution, max_vote_contribution)?;
+
+        emit!(CharterContributionCapsSet {
+            charter: charter.key(),
+            max_payment_contribution,
+            max_vote_contribution,
+        });
+        Ok(())
+    }
+
+    // Lowering the delay is queued behind the current delay like any other
+    // change, so it can't be used to skip the notice period.
+    pub fn set_charter_change_delay(ctx: Context<SetCharter>, change_delay: i64) -> Result<()> {
+        if change_delay < 0 {
+            return Err(error!(StrangemoodError::ChangeDelayIsInvalid));
+        }
+        let change = ctx
+            .accounts
+            .charter
+            .queue_change(Clock::get()?.unix_timestamp, |c| c.change_delay = change_delay)?;
+
+        emit_charter_change(ctx.accounts.charter.key(), change);
+        Ok(())
+    }
+
+    // Migrates the charter to a different authority, like a new governance program.
+    // The new authority has to accept with AcceptCharterAuthority.
+    pub fn propose_charter_authority(ctx: Context<ProposeCharterAuthority>) -> Result<()> {
+        ctx.accounts.charter.pending_authority = Some(ctx.accounts.new_authority.key());
+
+        emit!(CharterAuthorityProposed {
+            charter: ctx.accounts.charter.key(),
+            authority: ctx.accounts.authority.key(),
+            pending_authority: ctx.accounts.new_authority.key(),
+        });
+        Ok(())
+    }
+
+    pub fn accept_charter_authority(ctx: Context<AcceptCharterAuthority>) -> Result<()> {
+        let charter = &mut ctx.accounts.charter;
+        charter.authority = ctx.accounts.new_authority.key();
+        charter.pending_authority = None;
+
+        emit!(CharterAuthoritySet {
+            charter: charter.key(),
+            authority: charter.authority,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_charter_authority_transfer(ctx: Context<SetCharter>) -> Result<()> {
+        let pending_authority = ctx
+            .accounts
+            .charter
+            .pending_authority
+            .take()
+            .ok_or(StrangemoodError::NoPendingAuthority)?;
+
+        emit!(CharterAuthorityTransferCancelled {
+            charter: ctx.accounts.charter.key(),
+            pending_authority,
+        });
+        Ok(())
+    }
+
+    // Moves the host where off-chain services for this charter are found.
+    pub fn set_charter_uri(ctx: Context<SetCharter>, uri: String) -> Result<()> {
+        if uri.len() > Charter::MAX_URI_LENGTH {
+            return Err(error!(StrangemoodError::UriIsTooLong));
+        }
+        ctx.accounts.charter.uri = uri.clone();
+
+        emit!(CharterUriSet {
+            charter: ctx.accounts.charter.key(),
+            uri,
+        });
+        Ok(())
+    }
+
+    // Changes the number of epochs over which cashiers unlock
+    // stake_withdraw_amount of their stake. Applies to withdraws
+    // that haven't happened yet.
+    pub fn set_charter_withdraw_period(
+        ctx: Context<SetCharter>,
+        withdraw_period: u64,
+    ) -> Result<()> {
+        if withdraw_period == 0 {
+            return Err(error!(StrangemoodError::WithdrawPeriodIsInvalid));
+        }
+        ctx.accounts.charter.withdraw_period = withdraw_period;
+
+        emit!(CharterWithdrawPeriodSet {
+            charter: ctx.accounts.charter.key(),
+            withdraw_period,
+        });
+        Ok(())
+    }
+
+    pub fn set_charter_stake_withdraw_amount(
+        ctx: Context<SetCharter>,
+        stake_withdraw_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.charter.stake_withdraw_amount = stake_withdraw_amount;
+
+        emit!(CharterStakeWithdrawAmountSet {
+            charter: ctx.accounts.charter.key(),
+            stake_withdraw_amount,
+        });
+        Ok(())
+    }
+
+    pub fn set_charter_reserve(ctx: Context<SetCharterReserve>) -> Result<()> {
+        ctx.accounts.charter.reserve = ctx.accounts.reserve.key();
+
+        emit!(CharterReserveSet {
+            charter: ctx.accounts.charter.key(),
+            reserve: ctx.accounts.reserve.key(),
+        });
+        Ok(())
+    }
+
+    pub fn init_charter_treasury(ctx: Context<InitCharterTreasury>, scalar: u64) -> Result<()> {
+        // Purchases divide by the scalar
+        if scalar == 0 {
+            return Err(error!(StrangemoodError::ScalarIsInvalid));
+        }
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.is_initialized = true;
+        treasury.charter = ctx.accounts.charter.key();
+        treasury.deposit = ctx.accounts.deposit.key(); 
+        treasury.mint = ctx.accounts.mint.key();
+        treasury.scalar = scalar; 
+        treasury.version = ACCOUNT_VERSION;
+
+        emit!(CharterTreasuryInitialized {
+            treasury: treasury.key(),
+            charter: treasury.charter,
+            deposit: treasury.deposit,
+            mint: treasury.mint,
+            scalar,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_charter_treasury_scalar(ctx: Context<SetCharterTreasuryExpansionScalar>, scalar: u64) -> Result<()> {
+        if scalar == 0 {
+            return Err(error!(StrangemoodError::ScalarIsInvalid));
+        }
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.scalar = scalar; 
+
+        emit!(CharterTreasuryScalarSet {
+            treasury: treasury.key(),
+            scalar,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_charter_treasury_deposit(ctx: Context<SetCharterTreasuryDeposit>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.deposit = ctx.accounts.deposit.key(); 
+
+        emit!(CharterTreasuryDepositSet {
+            treasury: treasury.key(),
+            deposit: treasury.deposit,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_cashier(ctx: Context<InitCashier>, _stake_authority_bump: u8, uri: String) -> Result<()> {
+        if uri.len() > Cashier::MAX_URI_LENGTH {
+            return Err(error!(StrangemoodError::UriIsTooLong));
+        }
+
+        let cashier = &mut ctx.accounts.cashier;
+        cashier.is_initialized = true;
+        cashier.charter = ctx.accounts.charter.key();
+        cashier.stake = ctx.accounts.stake.key();
+        cashier.authority = ctx.accounts.authority.key();
+        cashier.last_withdraw_at = ctx.accounts.clock.epoch;
+        cashier.uri = uri;
+
+        emit!(CashierInitialized {
+            cashier: cashier.key(),
+            charter: cashier.charter,
+            stake: cashier.stake,
+            authority: cashier.authority,
+            uri: cashier.uri.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn set_cashier_uri(ctx: Context<SetCashier>, uri: String) -> Result<()> {
+        if uri.len() > Cashier::MAX_URI_LENGTH {
+            return Err(error!(StrangemoodError::UriIsTooLong));
+        }
+        ctx.accounts.cashier.uri = uri.clone();
+
+        emit!(CashierUriSet {
+            cashier: ctx.accounts.cashier.key(),
+            uri,
+        });
+        Ok(())
+    }
+
+    pub fn propose_cashier_authority(ctx: Context<ProposeCashierAuthority>) -> Result<()> {
+        ctx.accounts.cashier.pending_authority = Some(ctx.accounts.new_authority.key());
+
+        emit!(CashierAuthorityProposed {
+            cashier: ctx.accounts.cashier.key(),
+            authority: ctx.accounts.authority.key(),
+            pending_authority: ctx.accounts.new_authority.key(),
+        });
+        Ok(())
+    }
+
+    pub fn accept_cashier_authority(ctx: Context<AcceptCashierAuthority>) -> Result<()> {
+        let cashier = &mut ctx.accounts.cashier;
+        cashier.authority = ctx.accounts.new_authority.key();
+        cashier.pending_authority = None;
+
+        emit!(CashierAuthoritySet {
+            cashier: cashier.key(),
+            authority: cashier.authority,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_cashier_authority_transfer(ctx: Context<SetCashier>) -> Result<()> {
+        let pending_authority = ctx
+            .accounts
+            .cashier
+            .pending_authority
+            .take()
+            .ok_or(StrangemoodError::NoPendingAuthority)?;
+
+        emit!(CashierAuthorityTransferCancelled {
+            cashier: ctx.accounts.cashier.key(),
+            pending_authority,
+        });
+        Ok(())
+    }
+
+    pub fn init_cashier_treasury(ctx: Context<InitCashierTreasury>, _escrow_authority_bump: u8) -> Result<()> {
+        let treasury = &mut ctx.accounts.cashier_treasury; 
+        
+        treasury.is_initialized = true; 
+        treasury.cashier = ctx.accounts.cashier.key();
+        treasury.deposit = ctx.accounts.deposit.key();
+        treasury.escrow = ctx.accounts.escrow.key();
+        treasury.mint = ctx.accounts.mint.key();
+        treasury.last_withdraw_at = ctx.accounts.clock.epoch;
+
+        emit!(CashierTreasuryInitialized {
+            cashier_treasury: treasury.key(),
+            cashier: treasury.cashier,
+            escrow: treasury.escrow,
+            deposit: treasury.deposit,
+            mint: treasury.mint,
+        });
+
+        Ok(())
+    }
+
+    pub fn burn_cashier_stake(ctx: Context<BurnCashierStake>,  mint_authority_bump: u8, amount: u64) -> Result<()> {   
+        burn(
+            ctx.accounts.token_program.to_account_info(), 
+    ctx.accounts.mint.to_account_info(),
+ctx.accounts.stake.to_account_info(),
+ctx.accounts.stake_authority.to_account_info(),
+        mint_authority_bump,
+        amount
+        )?;
+
+        emit!(CashierStakeBurned {
+            charter: ctx.accounts.charter.key(),
+            cashier: ctx.accounts.cashier.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // A decentralized crank that moves money from the the cashier's escrow to their deposit.
+    pub fn withdraw_cashier_treasury(ctx: Context<WithdrawCashierTreasury>, _mint_authority_bump: u8, cashier_escrow_bump: u8) -> Result<()> {
+        let charter = ctx.accounts.charter.clone().into_inner();
+        let charter_treasury = ctx.accounts.charter_treasury.clone().into_inner();
+        let stake = ctx.accounts.stake.clone().into_inner();
+        let clock = ctx.accounts.clock.clone();
+        let cashier_treasury = &mut ctx.accounts.cashier_treasury;
+
+        // Calculate the amount to transfer, which is the stake (scaled by the treasury)
+        // that unlocks per period, times the fraction of the period that has passed.
+        let amount_per_period = math::apply_rate(stake.amount, charter_treasury.scalar)?;
+        let epochs_passed = clock.epoch.checked_sub(cashier_treasury.last_withdraw_at).unwrap();
+        let amount_to_transfer = math::mul_div_saturating(amount_per_period, epochs_passed, charter.withdraw_period)?;
+
+        // Transfer what we can
+        let amount = cmp::min(amount_to_transfer, ctx.accounts.escrow.amount);
+        token_transfer_with_seed(
+            ctx.accounts.token_program.to_account_info(), 
+        ctx.accounts.escrow.to_account_info(),
+         ctx.accounts.deposit.to_account_info(),
+        ctx.accounts.escrow_authority.to_account_info(),
+            amount,
+            b"cashier.escrow", 
+            cashier_escrow_bump
+        )?;
+
+        // Update cashier treasury's last epoch
+        cashier_treasury.last_withdraw_at = clock.epoch;
+
+        emit!(CashierTreasuryWithdrawn {
+            cashier: ctx.accounts.cashier.key(),
+            cashier_treasury: cashier_treasury.key(),
+            amount,
+            epoch: clock.epoch,
+        });
+
+        Ok(())
+    }
+
+    // A decentralized crank that moves money from the the cashier's escrow to their deposit.
+    pub fn withdraw_cashier_stake(ctx: Context<WithdrawCashierStake>, stake_authority_bump: u8) -> Result<()> {
+        let charter = ctx.accounts.charter.clone().into_inner();
+        let clock = ctx.accounts.clock.clone();
+        let cashier = &mut ctx.accounts.cashier;
+
+        // Calculate the amount to transfer
+        let amount_per_period = charter.stake_withdraw_amount;
+        let epochs_passed = clock.epoch.checked_sub(cashier.last_withdraw_at).unwrap();
+        let amount_to_transfer = math::mul_div_saturating(amount_per_period, epochs_passed, charter.withdraw_period)?;
+
+        // Transfer what we can
+        let amount = cmp::min(amount_to_transfer, ctx.accounts.stake.amount);
+        token_transfer_with_seed(
+            ctx.accounts.token_program.to_account_info(), 
+        ctx.accounts.stake.to_account_info(),
+            ctx.accounts.deposit.to_account_info(),
+        ctx.accounts.stake_authority.to_account_info(),
+            amount,
+            b"token_authority", 
+            stake_authority_bump
+        )?;
+
+        // Update cashier treasury's last epoch
+        cashier.last_withdraw_at = clock.epoch;
+
+        emit!(CashierStakeWithdrawn {
+            cashier: cashier.key(),
+            amount,
+            epoch: clock.epoch,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_listing_suspension(ctx: Context<SetListingSuspension>, suspended: bool) -> Result<()> {
+        let listing = &mut ctx.accounts.listing;
+        listing.is_suspended = suspended;
+
+        emit!(ListingSuspensionSet {
+            listing: listing.key(),
+            charter: listing.charter,
+            is_suspended: suspended,
+        });
+
+        Ok(())
+    } 
+
+    // Hands the charter to a native governance, after which it only
+    // changes through proposals that pass a vote. The governance is a PDA
+    // of this program, so it can't be mistyped, and it can't sign to accept
+    // the charter the way a new authority normally would.
+    //
+    // The charter's reserve has to be owned by the charter's authority,
+    // and is handed to the governance too, so SpendReserve can sign for it.
+    pub fn init_governance(
+        ctx: Context<InitGovernance>,
+        quorum: u64,
+        threshold: u64,
+        voting_period: i64,
+    ) -> Result<()> {
+        Governance::check_config(threshold, voting_period)?;
+
+        let governance = &mut ctx.accounts.governance;
+        governance.is_initialized = true;
+        governance.charter = ctx.accounts.charter.key();
+        governance.quorum = quorum;
+        governance.threshold = threshold;
+        governance.voting_period = voting_period;
+        governance.proposal_count = 0;
+
+        cpi::set_token_owner(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.reserve.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            governance.key(),
+        )?;
+
+        let charter = &mut ctx.accounts.charter;
+        charter.authority = governance.key();
+        charter.pending_authority = None;
+
+        emit!(GovernanceInitialized {
+            governance: governance.key(),
+            charter: charter.key(),
+            quorum,
+            threshold,
+            voting_period,
+        });
+        emit!(CharterAuthoritySet {
+            charter: charter.key(),
+            authority: charter.authority,
+        });
+        Ok(())
+    }
+
+    // Anyone can propose a change; it's the vote that decides.
+    pub fn init_proposal(ctx: Context<InitProposal>, action: ProposalAction) -> Result<()> {
+        match action {
+            ProposalAction::SetGovernanceConfig { threshold, voting_period, .. } => {
+                Governance::check_config(threshold, voting_period)?;
+            }
+            ProposalAction::SetCharterChangeDelay { change_delay } if change_delay < 0 => {
+                return Err(error!(StrangemoodError::ChangeDelayIsInvalid));
+            }
+            ProposalAction::InitGrant { amount, vesting, .. } => {
+                Grant::check_terms(amount, vesting)?;
+            }
+            _ => {}
+        }
+
+        let governance = &mut ctx.accounts.governance;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.is_initialized = true;
+        proposal.governance = governance.key();
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.index = governance.proposal_count;
+        proposal.action = action.clone();
+        proposal.quorum = governance.quorum;
+        proposal.threshold = governance.threshold;
+        proposal.voting_ends_at = ctx
+            .accounts
+            .clock
+            .unix_timestamp
+            .checked_add(governance.voting_period)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        proposal.executable_until = proposal
+            .voting_ends_at
+            .checked_add(Governance::EXECUTION_PERIOD)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        proposal.votes_for = 0;
+        proposal.votes_against = 0;
+        proposal.is_executed = false;
+
+        governance.proposal_count = governance
+            .proposal_count
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        emit!(ProposalInitialized {
+            proposal: proposal.key(),
+            governance: governance.key(),
+            proposer: proposal.proposer,
+            index: proposal.index,
+            action,
+            voting_ends_at: proposal.voting_ends_at,
+            executable_until: proposal.executable_until,
+        });
+        Ok(())
+    }
+
+    // Locks "amount" charter tokens in an escrow as a vote for or against
+    // a proposal, until the vote is withdrawn. This stands in for a
+    // snapshot of balances, which Solana has no history to take; see
+    // VoteRecord.
+    pub fn cast_vote(
+        ctx: Context<CastVote>,
+        _escrow_authority_bump: u8,
+        amount: u64,
+        approve: bool,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Err(error!(StrangemoodError::VoteAmountIsInvalid));
+        }
+        if ctx.accounts.clock.unix_timestamp >= ctx.accounts.proposal.voting_ends_at {
+            return Err(error!(StrangemoodError::VotingHasEnded));
+        }
+
+        token_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.source.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.voter.to_account_info(),
+            amount,
+        )?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        if approve {
+            proposal.votes_for = proposal
+                .votes_for
+                .checked_add(amount)
+                .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        } else {
+            proposal.votes_against = proposal
+                .votes_against
+                .checked_add(amount)
+                .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        }
+
+        let vote = &mut ctx.accounts.vote;
+        vote.is_initialized = true;
+        vote.proposal = proposal.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.escrow = ctx.accounts.escrow.key();
+        vote.amount = amount;
+        vote.approve = approve;
+
+        emit!(VoteCast {
+            vote: vote.key(),
+            proposal: vote.proposal,
+            voter: vote.voter,
+            amount,
+            approve,
+        });
+        Ok(())
+    }
+
+    // Returns a vote's tokens to the voter. While voting is still open,
+    // this also takes the vote back out of the proposal's tally.
+    pub fn withdraw_vote(ctx: Context<WithdrawVote>, escrow_authority_bump: u8) -> Result<()> {
+        let vote = ctx.accounts.vote.clone().into_inner();
+
+        let proposal = &mut ctx.accounts.proposal;
+        let is_retracted = ctx.accounts.clock.unix_timestamp < proposal.voting_ends_at;
+        if is_retracted {
+            if vote.approve {
+                proposal.votes_for = proposal.votes_for.saturating_sub(vote.amount);
+            } else {
+                proposal.votes_against = proposal.votes_against.saturating_sub(vote.amount);
+            }
+        }
+
+        token_transfer_with_seed(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.destination.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            vote.amount,
+            b"token_authority",
+            escrow_authority_bump,
+        )?;
+        close_token_escrow_account(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.voter.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            escrow_authority_bump,
+        )?;
+
+        emit!(VoteWithdrawn {
+            vote: ctx.accounts.vote.key(),
+            proposal: vote.proposal,
+            voter: vote.voter,
+            amount: vote.amount,
+            is_retracted,
+        });
+        Ok(())
+    }
+
+    // Carries out a proposal once voting has ended, if it passed, until
+    // its executable_until. Anyone can run it. The governance is the charter's authority, so it signs for
+    // anything the charter's authority could do, like spending the reserve.
+    //
+    // Accounts the action needs are passed in remaining_accounts:
+    //   SetListingSuspension: the listing
+    //   SpendReserve: the charter's reserve and the destination
+    //   InitGrant: the treasury, its deposit, and the grant
+    //   CancelGrant: the grant, and its payer
+    pub fn execute_proposal<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteProposal<'info>>,
+        governance_bump: u8,
+    ) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        if ctx.accounts.clock.unix_timestamp < proposal.voting_ends_at {
+            return Err(error!(StrangemoodError::VotingHasNotEnded));
+        }
+        if ctx.accounts.clock.unix_timestamp > proposal.executable_until {
+            return Err(error!(StrangemoodError::ProposalHasExpired));
+        }
+        if proposal.is_executed {
+            return Err(error!(StrangemoodError::ProposalHasBeenExecuted));
+        }
+        if !proposal.has_passed() {
+            return Err(error!(StrangemoodError::ProposalHasNotPassed));
+        }
+
+        let charter_key = ctx.accounts.charter.key();
+        let action = proposal.action.clone();
+        match action.clone() {
+            ProposalAction::SetCharterExpansionRate { expansion_rate } => {
+                let change = ctx
+                    .accounts
+                    .charter
+                    .queue_change(ctx.accounts.clock.unix_timestamp, |c| c.expansion_rate = expansion_rate)?;
+
+                emit_charter_change(charter_key, change);
+            }
+            ProposalAction::SetCharterContributionRate { payment_contribution, vote_contribution } => {
+                let change = ctx.accounts.charter.queue_change(ctx.accounts.clock.unix_timestamp, |c| {
+                    c.payment_contribution = payment_contribution;
+                    c.vote_contribution = vote_contribution;
+                })?;
+
+                emit_charter_change(charter_key, change);
+            }
+            ProposalAction::SetCharterContributionCaps { max_payment_contribution, max_vote_contribution } => {
+                set_contribution_caps(&mut ctx.accounts.charter, max_payment_contribution, max_vote_contribution)?;
+
+                emit!(CharterContributionCapsSet {
+                    charter: charter_key,
+                    max_payment_contribution,
+                    max_vote_contribution,
+                });
+            }
+            ProposalAction::SetCharterChangeDelay { change_delay } => {
+                let change = ctx
+                    .accounts
+                    .charter
+                    .queue_change(ctx.accounts.clock.unix_timestamp, |c| c.change_delay = change_delay)?;
+
+                emit_charter_change(charter_key, change);
+            }
+            ProposalAction::SetListingSuspension { listing, suspended } => {
+                let listing_info = ctx
+                    .remaining_accounts
+                    .iter()
+                    .find(|a| a.key() == listing)
+                    .ok_or(StrangemoodError::MissingProposalAccount)?;
+                let mut listing = Account::<Listing>::try_from(listing_info)?;
+                if listing.charter != charter_key {
+                    return Err(error!(StrangemoodError::ListingHasUnexpectedCharter));
+                }
+                listing.is_suspended = suspended;
+                listing.exit(&crate::ID)?;
+
+                emit!(ListingSuspensionSet {
+                    listing: listing.key(),
+                    charter: charter_key,
+                    is_suspended: suspended,
+                });
+            }
+            ProposalAction::SpendReserve { destination, amount } => {
+                let reserve = ctx.accounts.charter.reserve;
+                let find = |key: Pubkey| {
+                    ctx.remaining_accounts
+                        .iter()
+                        .find(|a| a.key() == key)
+                        .ok_or(StrangemoodError::MissingProposalAccount)
+                };
+                let reserve_info = find(reserve)?;
+                let destination_info = find(destination)?;
+
+                cpi::token_transfer_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    reserve_info.clone(),
+                    destination_info.clone(),
+                    ctx.accounts.governance.to_account_info(),
+                    amount,
+                    &[b"governance", charter_key.as_ref(), &[governance_bump]],
+                )?;
+
+                emit!(ReserveSpent {
+                    charter: charter_key,
+                    reserve,
+                    destination,
+                    amount,
+                });
+            }
+            ProposalAction::InitGrant { treasury, recipient, nonce, amount, vesting } => {
+                init_grant_for_proposal(
+                    ctx.remaining_accounts,
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    charter_key,
+                    treasury,
+                    recipient,
+                    nonce,
+                    amount,
+                    vesting,
+                )?;
+            }
+            ProposalAction::CancelGrant { grant } => {
+                let find = |key: Pubkey| {
+                    ctx.remaining_accounts
+                        .iter()
+                        .find(|a| a.key() == key)
+                        .ok_or(StrangemoodError::MissingProposalAccount)
+                };
+                let grant_info = find(grant)?;
+                let mut grant = Account::<Grant>::try_from(grant_info)?;
+                if grant.charter != charter_key {
+                    return Err(error!(StrangemoodError::GrantHasUnexpectedCharter));
+                }
+                grant.cancel(ctx.accounts.clock.unix_timestamp)?;
+
+                emit!(GrantCancelled {
+                    grant: grant.key(),
+                    amount: grant.amount,
+                });
+
+                if grant.claimed == grant.amount {
+                    let payer_info = find(grant.payer)?;
+                    close_native_account(grant_info, payer_info);
+
+                    emit!(GrantClosed {
+                        grant: grant.key(),
+                        claimed: grant.claimed,
+                    });
+                } else {
+                    grant.exit(&crate::ID)?;
+                }
+            }
+            ProposalAction::SetCharterAuthority { authority } => {
+                ctx.accounts.charter.pending_authority = Some(authority);
+
+                emit!(CharterAuthorityProposed {
+                    charter: charter_key,
+                    authority: ctx.accounts.governance.key(),
+                    pending_authority: authority,
+                });
+            }
+            ProposalAction::SetGovernanceConfig { quorum, threshold, voting_period } => {
+                let governance = &mut ctx.accounts.governance;
+                governance.quorum = quorum;
+                governance.threshold = threshold;
+                governance.voting_period = voting_period;
+
+                emit!(GovernanceConfigSet {
+                    governance: governance.key(),
+                    quorum,
+                    threshold,
+                    voting_period,
+                });
+            }
+        }
+
+        ctx.accounts.proposal.is_executed = true;
+
+        emit!(ProposalExecuted {
+            proposal: ctx.accounts.proposal.key(),
+            governance: ctx.accounts.governance.key(),
+            action,
+        });
+        Ok(())
+    }
+
+    // Grants funds from a charter treasury's deposit to a recipient, like
+    // an ecosystem project. Nothing is moved until the recipient claims it,
+    // so the deposit has to hold enough by then. The deposit has to be owned
+    // by ["grant_authority", treasury], rather than the ["token_authority", ..]
+    // PDA that owns escrows, so a grant can only ever pay out of it.
+    pub fn init_grant(
+        ctx: Context<InitGrant>,
+        _grant_authority_bump: u8,
+        nonce: u64,
+        amount: u64,
+        vesting: Option<GrantVesting>,
+    ) -> Result<()> {
+        Grant::check_terms(amount, vesting)?;
+
+        let grant = &mut ctx.accounts.grant;
+        grant.is_initialized = true;
+        grant.charter = ctx.accounts.charter.key();
+        grant.treasury = ctx.accounts.treasury.key();
+        grant.mint = ctx.accounts.treasury.mint;
+        grant.recipient = ctx.accounts.recipient.key();
+        grant.amount = amount;
+        grant.claimed = 0;
+        grant.vesting = vesting;
+        grant.nonce = nonce;
+        grant.payer = ctx.accounts.authority.key();
+
+        emit!(GrantInitialized {
+            grant: grant.key(),
+            charter: grant.charter,
+            treasury: grant.treasury,
+            mint: grant.mint,
+            recipient: grant.recipient,
+            amount,
+            vesting,
+        });
+        Ok(())
+    }
+
+    // A decentralized crank that pays whatever's vested, and hasn't been
+    // claimed yet, to the grant's recipient. Once the whole grant is
+    // claimed, it closes and its rent goes back to its payer.
+    pub fn claim_grant(ctx: Context<ClaimGrant>, grant_authority_bump: u8) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+        let amount = grant
+            .vested_at(ctx.accounts.clock.unix_timestamp)?
+            .saturating_sub(grant.claimed);
+        if amount == 0 {
+            return Err(error!(StrangemoodError::GrantHasNothingToClaim));
+        }
+
+        let treasury_key = ctx.accounts.treasury.key();
+        cpi::token_transfer_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.deposit.to_account_info(),
+            ctx.accounts.destination.to_account_info(),
+            ctx.accounts.grant_authority.to_account_info(),
+            amount,
+            &[b"grant_authority", treasury_key.as_ref(), &[grant_authority_bump]],
+        )?;
+
+        grant.claimed = grant
+            .claimed
+            .checked_add(amount)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        emit!(GrantClaimed {
+            grant: grant.key(),
+            recipient: grant.recipient,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            claimed: grant.claimed,
+        });
+
+        if grant.claimed == grant.amount {
+            close_native_account(&grant.to_account_info(), &ctx.accounts.payer);
+
+            emit!(GrantClosed {
+                grant: grant.key(),
+                claimed: grant.claimed,
+            });
+        }
+        Ok(())
+    }
+
+    // Stops a grant from paying out any more. What's already vested can
+    // still be claimed. A grant without a vesting schedule is vested as
+    // soon as it's made, so cancelling it revokes whatever hasn't been
+    // claimed yet. If nothing is left to claim, the grant closes.
+    pub fn cancel_grant(ctx: Context<CancelGrant>) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+        grant.cancel(Clock::get()?.unix_timestamp)?;
+
+        emit!(GrantCancelled {
+            grant: grant.key(),
+            amount: grant.amount,
+        });
+
+        if grant.claimed == grant.amount {
+            close_native_account(&grant.to_account_info(), &ctx.accounts.payer);
+
+            emit!(GrantClosed {
+                grant: grant.key(),
+                claimed: grant.claimed,
+            });
+        }
+        Ok(())
+    }
+
+    // Rewrites a listing created before rates were stored as basis points.
+    // The conversion is deterministic, so anyone can run it.
+    pub fn migrate_listing(ctx: Context<MigrateListing>) -> Result<()> {
+        let (listing, space) = {
+            let data = ctx.accounts.listing.try_borrow_data()?;
+            let listing = migrate::migrate_listing(&data, Clock::get()?.unix_timestamp)?;
+            let space = migrate::listing_space(&data, &listing)?;
+            (listing, space)
+        };
+
+        // The appended fields may not fit in what a legacy listing was allocated
+        if space > ctx.accounts.listing.data_len() {
+            cpi::realloc_account(
+                &ctx.accounts.listing,
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                space,
+            )?;
+        }
+        migrate::write(&mut ctx.accounts.listing.try_borrow_mut_data()?, &listing)?;
+
+        emit!(ListingMigrated {
+            listing: ctx.accounts.listing.key(),
+            cashier_split: listing.cashier_split,
+        });
+
+        Ok(())
+    }
+
+    // Gives a trial's receipt that was created before trials stored when they
+    // started a refund window of LEGACY_REFUND_WINDOW, starting now, so it can
+    // be finished or refunded, and counts it in its listing's open_trials.
+    // Receipts with a cashier are grown first, since they have no room for
+    // the new fields. Anyone can run it.
+    pub fn migrate_receipt(ctx: Context<MigrateReceipt>) -> Result<()> {
+        let space = migrate::receipt_space(&ctx.accounts.receipt.try_borrow_data()?)?;
+        if space > ctx.accounts.receipt.data_len() {
+            cpi::realloc_account(
+                &ctx.accounts.receipt,
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                space,
+            )?;
+        }
+
+        let mut data = ctx.accounts.receipt.try_borrow_mut_data()?;
+        let receipt = migrate::migrate_receipt(&mut data[..], Clock::get()?.unix_timestamp)?;
+        if receipt.listing != ctx.accounts.listing.key() {
+            return Err(error!(StrangemoodError::ReceiptHasUnexpectedListing));
+        }
+
+        let listing = &mut ctx.accounts.listing;
+        listing.open_trials = listing
+            .open_trials
+            .checked_add(1)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        emit!(ReceiptMigrated {
+            receipt: ctx.accounts.receipt.key(),
+            started_at: receipt.started_at,
+            refund_window: receipt.refund_window,
+        });
+
+        Ok(())
+    }
+
+    // Rewrites a charter created before rates were stored as basis points.
+    // The conversion is deterministic, so anyone can run it.
+    pub fn migrate_charter(ctx: Context<MigrateCharter>) -> Result<()> {
+        let (charter, space) = {
+            let data = ctx.accounts.charter.try_borrow_data()?;
+            let charter = migrate::migrate_charter(&data)?;
+            let space = migrate::charter_space(&data, &charter)?;
+            (charter, space)
+        };
+
+        // The appended fields may not fit in what a legacy charter was allocated
+        if space > ctx.accounts.charter.data_len() {
+            cpi::realloc_account(
+                &ctx.accounts.charter,
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                space,
+            )?;
+        }
+        migrate::write(&mut ctx.accounts.charter.try_borrow_mut_data()?, &charter)?;
+
+        emit!(CharterMigrated {
+            charter: ctx.accounts.charter.key(),
+            expansion_rate: charter.expansion_rate,
+            payment_contribution: charter.payment_contribution,
+            vote_contribution: charter.vote_contribution,
+        });
+
+        Ok(())
+    }
+
+    // Rewrites a charter treasury created before the scalar was stored as basis points.
+    // The conversion is deterministic, so anyone can run it.
+    pub fn migrate_charter_treasury(ctx: Context<MigrateCharterTreasury>) -> Result<()> {
+        let mut data = ctx.accounts.treasury.try_borrow_mut_data()?;
+        let treasury = migrate::migrate_charter_treasury(&mut data[..])?;
+
+        emit!(CharterTreasuryMigrated {
+            treasury: ctx.accounts.treasury.key(),
+            scalar: treasury.scalar,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, escrow_authority_bump:u8, inventory_delegate_bump: u8)]
+pub struct StartTrial<'info> {
+
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        constraint=listing_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        constraint=listing_payment_deposit.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint
+    )]
+    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The type of funds that this 
+    pub listing_payment_deposit_mint: Account<'info, Mint>,
+
+    // A token account of the listing.mint where listing tokens
+    // will be deposited at
+    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    // The mint associated with the listing
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    // A receipt that lets you refund something later.
+    //
+    // 8 for the tag
+    // 1 for is_initialized bool
+    // 32 for listing pubkey
+    // 32 for inventory pubkey
+    // 32 for purchaser pubkey
+    // 32 + 1 for cashier pubkey 
+    // 32 for escrow pubkey
+    // 8 for quantity u64
+    // 8 for price u64
+    // 8 for started_at i64
+    // 8 for refund_window i64
+    #[account(init,
+        seeds = [b"receipt", escrow.key().as_ref()],
+        bump,
+        payer = purchaser,
+        space = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 32 + 8 + 8 + 8 + 8)]
+    pub receipt: Box<Account<'info, Receipt>>,
+
+    #[account(
+        init,
+        payer=purchaser,
+        token::mint = listing_payment_deposit_mint,
+        token::authority = escrow_authority,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, escrow_authority_bump:u8, inventory_delegate_bump:u8)]
+pub struct StartTrialWithCashier<'info> {
+
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        constraint=listing_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        constraint=listing_payment_deposit.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint
+    )]
+    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The type of funds that this
+    pub listing_payment_deposit_mint: Account<'info, Mint>,
+
+    // The person who's allowed to cash out the listing
+    pub cashier: Account<'info, Cashier>,
+
+    // A token account of the listing.mint where listing tokens
+    // will be deposited at
+    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    // The mint associated with the listing
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    // A receipt that lets you refund something later.
+    //
+    // 8 for the tag
+    // 1 for is_initialized bool
+    // 32 for listing pubkey
+    // 32 for inventory pubkey
+    // 32 for purchaser pubkey
+    // 32 + 1 for cashier pubkey + option
+    // 32 for escrow pubkey
+    // 8 for quantity u64
+    // 8 for price u64
+    // 8 for started_at i64
+    // 8 for refund_window i64
+    #[account(init,
+        seeds = [b"receipt", escrow.key().as_ref()],
+        bump,
+        payer = purchaser,
+        space = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 32 + 8 + 8 + 8 + 8)]
+    pub receipt: Box<Account<'info, Receipt>>,
+
+    #[account(
+        init,
+        payer=purchaser,
+        token::mint = listing_payment_deposit_mint,
+        token::authority = escrow_authority,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, charter_mint_authority_bump: u8, inventory_delegate_bump: u8)]
+pub struct Purchase<'info> {
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
+
+    // TODO: consider rename? 
+    // Where the listing token is deposited when purchase is complete.
+    #[account(mut)]
+    pub inventory: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        has_one=charter,
+        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        has_one=charter,
+        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint, 
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_reserve: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", charter_mint.key().as_ref()],
+        bump = charter_mint_authority_bump,
+    )]
+    pub charter_mint_authority: AccountInfo<'info>,
+
+    // Box'd to move the charter (which is fairly hefty)
+    // to the heap instead of the stack.
+    // Not actually sure if this is a good idea, but
+    // without the Box, we run out of space?
+    #[account(
+        constraint=charter.clone().into_inner().mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    // Mutable, since they pay rent for the purchase receipt, if there is one.
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, charter_mint_authority_bump: u8, inventory_delegate_bump: u8)]
+pub struct PurchaseWithCashier<'info> {
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
+
+    #[account(has_one=charter)]
+    pub cashier: Box<Account<'info, Cashier>>,
+
+    #[account(
+        has_one=cashier @ StrangemoodError::CashierTreasuryHasUnexpectedCashier,
+        constraint=cashier_treasury.escrow==cashier_treasury_escrow.key() @ StrangemoodError::CashierTreasuryHasUnexpectedEscrow,
+        constraint=cashier_treasury.mint==charter_treasury.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=cashier_treasury.mint==listings_payment_deposit.mint @StrangemoodError::CashierTreasuryHasUnexpectedMint,
+    )]
+    pub cashier_treasury: Box<Account<'info, CashierTreasury>>,
+
+    #[account(mut)]
+    pub cashier_treasury_escrow: Box<Account<'info, TokenAccount>>,
+
+    // Where the listing token is deposited when purchase is complete.
+    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        has_one=charter @ StrangemoodError::ListingHasUnexpectedCharter,
+        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit, 
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_reserve: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", charter_mint.key().as_ref()],
+        bump = charter_mint_authority_bump,
+    )]
+    pub charter_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    // Mutable, since they pay rent for the purchase receipt, if there is one.
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(charter_mint_authority_bump: u8, receipt_escrow_authority_bump: u8)]
+pub struct FinishTrial<'info> {
+
+    #[account(mut,
+        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
+        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
+        constraint=receipt.escrow==receipt_escrow.key() @ StrangemoodError::ReceiptHasUnexpectedEscrow
+    )]
+    pub receipt: Box<Account<'info, Receipt>>,
+
+    /// CHECK: A purchaser is just a signer; we're not reading 
+    /// or writing from it.
+    /// The signer that purchased, who gets their SOL back that they used for the receipt.
+    /// Must sign to finish a trial that's still in its refund window.
+    pub purchaser: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub receipt_escrow: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", receipt_escrow.key().as_ref()],
+        bump=receipt_escrow_authority_bump,
+    )]
+    pub receipt_escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        constraint=charter.key()==listing.clone().into_inner().charter @ StrangemoodError::ListingHasUnexpectedCharter,
+        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_reserve: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", charter_mint.key().as_ref()],
+        bump = charter_mint_authority_bump,
+    )]
+    pub charter_mint_authority: AccountInfo<'info>,
+
+    // Box'd to move the charter (which is fairly hefty)
+    // to the heap instead of the stack.
+    // Not actually sure if this is a good idea, but
+    // without the Box, we run out of space?
+    #[account(
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(charter_mint_authority_bump: u8, receipt_escrow_authority_bump: u8)]
+pub struct FinishTrialWithCashier<'info> {
+    #[account(has_one=charter)]
+    pub cashier: Box<Account<'info, Cashier>>,
+
+    #[account(
+        has_one=cashier @ StrangemoodError::CashierTreasuryHasUnexpectedCashier,
+        constraint=cashier_treasury.escrow==cashier_treasury_escrow.key() @ StrangemoodError::CashierTreasuryHasUnexpectedEscrow,
+        constraint=cashier_treasury.mint==charter_treasury.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=cashier_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CashierTreasuryHasUnexpectedMint,
+    )]
+    pub cashier_treasury: Box<Account<'info, CashierTreasury>>,
+
+    #[account(mut)]
+    pub cashier_treasury_escrow: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut,
+        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
+        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
+        constraint=receipt.escrow==receipt_escrow.key() @ StrangemoodError::ReceiptHasUnexpectedEscrow,
+        constraint=receipt.cashier == Some(cashier.key()) @ StrangemoodError::ReceiptHasUnexpectedCashier,)
+    ]
+    pub receipt: Box<Account<'info, Receipt>>,
+
+    /// CHECK: A purchaser is just a signer; we're not reading 
+    /// or writing from it.
+    // The signer that purchased, who gets their SOL back that they used for the receipt.
+    // Must sign to finish a trial that's still in its refund window.
+    pub purchaser: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub receipt_escrow: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", receipt_escrow.key().as_ref()],
+        bump=receipt_escrow_authority_bump,
+    )]
+    pub receipt_escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    // The listing to purchase
+    #[account(
+        mut,
+        constraint=charter.key()==listing.clone().into_inner().charter @ StrangemoodError::ListingHasUnexpectedCharter,
+        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_reserve: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", charter_mint.key().as_ref()],
+        bump = charter_mint_authority_bump,
+    )]
+    pub charter_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, inventory_delegate_bump: u8, escrow_authority_bump: u8)]
+pub struct RefundTrial<'info> {
+    pub purchaser: Signer<'info>,
+
+    // Where we'll return the tokens back to 
+    #[account(mut)]
+    pub return_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut,
+        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
+        has_one=inventory @ StrangemoodError::ReceiptHasUnexpectedInventory, 
+        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
+        has_one=escrow @ StrangemoodError::ReceiptHasUnexpectedEscrow,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", inventory.key().as_ref()],
+        bump=inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    // The listing to purchase
+    #[account(mut, constraint=listing.mint==listing_mint.key() @ StrangemoodError::ListingHasUnexpectedMint)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, inventory_delegate_bump: u8, escrow_authority_bump: u8)]
+pub struct RefundTrialWithCashier<'info> {
+    pub purchaser: Signer<'info>,
+
+    // The cashier that started the trial
+    pub cashier: Box<Account<'info, Cashier>>,
+
+    // Where we'll return the tokens back to 
+    #[account(mut)]
+    pub return_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut,
+        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
+        has_one=inventory @ StrangemoodError::ReceiptHasUnexpectedInventory, 
+        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
+        has_one=escrow @ StrangemoodError::ReceiptHasUnexpectedEscrow,
+        constraint=receipt.cashier == Some(cashier.key()) @ StrangemoodError::ReceiptHasUnexpectedCashier,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", inventory.key().as_ref()],
+        bump=inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    // The listing to refund
+    #[account(mut, constraint=listing.mint==listing_mint.key() @ StrangemoodError::ListingHasUnexpectedMint)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_authority_bump: u8, inventory_delegate_bump:u8)]
+pub struct Consume<'info> {
+    #[account(
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        has_one=mint @ StrangemoodError::ListingHasUnexpectedMint)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", mint.key().as_ref()],
+        bump = mint_authority_bump,
+    )]
+    pub mint_authority: AccountInfo<'info>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
+    )]
+    pub inventory_delegate: AccountInfo<'info>,
+
+    #[account(mut, has_one=mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub inventory: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+
+    // The listing authority
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_authority_bump: u8, decimals: u8)]
+pub struct InitListing<'info> {
+    #[account(init, seeds=[b"listing", mint.key().as_ref()], bump, payer = authority, space = Listing::SPACE)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"mint_authority", mint.key().as_ref()],
+        bump = mint_authority_bump,
+    )]
+    pub mint_authority: AccountInfo<'info>,
+
+    #[account(init, mint::decimals = decimals, mint::authority = mint_authority, mint::freeze_authority = mint_authority, payer = authority)]
+    pub mint: Box<Account<'info, Mint>>,
+
+    // The charter that this listing will belong to, effectively determining it's governance.
+    pub charter: Box<Account<'info, Charter>>,
+
+    // The charter treasury that proves that this governance supports the payment method
+    // that this charter is using.
+    #[account(has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter)]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(constraint=payment_deposit.mint==charter_treasury.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(constraint=vote_deposit.mint==charter.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+
+    // The soon to be authority of the listing
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListing<'info> {
+    #[account(mut,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(charter_mint_authority_bump: u8, nonce: u64)]
+pub struct PurchaseGift<'info> {
+    #[account(init,
+        seeds = [b"gift", purchaser.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+        payer = purchaser,
+        space = Gift::SPACE
+    )]
+    pub gift: Box<Account<'info, Gift>>,
+
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one=charter,
+        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        has_one=charter,
+        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_reserve: Box<Account<'info, TokenAccount>>,
 
-    // The user's token account where funds will be transfered from
-    #[account(mut, constraint=payment.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub payment: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
 
-    // The listing to purchase
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        constraint=listing_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
-        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        seeds = [b"mint_authority", charter_mint.key().as_ref()],
+        bump = charter_mint_authority_bump,
     )]
-    pub listing: Box<Account<'info, Listing>>,
+    pub charter_mint_authority: AccountInfo<'info>,
 
     #[account(
-        constraint=listing_payment_deposit.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint
+        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
-    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
+    pub charter: Box<Account<'info, Charter>>,
 
-    // The type of funds that this 
-    pub listing_payment_deposit_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
 
-    // A token account of the listing.mint where listing tokens
-    // will be deposited at
-    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub inventory: Account<'info, TokenAccount>,
+#[derive(Accounts)]
+#[instruction(listing_mint_authority_bump: u8, inventory_delegate_bump: u8)]
+pub struct ClaimGift<'info> {
+    #[account(mut,
+        close = purchaser,
+        has_one=listing @ StrangemoodError::GiftHasUnexpectedListing,
+        has_one=purchaser @ StrangemoodError::GiftHasUnexpectedPurchaser,
+    )]
+    pub gift: Box<Account<'info, Gift>>,
+
+    /// CHECK: Checked by the gift, and only receives its rent.
+    #[account(mut)]
+    pub purchaser: AccountInfo<'info>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds = [b"token_authority", inventory.key().as_ref()],
-        bump = inventory_delegate_bump,
+        mut,
+        constraint=listing_mint.key()==listing.mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
-    pub inventory_delegate: AccountInfo<'info>,
+    pub listing: Box<Account<'info, Listing>>,
 
-    // The mint associated with the listing
     #[account(mut)]
     pub listing_mint: Box<Account<'info, Mint>>,
 
@@ -1112,76 +5327,61 @@ pub struct StartTrial<'info> {
     )]
     pub listing_mint_authority: AccountInfo<'info>,
 
-    // A receipt that lets you refund something later.
-    //
-    // 8 for the tag
-    // 1 for is_initialized bool
-    // 32 for listing pubkey
-    // 32 for inventory pubkey
-    // 32 for purchaser pubkey
-    // 32 + 1 for cashier pubkey 
-    // 32 for escrow pubkey
-    // 8 for quantity u64
-    // 8 for price u64
-    #[account(init,
-        seeds = [b"receipt", escrow.key().as_ref()],
-        bump,
-        payer = purchaser,
-        space = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 32 + 8 + 8)]
-    pub receipt: Box<Account<'info, Receipt>>,
-
-    #[account(
-        init,
-        payer=purchaser,
-        token::mint = listing_payment_deposit_mint,
-        token::authority = escrow_authority,
-    )]
-    pub escrow: Account<'info, TokenAccount>,
+    /// CHECK: Where the listing tokens are minted to. If it doesn't exist,
+    /// it's created as the recipient's associated token account, so its
+    /// mint and owner are checked in claim_gift.
+    #[account(mut)]
+    pub inventory: AccountInfo<'info>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds=[b"token_authority", escrow.key().as_ref()],
-        bump=escrow_authority_bump,
+        seeds = [b"token_authority", inventory.key().as_ref()],
+        bump = inventory_delegate_bump,
     )]
-    pub escrow_authority: AccountInfo<'info>,
+    pub inventory_delegate: AccountInfo<'info>,
 
+    // Pays the rent for the inventory, if it's created
     #[account(mut)]
-    pub purchaser: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub recipient: Signer<'info>,
     pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(listing_mint_authority_bump: u8, escrow_authority_bump:u8, inventory_delegate_bump:u8)]
-pub struct StartTrialWithCashier<'info> {
-
-    // The user's token account where funds will be transfered from
-    #[account(mut, constraint=payment.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub payment: Box<Account<'info, TokenAccount>>,
+#[instruction(listing_mint_authority_bump: u8, inventory_delegate_bump: u8)]
+pub struct ReclaimGift<'info> {
+    #[account(mut,
+        close = purchaser,
+        has_one=listing @ StrangemoodError::GiftHasUnexpectedListing,
+        has_one=purchaser @ StrangemoodError::GiftHasUnexpectedPurchaser,
+    )]
+    pub gift: Box<Account<'info, Gift>>,
 
-    // The listing to purchase
     #[account(
-        constraint=listing_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
-        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
+        mut,
+        constraint=listing_mint.key()==listing.mint @ StrangemoodError::ListingHasUnexpectedMint,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub listing: Box<Account<'info, Listing>>,
 
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        constraint=listing_payment_deposit.mint==listing_payment_deposit_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint
+        seeds = [b"mint_authority", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
     )]
-    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
-
-    // The type of funds that this
-    pub listing_payment_deposit_mint: Account<'info, Mint>,
-
-    // The person who's allowed to cash out the listing
-    pub cashier: Account<'info, Cashier>,
+    pub listing_mint_authority: AccountInfo<'info>,
 
-    // A token account of the listing.mint where listing tokens
-    // will be deposited at
-    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub inventory: Account<'info, TokenAccount>,
+    // Where the listing tokens are minted to
+    #[account(mut,
+        constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint,
+        constraint=inventory.owner==purchaser.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner,
+    )]
+    pub inventory: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
@@ -1190,68 +5390,88 @@ pub struct StartTrialWithCashier<'info> {
     )]
     pub inventory_delegate: AccountInfo<'info>,
 
-    // The mint associated with the listing
     #[account(mut)]
-    pub listing_mint: Box<Account<'info, Mint>>,
-
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"mint_authority", listing_mint.key().as_ref()],
-        bump = listing_mint_authority_bump,
-    )]
-    pub listing_mint_authority: AccountInfo<'info>,
+    pub purchaser: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
 
-    // A receipt that lets you refund something later.
-    //
-    // 8 for the tag
-    // 1 for is_initialized bool
-    // 32 for listing pubkey
-    // 32 for inventory pubkey
-    // 32 for purchaser pubkey
-    // 32 + 1 for cashier pubkey + option
-    // 32 for escrow pubkey
-    // 8 for quantity u64
-    // 8 for price u64
+#[derive(Accounts)]
+pub struct InitResaleOffer<'info> {
     #[account(init,
-        seeds = [b"receipt", escrow.key().as_ref()],
+        seeds = [b"resale_offer", inventory.key().as_ref()],
         bump,
-        payer = purchaser,
-        space = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 32 + 8 + 8)]
-    pub receipt: Box<Account<'info, Receipt>>,
-
-    #[account(
-        init,
-        payer=purchaser,
-        token::mint = listing_payment_deposit_mint,
-        token::authority = escrow_authority,
+        payer = seller,
+        space = ResaleOffer::SPACE
     )]
-    pub escrow: Account<'info, TokenAccount>,
+    pub resale_offer: Account<'info, ResaleOffer>,
+
+    #[account(constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration)]
+    pub listing: Account<'info, Listing>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds=[b"token_authority", escrow.key().as_ref()],
-        bump=escrow_authority_bump,
+        constraint=inventory.mint==listing.mint @ StrangemoodError::TokenAccountHasUnexpectedMint,
+        constraint=inventory.owner==seller.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner,
     )]
-    pub escrow_authority: AccountInfo<'info>,
+    pub inventory: Account<'info, TokenAccount>,
+
+    // Where the seller is paid. Checked against the listing's payment mint
+    // when the offer is filled.
+    pub deposit: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    pub purchaser: Signer<'info>,
+    pub seller: Signer<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct CancelResaleOffer<'info> {
+    #[account(mut,
+        close = seller,
+        has_one=seller @ StrangemoodError::ResaleOfferHasUnexpectedSeller,
+    )]
+    pub resale_offer: Account<'info, ResaleOffer>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+}
 
 #[derive(Accounts)]
-#[instruction(listing_mint_authority_bump: u8, charter_mint_authority_bump: u8, inventory_delegate_bump: u8)]
-pub struct Purchase<'info> {
+#[instruction(listing_mint_authority_bump: u8, charter_mint_authority_bump: u8, seller_inventory_delegate_bump: u8, inventory_delegate_bump: u8)]
+pub struct PurchaseResale<'info> {
+    #[account(mut,
+        has_one=listing @ StrangemoodError::ResaleOfferHasUnexpectedListing,
+        has_one=seller @ StrangemoodError::ResaleOfferHasUnexpectedSeller,
+        constraint=resale_offer.inventory==seller_inventory.key() @ StrangemoodError::ResaleOfferHasUnexpectedInventory,
+        constraint=resale_offer.deposit==seller_deposit.key() @ StrangemoodError::ResaleOfferHasUnexpectedDeposit,
+    )]
+    pub resale_offer: Box<Account<'info, ResaleOffer>>,
+
+    /// CHECK: Checked by the resale_offer, and only receives its rent once it's filled.
+    #[account(mut)]
+    pub seller: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub seller_inventory: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"token_authority", seller_inventory.key().as_ref()],
+        bump = seller_inventory_delegate_bump,
+    )]
+    pub seller_inventory_delegate: AccountInfo<'info>,
+
+    #[account(mut, constraint=seller_deposit.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub seller_deposit: Box<Account<'info, TokenAccount>>,
+
     // The user's token account where funds will be transfered from
     #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
     pub payment: Box<Account<'info, TokenAccount>>,
 
-    // TODO: consider rename? 
-    // Where the listing token is deposited when purchase is complete.
-    #[account(mut)]
+    // Where the listing tokens are moved to
+    #[account(mut,
+        constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint,
+        constraint=inventory.owner==purchaser.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner,
+    )]
     pub inventory: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
@@ -1267,16 +5487,15 @@ pub struct Purchase<'info> {
     #[account(mut)]
     pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
 
-    // The listing to purchase
     #[account(
         has_one=charter,
         constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
         constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
         constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub listing: Box<Account<'info, Listing>>,
 
-    #[account(mut)]
     pub listing_mint: Box<Account<'info, Mint>>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
@@ -1289,7 +5508,8 @@ pub struct Purchase<'info> {
     #[account(
         has_one=charter,
         constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
-        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint, 
+        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter_treasury: Box<Account<'info, CharterTreasury>>,
 
@@ -1309,139 +5529,130 @@ pub struct Purchase<'info> {
     )]
     pub charter_mint_authority: AccountInfo<'info>,
 
-    // Box'd to move the charter (which is fairly hefty)
-    // to the heap instead of the stack.
-    // Not actually sure if this is a good idea, but
-    // without the Box, we run out of space?
     #[account(
-        constraint=charter.clone().into_inner().mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
         constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
         constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter: Box<Account<'info, Charter>>,
 
+    // Mutable, since they may fund a wrapped SOL payment from their lamports.
+    #[account(mut)]
     pub purchaser: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(listing_mint_authority_bump: u8, charter_mint_authority_bump: u8, inventory_delegate_bump: u8)]
-pub struct PurchaseWithCashier<'info> {
-    // The user's token account where funds will be transfered from
-    #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub payment: Box<Account<'info, TokenAccount>>,
-
-    #[account(has_one=charter)]
-    pub cashier: Box<Account<'info, Cashier>>,
-
-    #[account(
-        has_one=cashier @ StrangemoodError::CashierTreasuryHasUnexpectedCashier,
-        constraint=cashier_treasury.escrow==cashier_treasury_escrow.key() @ StrangemoodError::CashierTreasuryHasUnexpectedEscrow,
-        constraint=cashier_treasury.mint==charter_treasury.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
-        constraint=cashier_treasury.mint==listings_payment_deposit.mint @StrangemoodError::CashierTreasuryHasUnexpectedMint,
-    )]
-    pub cashier_treasury: Box<Account<'info, CashierTreasury>>,
-
-    #[account(mut)]
-    pub cashier_treasury_escrow: Box<Account<'info, TokenAccount>>,
-
-    // Where the listing token is deposited when purchase is complete.
-    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub inventory: Box<Account<'info, TokenAccount>>,
-
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"token_authority", inventory.key().as_ref()],
-        bump = inventory_delegate_bump,
+pub struct CloseListing<'info> {
+    #[account(mut,
+        close = authority,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
-    pub inventory_delegate: AccountInfo<'info>,
-
-    #[account(mut)]
-    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+    pub listing: Account<'info, Listing>,
 
     #[account(mut)]
-    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+    pub authority: Signer<'info>,
+}
 
-    // The listing to purchase
+#[derive(Accounts)]
+pub struct CheckSubscription<'info> {
     #[account(
-        has_one=charter @ StrangemoodError::ListingHasUnexpectedCharter,
-        constraint=listing_mint.key()==listing.clone().into_inner().mint @ StrangemoodError::ListingHasUnexpectedMint,
-        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
-        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit, 
+        has_one=listing @ StrangemoodError::SubscriptionHasUnexpectedListing,
+        has_one=inventory @ StrangemoodError::SubscriptionHasUnexpectedInventory,
     )]
-    pub listing: Box<Account<'info, Listing>>,
+    pub subscription: Account<'info, Subscription>,
 
-    #[account(mut)]
-    pub listing_mint: Box<Account<'info, Mint>>,
+    pub listing: Account<'info, Listing>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"mint_authority", listing_mint.key().as_ref()],
-        bump = listing_mint_authority_bump,
-    )]
-    pub listing_mint_authority: AccountInfo<'info>,
+    pub inventory: Account<'info, TokenAccount>,
+}
 
-    #[account(
-        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
-        constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
-        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+#[derive(Accounts)]
+#[instruction(payment_delegate_bump: u8)]
+pub struct EnableAutoRenew<'info> {
+    #[account(init,
+        seeds = [b"auto_renewal", subscription.key().as_ref()],
+        bump,
+        payer = purchaser,
+        space = AutoRenewal::SPACE
     )]
-    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
-
-    #[account(mut)]
-    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+    pub auto_renewal: Account<'info, AutoRenewal>,
 
-    #[account(mut)]
-    pub charter_reserve: Box<Account<'info, TokenAccount>>,
+    #[account(has_one=purchaser @ StrangemoodError::SubscriptionHasUnexpectedPurchaser)]
+    pub subscription: Account<'info, Subscription>,
 
-    #[account(mut)]
-    pub charter_mint: Box<Account<'info, Mint>>,
+    #[account(mut, constraint=payment.owner==purchaser.key() @ StrangemoodError::AutoRenewalHasUnexpectedPayment)]
+    pub payment: Account<'info, TokenAccount>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds = [b"mint_authority", charter_mint.key().as_ref()],
-        bump = charter_mint_authority_bump,
+        seeds = [b"token_authority", payment.key().as_ref()],
+        bump = payment_delegate_bump,
     )]
-    pub charter_mint_authority: AccountInfo<'info>,
+    pub payment_delegate: AccountInfo<'info>,
 
-    #[account(
-        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
-        constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+    /// CHECK: Created by the payment account's first AutoRenewal, and checked by its address.
+    #[account(mut,
+        seeds = [b"auto_renewal_payment", payment.key().as_ref()],
+        bump,
     )]
-    pub charter: Box<Account<'info, Charter>>,
+    pub auto_renewal_payment: AccountInfo<'info>,
 
+    #[account(mut)]
     pub purchaser: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
-
 #[derive(Accounts)]
-#[instruction(charter_mint_authority_bump: u8, receipt_escrow_authority_bump: u8)]
-pub struct FinishTrial<'info> {
+pub struct CancelAutoRenew<'info> {
+    #[account(mut,
+        close = purchaser,
+        has_one=subscription @ StrangemoodError::AutoRenewalHasUnexpectedSubscription,
+        has_one=payment @ StrangemoodError::AutoRenewalHasUnexpectedPayment,
+        has_one=purchaser @ StrangemoodError::AutoRenewalHasUnexpectedPurchaser,
+    )]
+    pub auto_renewal: Account<'info, AutoRenewal>,
 
     #[account(mut,
-        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
-        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
-        constraint=receipt.escrow==receipt_escrow.key() @ StrangemoodError::ReceiptHasUnexpectedEscrow
+        seeds = [b"auto_renewal_payment", payment.key().as_ref()],
+        bump,
     )]
-    pub receipt: Box<Account<'info, Receipt>>,
+    pub auto_renewal_payment: Account<'info, AutoRenewalPayment>,
 
-    /// CHECK: A purchaser is just a signer; we're not reading 
-    /// or writing from it.
-    /// The signer that purchased, who gets their SOL back that they used for the receipt.
-    pub purchaser: AccountInfo<'info>,
+    pub subscription: Account<'info, Subscription>,
 
     #[account(mut)]
-    pub receipt_escrow: Box<Account<'info, TokenAccount>>,
+    pub payment: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(charter_mint_authority_bump: u8, payment_delegate_bump: u8)]
+pub struct RenewSubscription<'info> {
+    #[account(mut, has_one=listing @ StrangemoodError::SubscriptionHasUnexpectedListing)]
+    pub subscription: Box<Account<'info, Subscription>>,
+
+    #[account(mut,
+        has_one=subscription @ StrangemoodError::AutoRenewalHasUnexpectedSubscription,
+        has_one=payment @ StrangemoodError::AutoRenewalHasUnexpectedPayment,
+    )]
+    pub auto_renewal: Box<Account<'info, AutoRenewal>>,
+
+    #[account(mut, constraint=payment.mint==listings_payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds=[b"token_authority", receipt_escrow.key().as_ref()],
-        bump=receipt_escrow_authority_bump,
+        seeds = [b"token_authority", payment.key().as_ref()],
+        bump = payment_delegate_bump,
     )]
-    pub receipt_escrow_authority: AccountInfo<'info>,
+    pub payment_delegate: AccountInfo<'info>,
 
     #[account(mut)]
     pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
@@ -1449,18 +5660,19 @@ pub struct FinishTrial<'info> {
     #[account(mut)]
     pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
 
-    // The listing to purchase
     #[account(
-        constraint=charter.key()==listing.clone().into_inner().charter @ StrangemoodError::ListingHasUnexpectedCharter,
+        has_one=charter,
         constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
         constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub listing: Box<Account<'info, Listing>>,
 
     #[account(
-        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        has_one=charter,
         constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
         constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter_treasury: Box<Account<'info, CharterTreasury>>,
 
@@ -1480,78 +5692,57 @@ pub struct FinishTrial<'info> {
     )]
     pub charter_mint_authority: AccountInfo<'info>,
 
-    // Box'd to move the charter (which is fairly hefty)
-    // to the heap instead of the stack.
-    // Not actually sure if this is a good idea, but
-    // without the Box, we run out of space?
     #[account(
-        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
         constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter: Box<Account<'info, Charter>>,
 
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(charter_mint_authority_bump: u8, receipt_escrow_authority_bump: u8)]
-pub struct FinishTrialWithCashier<'info> {
-    #[account(has_one=charter)]
-    pub cashier: Box<Account<'info, Cashier>>,
-
-    #[account(
-        has_one=cashier @ StrangemoodError::CashierTreasuryHasUnexpectedCashier,
-        constraint=cashier_treasury.escrow==cashier_treasury_escrow.key() @ StrangemoodError::CashierTreasuryHasUnexpectedEscrow,
-        constraint=cashier_treasury.mint==charter_treasury.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
-        constraint=cashier_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CashierTreasuryHasUnexpectedMint,
+#[instruction(nonce: u64)]
+pub struct InitBundle<'info> {
+    #[account(init,
+        seeds = [b"bundle", authority.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = Bundle::SPACE
     )]
-    pub cashier_treasury: Box<Account<'info, CashierTreasury>>,
-
-    #[account(mut)]
-    pub cashier_treasury_escrow: Box<Account<'info, TokenAccount>>,
-
-    #[account(mut,
-        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
-        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
-        constraint=receipt.escrow==receipt_escrow.key() @ StrangemoodError::ReceiptHasUnexpectedEscrow,
-        constraint=receipt.cashier == Some(cashier.key()) @ StrangemoodError::ReceiptHasUnexpectedCashier,)
-    ]
-    pub receipt: Box<Account<'info, Receipt>>,
+    pub bundle: Account<'info, Bundle>,
 
-    /// CHECK: A purchaser is just a signer; we're not reading 
-    /// or writing from it.
-    // The signer that purchased, who gets their SOL back that they used for the receipt.
-    pub purchaser: AccountInfo<'info>,
+    // The charter that every listing in the bundle belongs to
+    pub charter: Account<'info, Charter>,
 
     #[account(mut)]
-    pub receipt_escrow: Box<Account<'info, TokenAccount>>,
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds=[b"token_authority", receipt_escrow.key().as_ref()],
-        bump=receipt_escrow_authority_bump,
-    )]
-    pub receipt_escrow_authority: AccountInfo<'info>,
+#[derive(Accounts)]
+pub struct SetBundle<'info> {
+    #[account(mut, has_one=authority @ StrangemoodError::BundleHasUnexpectedAuthority)]
+    pub bundle: Account<'info, Bundle>,
 
-    #[account(mut)]
-    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+    pub authority: Signer<'info>,
+}
 
-    #[account(mut)]
-    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+#[derive(Accounts)]
+#[instruction(charter_mint_authority_bump: u8)]
+pub struct PurchaseBundle<'info> {
+    #[account(has_one=charter @ StrangemoodError::BundleHasUnexpectedCharter)]
+    pub bundle: Box<Account<'info, Bundle>>,
 
-    // The listing to purchase
-    #[account(
-        constraint=charter.key()==listing.clone().into_inner().charter @ StrangemoodError::ListingHasUnexpectedCharter,
-        constraint=listings_payment_deposit.key()==listing.clone().into_inner().payment_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
-        constraint=listings_vote_deposit.key()==listing.clone().into_inner().vote_deposit @ StrangemoodError::ListingHasUnexpectedDeposit,
-    )]
-    pub listing: Box<Account<'info, Listing>>,
+    // The user's token account where funds will be transfered from
+    #[account(mut, constraint=payment.mint==charter_treasury.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub payment: Box<Account<'info, TokenAccount>>,
 
     #[account(
-        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        has_one=charter,
         constraint=charter_treasury_deposit.key()==charter_treasury.clone().into_inner().deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
-        constraint=charter_treasury.mint==listings_payment_deposit.mint @ StrangemoodError::CharterTreasuryHasUnexpectedMint,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter_treasury: Box<Account<'info, CharterTreasury>>,
 
@@ -1572,170 +5763,120 @@ pub struct FinishTrialWithCashier<'info> {
     pub charter_mint_authority: AccountInfo<'info>,
 
     #[account(
-        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
         constraint=charter.reserve==charter_reserve.key() @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.mint==charter_mint.key() @ StrangemoodError::CharterHasUnexpectedMint,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
     pub charter: Box<Account<'info, Charter>>,
 
+    // Mutable, since they pay rent for any WalletPurchases the listings need.
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(listing_mint_authority_bump: u8, inventory_delegate_bump: u8, escrow_authority_bump: u8)]
-pub struct RefundTrial<'info> {
-    pub purchaser: Signer<'info>,
-
-    // Where we'll return the tokens back to 
-    #[account(mut)]
-    pub return_deposit: Account<'info, TokenAccount>,
-
-    #[account(mut,
-        has_one=listing @ StrangemoodError::ReceiptHasUnexpectedListing, 
-        has_one=inventory @ StrangemoodError::ReceiptHasUnexpectedInventory, 
-        has_one=purchaser @ StrangemoodError::ReceiptHasUnexpectedPurchaser,
-        has_one=escrow @ StrangemoodError::ReceiptHasUnexpectedEscrow,
-    )]
-    pub receipt: Account<'info, Receipt>,
-
-    #[account(mut)]
-    pub escrow: Account<'info, TokenAccount>,
-
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds=[b"token_authority", escrow.key().as_ref()],
-        bump=escrow_authority_bump,
+pub struct InitListingSplits<'info> {
+    #[account(init,
+        seeds = [b"listing_splits", listing.key().as_ref()],
+        bump,
+        payer = authority,
+        space = ListingSplits::SPACE
     )]
-    pub escrow_authority: AccountInfo<'info>,
-
-    #[account(mut, constraint=inventory.mint==listing_mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub inventory: Box<Account<'info, TokenAccount>>,
+    pub listing_splits: Account<'info, ListingSplits>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds=[b"token_authority", inventory.key().as_ref()],
-        bump=inventory_delegate_bump,
+    #[account(mut,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
-    pub inventory_delegate: AccountInfo<'info>,
+    pub listing: Account<'info, Listing>,
 
-    // The listing to purchase
-    #[account(constraint=listing.mint==listing_mint.key() @ StrangemoodError::ListingHasUnexpectedMint)]
-    pub listing: Box<Account<'info, Listing>>,
+    // Used to check that the recipients are in the listing's payment mint
+    #[account(constraint=listing.payment_deposit == payment_deposit.key() @ StrangemoodError::ListingHasUnexpectedDeposit)]
+    pub payment_deposit: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    pub listing_mint: Box<Account<'info, Mint>>,
-
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"mint_authority", listing_mint.key().as_ref()],
-        bump = listing_mint_authority_bump,
-    )]
-    pub listing_mint_authority: AccountInfo<'info>,
-
-    pub token_program: Program<'info, Token>,
+    pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(mint_authority_bump: u8, inventory_delegate_bump:u8)]
-pub struct Consume<'info> {
+pub struct SetListingSplits<'info> {
+    #[account(mut, has_one=listing @ StrangemoodError::ListingSplitsHasUnexpectedListing)]
+    pub listing_splits: Account<'info, ListingSplits>,
+
     #[account(
         has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
-        has_one=mint @ StrangemoodError::ListingHasUnexpectedMint)]
-    pub listing: Box<Account<'info, Listing>>,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Account<'info, Listing>,
 
-    #[account(mut)]
-    pub mint: Box<Account<'info, Mint>>,
+    // Used to check that the recipients are in the listing's payment mint
+    #[account(constraint=listing.payment_deposit == payment_deposit.key() @ StrangemoodError::ListingHasUnexpectedDeposit)]
+    pub payment_deposit: Account<'info, TokenAccount>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"mint_authority", mint.key().as_ref()],
-        bump = mint_authority_bump,
-    )]
-    pub mint_authority: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
-    #[account(
-        seeds = [b"token_authority", inventory.key().as_ref()],
-        bump = inventory_delegate_bump,
+#[derive(Accounts)]
+pub struct CloseListingSplits<'info> {
+    #[account(mut,
+        close = authority,
+        has_one=listing @ StrangemoodError::ListingSplitsHasUnexpectedListing
     )]
-    pub inventory_delegate: AccountInfo<'info>,
+    pub listing_splits: Account<'info, ListingSplits>,
 
-    #[account(mut, has_one=mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub inventory: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
+    #[account(mut, has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority)]
+    pub listing: Account<'info, Listing>,
 
-    // The listing authority
+    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(mint_authority_bump: u8, decimals: u8)]
-pub struct InitListing<'info> {
-    // 8 for the tag
-    // 1 for is_initialized
-    // 1 for is_available 
-    // 1 for is_suspended 
-    // 32 for charter
-    // 32 for authority
-    // 32 for payment_deposit
-    // 32 for payment_deposit
-    // 8 for price
-    // 32 for mint 
-    // 1 for is_refundable
-    // 1 for is_consumable 
-    // 8 for cashier_split
-    // 256 for metadata URI
-    // 128 for future versions
-    #[account(init, seeds=[b"listing", mint.key().as_ref()], bump, payer = authority, space = 8 + 1 + 1 + 32 + 32 + 32 + 32 + 8 + 32 + 1 + 1 + 8 + 256 + 128)]
-    pub listing: Box<Account<'info, Listing>>,
+#[instruction(nonce: u64)]
+pub struct InitCoupon<'info> {
+    #[account(init,
+        seeds = [b"coupon", listing.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = Coupon::SPACE
+    )]
+    pub coupon: Account<'info, Coupon>,
 
-    /// CHECK: This is a PDA, and we're not reading or writing from it.
     #[account(
-        seeds = [b"mint_authority", mint.key().as_ref()],
-        bump = mint_authority_bump,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
     )]
-    pub mint_authority: AccountInfo<'info>,
-
-    #[account(init, mint::decimals = decimals, mint::authority = mint_authority, mint::freeze_authority = mint_authority, payer = authority)]
-    pub mint: Box<Account<'info, Mint>>,
-
-    // The charter that this listing will belong to, effectively determining it's governance.
-    pub charter: Box<Account<'info, Charter>>,
-
-    // The charter treasury that proves that this governance supports the payment method
-    // that this charter is using.
-    #[account(has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter)]
-    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
-
-    #[account(constraint=payment_deposit.mint==charter_treasury.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub payment_deposit: Box<Account<'info, TokenAccount>>,
-
-    #[account(constraint=vote_deposit.mint==charter.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
-    pub vote_deposit: Box<Account<'info, TokenAccount>>,
-
-    pub rent: Sysvar<'info, Rent>,
-    pub token_program: Program<'info, Token>,
+    pub listing: Account<'info, Listing>,
 
-    // The soon to be authority of the listing
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetListing<'info> {
-    #[account(mut, has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority)]
+pub struct CloseCoupon<'info> {
+    #[account(mut,
+        close = authority,
+        has_one=listing @ StrangemoodError::CouponHasUnexpectedListing
+    )]
+    pub coupon: Account<'info, Coupon>,
+
+    #[account(has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority)]
     pub listing: Account<'info, Listing>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct SetListingCharter<'info> {
-    #[account(mut, has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority)]
+    #[account(mut,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub listing: Account<'info, Listing>,
 
     #[account(
@@ -1758,7 +5899,11 @@ pub struct SetListingCharter<'info> {
 
 #[derive(Accounts)]
 pub struct SetListingDeposit<'info> {
-    #[account(mut, has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority, has_one=charter @ StrangemoodError::ListingHasUnexpectedCharter)]
+    #[account(mut,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        has_one=charter @ StrangemoodError::ListingHasUnexpectedCharter,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub listing: Account<'info, Listing>,
 
     #[account(constraint=charter_treasury.mint==payment_deposit.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
@@ -1778,8 +5923,11 @@ pub struct SetListingDeposit<'info> {
 }
 
 #[derive(Accounts)]
-pub struct SetListingAuthority<'info> {
-    #[account(mut, has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority)]
+pub struct ProposeListingAuthority<'info> {
+    #[account(mut,
+        has_one=authority @ StrangemoodError::ListingHasUnexpectedAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub listing: Account<'info, Listing>,
 
     /// CHECK: This is an authority, and we're not reading or writing from it.
@@ -1790,13 +5938,21 @@ pub struct SetListingAuthority<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptListingAuthority<'info> {
+    #[account(mut,
+        constraint=listing.pending_authority==Some(new_authority.key()) @ StrangemoodError::SignerIsNotPendingAuthority,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub listing: Account<'info, Listing>,
+
+    pub new_authority: Signer<'info>,
+}
+
 
 #[derive(Accounts)]
 pub struct InitCharter<'info> {
-    // 8 for the tag
-    // 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 256 for the charter
-    // 256 as a buffer for future versions
-    #[account(init, seeds = [b"charter", mint.key().as_ref()], bump, payer = user, space = 8 + 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 256 + 256)]
+    #[account(init, seeds = [b"charter", mint.key().as_ref()], bump, payer = user, space = Charter::SPACE)]
     pub charter: Account<'info, Charter>,
 
     pub mint: Account<'info, Mint>,
@@ -1813,7 +5969,10 @@ pub struct InitCharter<'info> {
 
 #[derive(Accounts)]
 pub struct SetCharter<'info> {
-    #[account(mut, has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority)]
+    #[account(mut,
+        has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub charter: Account<'info, Charter>,
 
     #[account(mut)]
@@ -1821,6 +5980,35 @@ pub struct SetCharter<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetCashier<'info> {
+    #[account(mut, has_one=authority @ StrangemoodError::CashierHasUnexpectedAuthority)]
+    pub cashier: Account<'info, Cashier>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeCashierAuthority<'info> {
+    #[account(mut, has_one=authority @ StrangemoodError::CashierHasUnexpectedAuthority)]
+    pub cashier: Account<'info, Cashier>,
+
+    /// CHECK: This is an authority, and we're not reading or writing from it.
+    pub new_authority: AccountInfo<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptCashierAuthority<'info> {
+    #[account(mut,
+        constraint=cashier.pending_authority==Some(new_authority.key()) @ StrangemoodError::SignerIsNotPendingAuthority,
+    )]
+    pub cashier: Account<'info, Cashier>,
+
+    pub new_authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct SetCharterReserve<'info> {
     #[account(mut, has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority)]
@@ -1832,7 +6020,7 @@ pub struct SetCharterReserve<'info> {
     pub system_program: Program<'info, System>,
 }
 #[derive(Accounts)]
-pub struct SetCharterAuthority<'info> {
+pub struct ProposeCharterAuthority<'info> {
     #[account(mut, has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority)]
     pub charter: Account<'info, Charter>,
 
@@ -1844,6 +6032,16 @@ pub struct SetCharterAuthority<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptCharterAuthority<'info> {
+    #[account(mut,
+        constraint=charter.pending_authority==Some(new_authority.key()) @ StrangemoodError::SignerIsNotPendingAuthority,
+    )]
+    pub charter: Account<'info, Charter>,
+
+    pub new_authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct InitCharterTreasury<'info> {
     // 8 for the tag
@@ -1852,12 +6050,13 @@ pub struct InitCharterTreasury<'info> {
     // 32 for escrow 
     // 32 for mint
     // 8 for scalar
+    // 1 for version
     // 256 as a buffer for future versions
     #[account(init,
         seeds = [b"treasury", charter.key().as_ref(), mint.key().as_ref()],
         bump,
         payer = authority,
-        space = 8 + 1 + 32 + 32 + 32 + 8 + 256
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 1 + 256
     )]
     pub treasury: Account<'info, CharterTreasury>,
 
@@ -1881,7 +6080,10 @@ pub struct InitCharterTreasury<'info> {
 
 #[derive(Accounts)]
 pub struct SetCharterTreasuryExpansionScalar<'info> {
-    #[account(mut, has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter)]
+    #[account(mut,
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        constraint=treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub treasury: Account<'info, CharterTreasury>,
 
     #[account(has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority)]
@@ -1919,12 +6121,13 @@ pub struct InitCashier<'info> {
     // 8 for last_withdraw_at
     // 32 for authority
     // 256 for URI
+    // 32 + 1 for pending_authority
     // 128 for future versions
     #[account(init,
         seeds = [b"cashier", stake.key().as_ref()],
         bump,
         payer = authority,
-        space = 8 + 1 + 32 + 32 + 8 + 256 + 128
+        space = 8 + 1 + 32 + 32 + 8 + 32 + 256 + (32 + 1) + 128
     )]
     pub cashier: Account<'info, Cashier>,
 
@@ -2061,7 +6264,10 @@ pub struct WithdrawCashierTreasury<'info> {
     pub charter: Box<Account<'info, Charter>>,
 
     // The treasury of the charter
-    #[account(mut, has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter)]
+    #[account(mut,
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        constraint=charter_treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub charter_treasury: Box<Account<'info, CharterTreasury>>,
 
     #[account(has_one=charter @ StrangemoodError::CashierHasUnexpectedCharter, 
@@ -2156,7 +6362,10 @@ pub struct WithdrawCashierStake<'info> {
 
 #[derive(Accounts)]
 pub struct SetListingSuspension<'info> {
-    #[account(mut, has_one=charter)]
+    #[account(mut,
+        has_one=charter,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
     pub listing: Account<'info, Listing>,
 
     #[account(has_one=authority)]
@@ -2166,3 +6375,318 @@ pub struct SetListingSuspension<'info> {
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct MigrateListing<'info> {
+    /// CHECK: This is still in the legacy layout, so it's deserialized by hand.
+    #[account(mut, owner=crate::ID)]
+    pub listing: AccountInfo<'info>,
+
+    // Pays the rent for the space the listing grows by
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateReceipt<'info> {
+    /// CHECK: This is still in the legacy layout, so it's deserialized by hand.
+    #[account(mut, owner=crate::ID)]
+    pub receipt: AccountInfo<'info>,
+
+    // Counts the trial in open_trials, so it can't be closed until
+    // the trial is finished or refunded
+    #[account(
+        mut,
+        constraint=listing.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration
+    )]
+    pub listing: Account<'info, Listing>,
+
+    // Pays the rent for the space the receipt grows by
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateCharter<'info> {
+    /// CHECK: This is still in the legacy layout, so it's deserialized by hand.
+    #[account(mut, owner=crate::ID)]
+    pub charter: AccountInfo<'info>,
+
+    // Pays the rent for the space the charter grows by
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateCharterTreasury<'info> {
+    /// CHECK: This is still in the legacy layout, so it's deserialized by hand.
+    #[account(mut, owner=crate::ID)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitGovernance<'info> {
+    #[account(init,
+        seeds = [b"governance", charter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = Governance::SPACE,
+    )]
+    pub governance: Account<'info, Governance>,
+
+    #[account(mut,
+        has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority,
+        has_one=reserve @ StrangemoodError::CharterHasUnexpectedReserve,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Account<'info, Charter>,
+
+    #[account(mut, constraint=reserve.owner==authority.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner)]
+    pub reserve: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitProposal<'info> {
+    #[account(init,
+        seeds = [b"proposal", governance.key().as_ref(), &governance.proposal_count.to_le_bytes()],
+        bump,
+        payer = proposer,
+        space = Proposal::SPACE,
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(mut)]
+    pub governance: Account<'info, Governance>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_authority_bump: u8)]
+pub struct CastVote<'info> {
+    #[account(init,
+        seeds = [b"vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump,
+        payer = voter,
+        space = VoteRecord::SPACE,
+    )]
+    pub vote: Box<Account<'info, VoteRecord>>,
+
+    #[account(mut, has_one=governance @ StrangemoodError::ProposalHasUnexpectedGovernance)]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(has_one=charter @ StrangemoodError::GovernanceHasUnexpectedCharter)]
+    pub governance: Box<Account<'info, Governance>>,
+
+    #[account(has_one=mint @ StrangemoodError::CharterHasUnexpectedMint)]
+    pub charter: Box<Account<'info, Charter>>,
+
+    pub mint: Box<Account<'info, Mint>>,
+
+    // The voter's charter tokens
+    #[account(mut, constraint=source.mint==mint.key() @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub source: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer=voter,
+        token::mint = mint,
+        token::authority = escrow_authority,
+    )]
+    pub escrow: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(escrow_authority_bump: u8)]
+pub struct WithdrawVote<'info> {
+    #[account(mut,
+        close = voter,
+        has_one=proposal @ StrangemoodError::VoteRecordHasUnexpectedProposal,
+        has_one=voter @ StrangemoodError::VoteRecordHasUnexpectedVoter,
+        has_one=escrow @ StrangemoodError::VoteRecordHasUnexpectedEscrow,
+    )]
+    pub vote: Box<Account<'info, VoteRecord>>,
+
+    #[account(mut)]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(mut)]
+    pub escrow: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds=[b"token_authority", escrow.key().as_ref()],
+        bump=escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    // Where the voter's charter tokens are returned to
+    #[account(mut, constraint=destination.mint==escrow.mint @ StrangemoodError::TokenAccountHasUnexpectedMint)]
+    pub destination: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(governance_bump: u8)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut, has_one=governance @ StrangemoodError::ProposalHasUnexpectedGovernance)]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(mut,
+        has_one=charter @ StrangemoodError::GovernanceHasUnexpectedCharter,
+        seeds = [b"governance", charter.key().as_ref()],
+        bump = governance_bump,
+    )]
+    pub governance: Box<Account<'info, Governance>>,
+
+    #[account(mut,
+        constraint=charter.authority==governance.key() @ StrangemoodError::CharterHasUnexpectedAuthority,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    // Whoever executes the proposal, who pays the rent of any
+    // account the action creates
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(grant_authority_bump: u8, nonce: u64)]
+pub struct InitGrant<'info> {
+    #[account(init,
+        seeds = [b"grant", charter.key().as_ref(), &nonce.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = Grant::SPACE,
+    )]
+    pub grant: Box<Account<'info, Grant>>,
+
+    #[account(
+        has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority,
+        constraint=charter.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(
+        has_one=charter @ StrangemoodError::CharterTreasuryHasUnexpectedCharter,
+        has_one=deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit,
+        constraint=treasury.version==ACCOUNT_VERSION @ StrangemoodError::AccountNeedsMigration,
+    )]
+    pub treasury: Box<Account<'info, CharterTreasury>>,
+
+    // The treasury's grant authority has to own the deposit, or the grant
+    // could never be claimed
+    #[account(
+        constraint=deposit.owner==grant_authority.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner,
+        constraint=deposit.mint==treasury.mint @ StrangemoodError::TokenAccountHasUnexpectedMint,
+    )]
+    pub deposit: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"grant_authority", treasury.key().as_ref()],
+        bump = grant_authority_bump,
+    )]
+    pub grant_authority: AccountInfo<'info>,
+
+    /// CHECK: This is the grant's recipient, and we're not reading or writing from it.
+    pub recipient: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(grant_authority_bump: u8)]
+pub struct ClaimGrant<'info> {
+    #[account(mut,
+        has_one=treasury @ StrangemoodError::GrantHasUnexpectedTreasury,
+        has_one=payer @ StrangemoodError::GrantHasUnexpectedPayer,
+    )]
+    pub grant: Box<Account<'info, Grant>>,
+
+    /// CHECK: Checked by the grant, and only receives its rent once it's fully claimed.
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    #[account(has_one=deposit @ StrangemoodError::CharterTreasuryHasUnexpectedDeposit)]
+    pub treasury: Box<Account<'info, CharterTreasury>>,
+
+    // The deposit can be changed after the grant is made, so it's checked
+    // again here
+    #[account(mut,
+        constraint=deposit.owner==grant_authority.key() @ StrangemoodError::TokenAccountHasUnexpectedOwner,
+        constraint=deposit.mint==grant.mint @ StrangemoodError::TokenAccountHasUnexpectedMint,
+    )]
+    pub deposit: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: This is a PDA, and we're not reading or writing from it.
+    #[account(
+        seeds = [b"grant_authority", treasury.key().as_ref()],
+        bump = grant_authority_bump,
+    )]
+    pub grant_authority: AccountInfo<'info>,
+
+    // The recipient's token account, in the grant's mint
+    #[account(mut,
+        constraint=destination.mint==grant.mint @ StrangemoodError::TokenAccountHasUnexpectedMint,
+        constraint=destination.owner==grant.recipient @ StrangemoodError::TokenAccountHasUnexpectedOwner,
+    )]
+    pub destination: Box<Account<'info, TokenAccount>>,
+
+    pub clock: Sysvar<'info, Clock>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelGrant<'info> {
+    #[account(mut,
+        has_one=charter @ StrangemoodError::GrantHasUnexpectedCharter,
+        has_one=payer @ StrangemoodError::GrantHasUnexpectedPayer,
+    )]
+    pub grant: Box<Account<'info, Grant>>,
+
+    /// CHECK: Checked by the grant, and only receives its rent if it closes.
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    #[account(has_one=authority @ StrangemoodError::CharterHasUnexpectedAuthority)]
+    pub charter: Box<Account<'info, Charter>>,
+
+    pub authority: Signer<'info>,
+}
@@ -0,0 +1,228 @@
+use anchor_lang::prelude::*;
+use std::convert::TryFrom;
+
+use crate::error::StrangemoodError;
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_rate, mul_div_saturating, scale, split, split_shares, split_weighted, BASIS_POINTS};
+    use crate::error::StrangemoodError;
+    use anchor_lang::prelude::*;
+
+    #[test]
+    fn split_rounds_in_favor_of_the_contribution() {
+        // 1% of 100 is exactly 1
+        assert_eq!(split(100, 100).unwrap(), (99, 1));
+
+        // 1% of 150 is 1.5; the kept share rounds down to 148,
+        // and the contribution takes the remainder.
+        assert_eq!(split(150, 100).unwrap(), (148, 2));
+
+        // Nothing is ever lost to rounding
+        let (kept, contributed) = split(123_456_789, 333).unwrap();
+        assert_eq!(kept + contributed, 123_456_789);
+
+        // The edges of the range
+        assert_eq!(split(100, 0).unwrap(), (100, 0));
+        assert_eq!(split(100, BASIS_POINTS).unwrap(), (0, 100));
+    }
+
+    #[test]
+    fn split_does_not_lose_precision_on_large_amounts() {
+        // f64 can only represent integers exactly up to 2^53,
+        // so this would have drifted with the old float math.
+        let (kept, contributed) = split(u64::MAX, 100).unwrap();
+        assert_eq!(contributed, u64::MAX - kept);
+        assert_eq!(kept, ((u64::MAX as u128) * 9_900 / 10_000) as u64);
+    }
+
+    #[test]
+    fn split_rejects_rates_above_one() {
+        assert!(split(100, BASIS_POINTS + 1).is_err());
+    }
+
+    #[test]
+    fn split_shares_matches_split() {
+        // Two shares round exactly like a charter contribution
+        for amount in [0, 1, 150, 123_456_789, u64::MAX] {
+            let (kept, contributed) = split(amount, 333).unwrap();
+            assert_eq!(
+                split_shares(amount, &[BASIS_POINTS - 333, 333]).unwrap(),
+                vec![kept, contributed]
+            );
+        }
+
+        // Every share but the last rounds down, and the last takes the rest
+        assert_eq!(split_shares(100, &[3_333, 3_333, 3_334]).unwrap(), vec![33, 33, 34]);
+        assert_eq!(split_shares(100, &[BASIS_POINTS]).unwrap(), vec![100]);
+
+        // Shares must add up to exactly 100%
+        assert!(split_shares(100, &[5_000, 4_999]).is_err());
+        assert!(split_shares(100, &[5_000, 5_001]).is_err());
+        assert!(split_shares(100, &[]).is_err());
+    }
+
+    #[test]
+    fn split_weighted_is_proportional() {
+        // A 15 bundle of a 20 game and a 10 soundtrack
+        assert_eq!(split_weighted(15, &[20, 10]).unwrap(), vec![10, 5]);
+
+        // The last weight takes the rounding remainder
+        assert_eq!(split_weighted(10, &[1, 1, 1]).unwrap(), vec![3, 3, 4]);
+
+        // If everything's free, the amount is split evenly
+        assert_eq!(split_weighted(10, &[0, 0]).unwrap(), vec![5, 5]);
+
+        let amounts = split_weighted(u64::MAX, &[u64::MAX, 1]).unwrap();
+        assert_eq!(amounts.iter().map(|a| *a as u128).sum::<u128>(), u64::MAX as u128);
+
+        // There's no one to give the amount to
+        assert_eq!(split_weighted(10, &[]).unwrap_err(), error!(StrangemoodError::InvalidSplits));
+    }
+
+    #[test]
+    fn apply_rate_rounds_down() {
+        assert_eq!(apply_rate(10, 2_500).unwrap(), 2);
+        assert_eq!(apply_rate(100, 100_000).unwrap(), 1_000);
+        assert!(apply_rate(u64::MAX, 2 * BASIS_POINTS).is_err());
+    }
+
+    #[test]
+    fn scale_divides_by_the_scalar() {
+        // An expansion rate of 10.0 with a scalar of 1.0
+        assert_eq!(scale(7, 100_000, 10_000).unwrap(), 70);
+
+        // An expansion rate of 10.0 with a scalar of 4.0
+        assert_eq!(scale(7, 100_000, 40_000).unwrap(), 17);
+
+        // A zero scalar is an error, not a panic
+        assert!(scale(7, 100_000, 0).is_err());
+    }
+
+    #[test]
+    fn mul_div_saturating_caps_at_u64_max() {
+        // 3 epochs of a 10 epoch withdraw period
+        assert_eq!(mul_div_saturating(1_000, 3, 10).unwrap(), 300);
+        assert_eq!(mul_div_saturating(u64::MAX, 1_000, 1).unwrap(), u64::MAX);
+        assert!(mul_div_saturating(1_000, 3, 0).is_err());
+    }
+}
+
+/// Rates are stored as basis points, so 10_000 is 1.0 (or 100%),
+/// 100 is 0.01 (or 1%), and 1 is 0.0001.
+///
+/// Rates are kept in a u64 rather than a u16 so that they occupy
+/// the same 8 bytes as the f64 they replaced, which lets existing
+/// accounts be migrated in place.
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Splits `amount` into a kept share and a contributed share, where
+/// `rate` (in basis points) is the portion that's contributed.
+///
+/// Rounding rule: the kept share is rounded down, and the contributed
+/// share is whatever remains. In other words, the contribution is
+/// rounded up, and the two shares always add back up to `amount`.
+pub fn split(amount: u64, rate: u64) -> Result<(u64, u64)> {
+    let kept_rate = BASIS_POINTS
+        .checked_sub(rate)
+        .ok_or(StrangemoodError::InvalidBasisPoints)?;
+    let kept = apply_rate(amount, kept_rate)?;
+    let contributed = amount
+        .checked_sub(kept)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+    Ok((kept, contributed))
+}
+
+/// Splits `amount` between several recipients by their `shares` (in basis
+/// points), which must add up to exactly BASIS_POINTS.
+///
+/// Rounding rule: the same as `split`. Every share except the last is
+/// rounded down, and the last share is whatever remains, so the shares
+/// always add back up to `amount`.
+pub fn split_shares(amount: u64, shares: &[u64]) -> Result<Vec<u64>> {
+    let mut total: u64 = 0;
+    for share in shares {
+        total = total
+            .checked_add(*share)
+            .ok_or(StrangemoodError::InvalidBasisPoints)?;
+    }
+    if total != BASIS_POINTS {
+        return Err(error!(StrangemoodError::InvalidBasisPoints));
+    }
+
+    split_weighted(amount, shares)
+}
+
+/// Splits `amount` in proportion to `weights`, such as splitting a bundle's
+/// price in proportion to the prices of the listings in it. If every weight
+/// is 0, the amount is split evenly. There has to be at least one weight.
+///
+/// Rounding rule: the same as `split_shares`.
+pub fn split_weighted(amount: u64, weights: &[u64]) -> Result<Vec<u64>> {
+    if weights.is_empty() {
+        return Err(error!(StrangemoodError::InvalidSplits));
+    }
+
+    let mut total: u128 = weights.iter().map(|w| *w as u128).sum();
+    let is_even = total == 0;
+    if is_even {
+        total = weights.len() as u128;
+    }
+
+    let mut amounts = Vec::with_capacity(weights.len());
+    let mut remaining = amount;
+    for weight in &weights[..weights.len() - 1] {
+        let weight = if is_even { 1 } else { *weight as u128 };
+        let part = u64::try_from((amount as u128) * weight / total)
+            .map_err(|_| error!(StrangemoodError::ArithmeticOverflow))?;
+        remaining = remaining
+            .checked_sub(part)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        amounts.push(part);
+    }
+    amounts.push(remaining);
+
+    Ok(amounts)
+}
+
+/// Multiplies `amount` by `rate` (in basis points), rounding down.
+pub fn apply_rate(amount: u64, rate: u64) -> Result<u64> {
+    mul_div(amount, rate, BASIS_POINTS)
+}
+
+/// Multiplies `amount` by `numerator / denominator`, where both are
+/// in basis points, rounding down.
+///
+/// Used to scale a contribution by the charter's expansion rate and
+/// the treasury's scalar.
+pub fn scale(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    mul_div(amount, numerator, denominator)
+}
+
+/// Multiplies `amount` by `numerator / denominator`, rounding down, and
+/// caps the result at u64::MAX rather than failing.
+///
+/// Used by the withdraw cranks, where the result is an upper bound
+/// that's always capped by an account balance anyway.
+pub fn mul_div_saturating(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = (amount as u128)
+        .checked_mul(numerator as u128)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?
+        .checked_div(denominator as u128)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+    Ok(u64::try_from(result).unwrap_or(u64::MAX))
+}
+
+// Computes floor(a * b / c) with a u128 intermediate, so the
+// multiplication can't overflow before the division.
+fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    let result = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?
+        .checked_div(c as u128)
+        .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+    u64::try_from(result).map_err(|_| error!(StrangemoodError::ArithmeticOverflow))
+}
@@ -0,0 +1,463 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::error::StrangemoodError;
+use crate::math::BASIS_POINTS;
+use crate::state::{Charter, CharterTreasury, Listing, Receipt, ACCOUNT_VERSION, LEGACY_REFUND_WINDOW};
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::{prelude::Pubkey, AccountDeserialize, AnchorSerialize, Discriminator};
+
+    use super::{
+        basis_points_from_f64, charter_space, listing_space, migrate_charter, migrate_listing,
+        migrate_receipt, receipt_space, write, LegacyCharter, LegacyListing, LegacyReceipt,
+    };
+    use crate::state::{Charter, Listing, Receipt, ACCOUNT_VERSION, LEGACY_REFUND_WINDOW};
+
+    #[test]
+    fn converts_rates_to_basis_points() {
+        assert_eq!(basis_points_from_f64(0.01).unwrap(), 100);
+        assert_eq!(basis_points_from_f64(0.2).unwrap(), 2_000);
+        assert_eq!(basis_points_from_f64(10.0).unwrap(), 100_000);
+
+        // Rounds to the nearest basis point
+        assert_eq!(basis_points_from_f64(0.000149).unwrap(), 1);
+
+        assert!(basis_points_from_f64(-0.1).is_err());
+        assert!(basis_points_from_f64(f64::NAN).is_err());
+        assert!(basis_points_from_f64(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn migrates_a_listing_in_place() {
+        let legacy = LegacyListing {
+            is_initialized: true,
+            is_available: true,
+            is_suspended: false,
+            charter: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            payment_deposit: Pubkey::new_unique(),
+            vote_deposit: Pubkey::new_unique(),
+            price: 100,
+            mint: Pubkey::new_unique(),
+            is_refundable: true,
+            is_consumable: false,
+            cashier_split: 0.1,
+            uri: "ipfs://cid".to_string(),
+        };
+
+        let mut data = Listing::discriminator().to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(data.len() + 128, 0);
+
+        // Legacy accounts deserialize as version 0
+        let before = Listing::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(before.version, 0);
+
+        let listing = migrate_listing(&data, 1_000).unwrap();
+        data.resize(listing_space(&data, &listing).unwrap(), 0);
+        write(&mut data, &listing).unwrap();
+        let after = Listing::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(after.version, ACCOUNT_VERSION);
+        assert_eq!(after.cashier_split, 1_000);
+        assert_eq!(after.price, 100);
+        assert_eq!(after.authority, legacy.authority);
+        assert_eq!(after.uri, legacy.uri);
+        assert_eq!(after.refund_window, LEGACY_REFUND_WINDOW);
+        assert_eq!(after.legacy_trials_until, 1_000 + LEGACY_REFUND_WINDOW);
+
+        // Migrating twice would reinterpret the new integers as floats
+        assert!(migrate_listing(&data, 1_000).is_err());
+    }
+
+    #[test]
+    fn grows_a_listing_with_a_long_uri() {
+        let legacy = LegacyListing {
+            is_initialized: true,
+            is_available: true,
+            is_suspended: false,
+            charter: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            payment_deposit: Pubkey::new_unique(),
+            vote_deposit: Pubkey::new_unique(),
+            price: 100,
+            mint: Pubkey::new_unique(),
+            is_refundable: true,
+            is_consumable: false,
+            cashier_split: 0.1,
+            uri: "a".repeat(300),
+        };
+
+        // Legacy listings were allocated 572 bytes
+        let mut data = Listing::discriminator().to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(572, 0);
+        assert!(Listing::try_deserialize(&mut data.as_slice()).is_err());
+
+        let listing = migrate_listing(&data, 1_000).unwrap();
+        let len = listing_space(&data, &listing).unwrap();
+        assert!(len >= Listing::SPACE);
+        assert!(len > 572);
+
+        // Writing it back without growing the account would overflow it
+        assert!(write(&mut data.clone(), &listing).is_err());
+
+        data.resize(len, 0);
+        write(&mut data, &listing).unwrap();
+        let after = Listing::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(after.version, ACCOUNT_VERSION);
+        assert_eq!(after.uri, legacy.uri);
+        assert_eq!(after.cashier_split, 1_000);
+
+        assert!(migrate_listing(&data, 1_000).is_err());
+    }
+
+    #[test]
+    fn grows_a_charter_with_a_long_uri() {
+        let legacy = LegacyCharter {
+            is_initialized: true,
+            expansion_rate: 10.0,
+            payment_contribution: 0.01,
+            vote_contribution: 0.2,
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            withdraw_period: 1,
+            stake_withdraw_amount: 1,
+            uri: "a".repeat(490),
+        };
+
+        // Legacy charters were allocated 643 bytes
+        let mut data = Charter::discriminator().to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(643, 0);
+
+        let charter = migrate_charter(&data).unwrap();
+        let len = charter_space(&data, &charter).unwrap();
+        assert!(len >= Charter::SPACE);
+
+        // Writing it back without growing the account would overflow it
+        assert!(write(&mut data.clone(), &charter).is_err());
+
+        data.resize(len, 0);
+        write(&mut data, &charter).unwrap();
+        let after = Charter::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(after.version, ACCOUNT_VERSION);
+        assert_eq!(after.uri, legacy.uri);
+        assert_eq!(after.payment_contribution, 100);
+
+        assert!(migrate_charter(&data).is_err());
+    }
+
+    #[test]
+    fn grows_a_legacy_cashier_receipt() {
+        let legacy = LegacyReceipt {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            cashier: Some(Pubkey::new_unique()),
+            escrow: Pubkey::new_unique(),
+            quantity: 1,
+            price: 100,
+        };
+
+        // A receipt with a cashier filled its whole account
+        let mut data = Receipt::discriminator().to_vec();
+        legacy.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 186);
+        assert!(Receipt::try_deserialize(&mut data.as_slice()).is_err());
+
+        // The bytes it grows by read as "no refund window"
+        let len = receipt_space(&data).unwrap();
+        assert_eq!(len, Receipt::SPACE);
+        data.resize(len, 0);
+        let receipt = Receipt::try_deserialize(&mut data.as_slice()).unwrap();
+        assert!(receipt.needs_migration());
+
+        // Migrating it starts the legacy refund window
+        let receipt = migrate_receipt(&mut data, 1_000).unwrap();
+        assert_eq!(receipt.cashier, legacy.cashier);
+        assert_eq!(receipt.price, 100);
+        assert_eq!(receipt.started_at, 1_000);
+        assert_eq!(receipt.refund_window, LEGACY_REFUND_WINDOW);
+        assert!(receipt.is_refundable_at(1_000 + LEGACY_REFUND_WINDOW));
+        assert!(!receipt.is_refundable_at(1_001 + LEGACY_REFUND_WINDOW));
+
+        let after = Receipt::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(after.refund_window, LEGACY_REFUND_WINDOW);
+        assert!(receipt_space(&data).is_err());
+    }
+
+    #[test]
+    fn migrates_a_legacy_receipt_in_place() {
+        let legacy = LegacyReceipt {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            cashier: None,
+            escrow: Pubkey::new_unique(),
+            quantity: 1,
+            price: 100,
+        };
+
+        // A receipt without a cashier has room to spare, so it already
+        // reads as a Receipt, but without a refund window
+        let mut data = Receipt::discriminator().to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(186, 0);
+        assert!(Receipt::try_deserialize(&mut data.as_slice()).unwrap().needs_migration());
+        assert_eq!(receipt_space(&data).unwrap(), Receipt::SPACE);
+
+        let receipt = migrate_receipt(&mut data, 1_000).unwrap();
+        assert_eq!(receipt.started_at, 1_000);
+        assert_eq!(receipt.refund_window, LEGACY_REFUND_WINDOW);
+        assert!(migrate_receipt(&mut data, 2_000).is_err());
+    }
+}
+
+// The layout of a Listing before rates were stored as basis points.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyListing {
+    pub is_initialized: bool,
+    pub is_available: bool,
+    pub is_suspended: bool,
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+    pub payment_deposit: Pubkey,
+    pub vote_deposit: Pubkey,
+    pub price: u64,
+    pub mint: Pubkey,
+    pub is_refundable: bool,
+    pub is_consumable: bool,
+    pub cashier_split: f64,
+    pub uri: String,
+}
+
+// The layout of a Charter before rates were stored as basis points.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyCharter {
+    pub is_initialized: bool,
+    pub expansion_rate: f64,
+    pub payment_contribution: f64,
+    pub vote_contribution: f64,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub reserve: Pubkey,
+    pub withdraw_period: u64,
+    pub stake_withdraw_amount: u64,
+    pub uri: String,
+}
+
+// The layout of a CharterTreasury before the scalar was stored as basis points.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyCharterTreasury {
+    pub is_initialized: bool,
+    pub charter: Pubkey,
+    pub deposit: Pubkey,
+    pub mint: Pubkey,
+    pub scalar: f64,
+}
+
+// The layout of a Receipt before trials stored when they started.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyReceipt {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub inventory: Pubkey,
+    pub purchaser: Pubkey,
+    pub cashier: Option<Pubkey>,
+    pub escrow: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+}
+
+// Converts a legacy floating point rate, like 0.01, into basis points, like 100.
+// Rounds to the nearest basis point.
+pub fn basis_points_from_f64(rate: f64) -> Result<u64> {
+    if !rate.is_finite() || rate < 0.0 {
+        return Err(error!(StrangemoodError::InvalidBasisPoints));
+    }
+
+    let bps = (rate * BASIS_POINTS as f64).round();
+    if bps > u64::MAX as f64 {
+        return Err(error!(StrangemoodError::ArithmeticOverflow));
+    }
+
+    Ok(bps as u64)
+}
+
+// The f64 fields and the u64 fields that replace them are both 8 bytes,
+// so a migration keeps every legacy field where it was. Fields added since
+// were appended after the uri, and read from what used to be zeroed
+// padding at the end of the account, so legacy accounts are version 0.
+// A legacy listing's or charter's uri was never limited, so one with a
+// long uri may not have room for the appended fields and won't deserialize
+// at all until it grows; that's treated as legacy too.
+fn check_legacy<T: AccountDeserialize + Discriminator>(
+    data: &[u8],
+    version: impl Fn(&T) -> u8,
+) -> Result<()> {
+    if data.len() < 8 || data[..8] != T::discriminator() {
+        return Err(error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch));
+    }
+
+    let mut slice = data;
+    if let Ok(account) = T::try_deserialize(&mut slice) {
+        if version(&account) != 0 {
+            return Err(error!(StrangemoodError::AccountAlreadyMigrated));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write<T: AccountSerialize>(data: &mut [u8], account: &T) -> Result<()> {
+    let mut writer: &mut [u8] = data;
+    account.try_serialize(&mut writer)
+}
+
+// Reads a legacy listing as a Listing. It isn't written back, since it may
+// need more space than the legacy account has; see listing_space.
+pub fn migrate_listing(data: &[u8], now: i64) -> Result<Listing> {
+    check_legacy::<Listing>(data, |l| l.version)?;
+    let legacy = LegacyListing::deserialize(&mut &data[8..])
+        .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?;
+
+    let listing = Listing {
+        is_initialized: legacy.is_initialized,
+        is_available: legacy.is_available,
+        is_suspended: legacy.is_suspended,
+        charter: legacy.charter,
+        authority: legacy.authority,
+        payment_deposit: legacy.payment_deposit,
+        vote_deposit: legacy.vote_deposit,
+        price: legacy.price,
+        mint: legacy.mint,
+        is_refundable: legacy.is_refundable,
+        is_consumable: legacy.is_consumable,
+        cashier_split: basis_points_from_f64(legacy.cashier_split)?,
+        uri: legacy.uri,
+        version: ACCOUNT_VERSION,
+        refund_window: LEGACY_REFUND_WINDOW,
+        max_supply: 0,
+        max_per_wallet: 0,
+        supply: 0,
+        sale_price: 0,
+        sale_starts_at: 0,
+        sale_ends_at: 0,
+        has_splits: false,
+        requires: None,
+        subscription_period: 0,
+        renewal_price: 0,
+        is_resellable: false,
+        resale_royalty: 0,
+        open_trials: 0,
+        open_gifts: 0,
+        pending_authority: None,
+        legacy_trials_until: now + LEGACY_REFUND_WINDOW,
+    };
+
+    Ok(listing)
+}
+
+// The space a migrated listing needs: what a new listing is allocated,
+// or more if its uri is longer than a new listing has room for. Never
+// less than the account already has.
+pub fn listing_space(data: &[u8], listing: &Listing) -> Result<usize> {
+    let len = 8 + listing.try_to_vec()?.len();
+    Ok(len.max(Listing::SPACE).max(data.len()))
+}
+
+// Reads a legacy charter as a Charter. Like a listing, it isn't written
+// back, since it may need more space than the legacy account has; see
+// charter_space.
+pub fn migrate_charter(data: &[u8]) -> Result<Charter> {
+    check_legacy::<Charter>(data, |c| c.version)?;
+    let legacy = LegacyCharter::deserialize(&mut &data[8..])
+        .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?;
+
+    let charter = Charter {
+        is_initialized: legacy.is_initialized,
+        expansion_rate: basis_points_from_f64(legacy.expansion_rate)?,
+        payment_contribution: basis_points_from_f64(legacy.payment_contribution)?,
+        vote_contribution: basis_points_from_f64(legacy.vote_contribution)?,
+        authority: legacy.authority,
+        mint: legacy.mint,
+        reserve: legacy.reserve,
+        withdraw_period: legacy.withdraw_period,
+        stake_withdraw_amount: legacy.stake_withdraw_amount,
+        uri: legacy.uri,
+        version: ACCOUNT_VERSION,
+        pending_authority: None,
+        change_delay: 0,
+        pending_change: None,
+        max_payment_contribution: None,
+        max_vote_contribution: None,
+    };
+
+    Ok(charter)
+}
+
+// The space a migrated charter needs: what a new charter is allocated,
+// or more if its uri is longer than a new charter has room for. Never
+// less than the account already has.
+pub fn charter_space(data: &[u8], charter: &Charter) -> Result<usize> {
+    let len = 8 + charter.try_to_vec()?.len();
+    Ok(len.max(Charter::SPACE).max(data.len()))
+}
+
+pub fn migrate_charter_treasury(data: &mut [u8]) -> Result<CharterTreasury> {
+    check_legacy::<CharterTreasury>(data, |t| t.version)?;
+    let legacy = LegacyCharterTreasury::deserialize(&mut &data[8..])
+        .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?;
+
+    let treasury = CharterTreasury {
+        is_initialized: legacy.is_initialized,
+        charter: legacy.charter,
+        deposit: legacy.deposit,
+        mint: legacy.mint,
+        scalar: basis_points_from_f64(legacy.scalar)?,
+        version: ACCOUNT_VERSION,
+    };
+
+    write(data, &treasury)?;
+    Ok(treasury)
+}
+
+// Receipts don't have a version byte. Ones created before trials stored
+// when they started were allocated with no room to spare, so a receipt
+// with a cashier fills its account and can't be read as a Receipt until
+// it grows. Returns the space to grow it to; never less than it has.
+// The zeroed bytes it grows by read as a refund_window of 0, which only
+// legacy receipts have.
+pub fn receipt_space(data: &[u8]) -> Result<usize> {
+    if data.len() < 8 || data[..8] != Receipt::discriminator() {
+        return Err(error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch));
+    }
+    if let Ok(receipt) = Receipt::try_deserialize(&mut &data[..]) {
+        if !receipt.needs_migration() {
+            return Err(error!(StrangemoodError::AccountAlreadyMigrated));
+        }
+    }
+    LegacyReceipt::deserialize(&mut &data[8..])
+        .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?;
+
+    Ok(Receipt::SPACE.max(data.len()))
+}
+
+// Starts a legacy receipt's refund window at "now", since when it really
+// started was never stored. The account has to have grown to receipt_space.
+pub fn migrate_receipt(data: &mut [u8], now: i64) -> Result<Receipt> {
+    let mut receipt = Receipt::try_deserialize(&mut &data[..])?;
+    if !receipt.needs_migration() {
+        return Err(error!(StrangemoodError::AccountAlreadyMigrated));
+    }
+
+    receipt.started_at = now;
+    receipt.refund_window = LEGACY_REFUND_WINDOW;
+
+    write(data, &receipt)?;
+    Ok(receipt)
+}
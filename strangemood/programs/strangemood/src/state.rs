@@ -1,10 +1,23 @@
 use anchor_lang::{account, prelude::*};
 
+use std::cmp;
+use std::convert::TryFrom;
+
+use crate::error::StrangemoodError;
+use crate::math;
+
+// The current layout version of the Charter, CharterTreasury and Listing accounts.
+pub const ACCOUNT_VERSION: u8 = 1;
+
+// The refund window, in seconds, given to listings and trials that were
+// created before refund windows existed.
+pub const LEGACY_REFUND_WINDOW: i64 = 7 * 24 * 60 * 60;
+
 #[cfg(test)]
 mod tests {
     use anchor_lang::{prelude::Pubkey, AccountDeserialize, AccountSerialize};
 
-    use super::Receipt;
+    use super::{AutoRenewal, AutoRenewalPayment, Bundle, Charter, Coupon, Gift, Grant, GrantVesting, Listing, ListingSplits, Proposal, ProposalAction, PurchaseReceipt, Receipt, ResaleOffer, SplitRecipient, Subscription, VoteRecord, WalletPurchases};
 
     #[test]
     fn receipt() {
@@ -18,13 +31,15 @@ mod tests {
             escrow: Pubkey::new_unique(),
             quantity: 8,
             price: 10,
+            started_at: 1,
+            refund_window: 2,
         };
         let mut buf = Vec::new();
         r.try_serialize(&mut buf).unwrap();
         let len = buf.len();
         Receipt::try_deserialize(&mut buf.as_slice()).unwrap();
 
-        assert!(len == 154);
+        assert!(len == 170);
 
         // With cashier
         let r = Receipt {
@@ -36,13 +51,491 @@ mod tests {
             escrow: Pubkey::new_unique(),
             quantity: 8,
             price: 10,
+            started_at: 1,
+            refund_window: 2,
         };
         let mut buf = Vec::new();
         r.try_serialize(&mut buf).unwrap();
         let len = buf.len();
         Receipt::try_deserialize(&mut buf.as_slice()).unwrap();
 
-        assert!(len == 186);
+        assert!(len == 202);
+    }
+
+    #[test]
+    fn receipt_refund_window() {
+        let mut r = Receipt {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            cashier: None,
+            escrow: Pubkey::new_unique(),
+            quantity: 1,
+            price: 10,
+            started_at: 1_000,
+            refund_window: 60,
+        };
+
+        // Refundable up to and including the last second of the window
+        assert!(r.is_refundable_at(1_000));
+        assert!(r.is_refundable_at(1_060));
+        assert!(!r.is_refundable_at(1_061));
+
+        // Without a window, a trial is refundable until it's finished
+        // Receipts from before refund windows have to be migrated
+        assert!(!r.needs_migration());
+        r.refund_window = 0;
+        assert!(r.needs_migration());
+    }
+
+    #[test]
+    fn purchase_receipt() {
+        let r = PurchaseReceipt {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            cashier: Some(Pubkey::new_unique()),
+            quantity: 8,
+            price: 10,
+            slot: 1,
+            timestamp: 2,
+            nonce: 3,
+        };
+        let mut buf = Vec::new();
+        r.try_serialize(&mut buf).unwrap();
+        let len = buf.len();
+        PurchaseReceipt::try_deserialize(&mut buf.as_slice()).unwrap();
+
+        assert!(len <= PurchaseReceipt::SPACE);
+    }
+
+    #[test]
+    fn listing_sale() {
+        let mut l = Listing {
+            is_initialized: true,
+            is_available: true,
+            is_suspended: false,
+            charter: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            payment_deposit: Pubkey::new_unique(),
+            vote_deposit: Pubkey::new_unique(),
+            price: 100,
+            mint: Pubkey::new_unique(),
+            is_refundable: false,
+            is_consumable: false,
+            cashier_split: 0,
+            uri: "ipfs://cid".to_string(),
+            version: 1,
+            refund_window: 0,
+            max_supply: 0,
+            max_per_wallet: 0,
+            supply: 0,
+            sale_price: 0,
+            sale_starts_at: 0,
+            sale_ends_at: 0,
+            has_splits: false,
+            requires: None,
+            subscription_period: 0,
+            renewal_price: 0,
+            is_resellable: false,
+            resale_royalty: 0,
+            open_trials: 0,
+            open_gifts: 0,
+            pending_authority: None,
+            legacy_trials_until: 0,
+        };
+
+        // Without a sale
+        assert_eq!(l.price_at(1_000), 100);
+
+        // The sale starts at sale_starts_at, and ends just before sale_ends_at
+        l.sale_price = 60;
+        l.sale_starts_at = 1_000;
+        l.sale_ends_at = 2_000;
+        assert_eq!(l.price_at(999), 100);
+        assert_eq!(l.price_at(1_000), 60);
+        assert_eq!(l.price_at(1_999), 60);
+        assert_eq!(l.price_at(2_000), 100);
+    }
+
+    #[test]
+    fn coupon() {
+        let mut c = Coupon {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            discount_amount: 30,
+            discount_rate: 0,
+            max_uses: 0,
+            uses: 0,
+            expires_at: 0,
+            purchaser: Some(Pubkey::new_unique()),
+            nonce: 1,
+        };
+        let mut buf = Vec::new();
+        c.try_serialize(&mut buf).unwrap();
+        assert!(buf.len() <= Coupon::SPACE);
+
+        // A fixed amount off, which can't go below free
+        assert_eq!(c.discounted_price(100).unwrap(), 70);
+        assert_eq!(c.discounted_price(20).unwrap(), 0);
+
+        // A percentage off, where the buyer's price rounds down
+        c.discount_amount = 0;
+        c.discount_rate = 2_500;
+        assert_eq!(c.discounted_price(100).unwrap(), 75);
+        assert_eq!(c.discounted_price(3).unwrap(), 2);
+    }
+
+    #[test]
+    fn listing_splits() {
+        let splits = ListingSplits {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            recipients: vec![
+                SplitRecipient {
+                    deposit: Pubkey::new_unique(),
+                    share: 1,
+                };
+                ListingSplits::MAX_RECIPIENTS
+            ],
+        };
+        let mut buf = Vec::new();
+        splits.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), ListingSplits::SPACE);
+    }
+
+    #[test]
+    fn bundle() {
+        let bundle = Bundle {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            charter: Pubkey::new_unique(),
+            listings: vec![Pubkey::new_unique(); Bundle::MAX_LISTINGS],
+            weights: vec![u64::MAX; Bundle::MAX_LISTINGS],
+            price: 15,
+            is_available: true,
+            nonce: 1,
+            uri: "a".repeat(Bundle::MAX_URI_LENGTH),
+        };
+        let mut buf = Vec::new();
+        bundle.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), Bundle::SPACE);
+    }
+
+    #[test]
+    fn subscription() {
+        let mut s = Subscription {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            expires_at: 0,
+        };
+        let mut buf = Vec::new();
+        s.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), Subscription::SPACE);
+
+        // A new subscription starts now
+        s.extend(1_000, 30, 2).unwrap();
+        assert_eq!(s.expires_at, 1_060);
+        assert!(s.is_active_at(1_059));
+        assert!(!s.is_active_at(1_060));
+
+        // Renewing early extends from the current expiry
+        s.extend(1_050, 30, 1).unwrap();
+        assert_eq!(s.expires_at, 1_090);
+
+        // Renewing after it's lapsed starts over from now
+        s.extend(2_000, 30, 1).unwrap();
+        assert_eq!(s.expires_at, 2_030);
+    }
+
+    #[test]
+    fn auto_renewal() {
+        let a = AutoRenewal {
+            is_initialized: true,
+            subscription: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            payment: Pubkey::new_unique(),
+        };
+        let mut buf = Vec::new();
+        a.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), AutoRenewal::SPACE);
+
+        let p = AutoRenewalPayment {
+            is_initialized: true,
+            payment: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            renewals: 2,
+        };
+        let mut buf = Vec::new();
+        p.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), AutoRenewalPayment::SPACE);
+    }
+
+    #[test]
+    fn resale_offer() {
+        let o = ResaleOffer {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            seller: Pubkey::new_unique(),
+            inventory: Pubkey::new_unique(),
+            deposit: Pubkey::new_unique(),
+            price: 50,
+            quantity: 2,
+        };
+        let mut buf = Vec::new();
+        o.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), ResaleOffer::SPACE);
+    }
+
+    #[test]
+    fn gift() {
+        let recipient = Pubkey::new_unique();
+        let mut g = Gift {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            recipient: Some(recipient),
+            secret_key: None,
+            quantity: 1,
+            expires_at: 1_000,
+            nonce: 0,
+        };
+        let mut buf = Vec::new();
+        g.try_serialize(&mut buf).unwrap();
+        assert!(buf.len() <= Gift::SPACE);
+
+        // Only the recipient can claim it
+        assert!(g.check_claim(&recipient, &[]).is_ok());
+        assert!(g.check_claim(&Pubkey::new_unique(), &[]).is_err());
+
+        // Anyone the secret key signs for can claim it
+        let secret_key = Pubkey::new_unique();
+        g.recipient = None;
+        g.secret_key = Some(secret_key);
+        assert!(g.check_claim(&recipient, &[secret_key]).is_ok());
+        assert!(g.check_claim(&recipient, &[Pubkey::new_unique()]).is_err());
+        assert!(g.check_claim(&recipient, &[]).is_err());
+    }
+
+    #[test]
+    fn charter_changes_wait_for_the_delay() {
+        let mut c = Charter {
+            is_initialized: true,
+            expansion_rate: 100_000,
+            payment_contribution: 100,
+            vote_contribution: 2_000,
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            withdraw_period: 1,
+            stake_withdraw_amount: 1,
+            uri: "https://strangemood.org".to_string(),
+            version: 1,
+            pending_authority: None,
+            change_delay: 0,
+            pending_change: None,
+            max_payment_contribution: None,
+            max_vote_contribution: None,
+        };
+
+        // Without a delay, changes apply right away
+        c.queue_change(1_000, |p| p.change_delay = 100).unwrap();
+        assert_eq!(c.change_delay, 100);
+        assert!(c.pending_change.is_none());
+
+        let change = c.queue_change(1_000, |p| p.payment_contribution = 500).unwrap();
+        assert_eq!(change.effective_at, 1_100);
+        assert_eq!(c.payment_contribution, 100);
+        assert_eq!(c.active_at(1_099).payment_contribution, 100);
+        assert_eq!(c.active_at(1_100).payment_contribution, 500);
+
+        // Changes stack, and the whole set waits for the latest delay
+        let change = c.queue_change(1_050, |p| p.expansion_rate = 50_000).unwrap();
+        assert_eq!(change.effective_at, 1_150);
+        assert_eq!(change.payment_contribution, 500);
+        assert_eq!(c.active_at(1_100).payment_contribution, 100);
+
+        // A change that's taken effect is written in before the next one
+        c.queue_change(2_000, |p| p.vote_contribution = 0).unwrap();
+        assert_eq!(c.payment_contribution, 500);
+        assert_eq!(c.expansion_rate, 50_000);
+        assert_eq!(c.vote_contribution, 2_000);
+        assert_eq!(c.active_at(2_100).vote_contribution, 0);
+    }
+
+    #[test]
+    fn charter_contributions_stay_under_the_caps() {
+        let mut c = Charter {
+            is_initialized: true,
+            expansion_rate: 100_000,
+            payment_contribution: 100,
+            vote_contribution: 2_000,
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            withdraw_period: 1,
+            stake_withdraw_amount: 1,
+            uri: "https://strangemood.org".to_string(),
+            version: 1,
+            pending_authority: None,
+            change_delay: 0,
+            pending_change: None,
+            max_payment_contribution: None,
+            max_vote_contribution: None,
+        };
+
+        // Without caps, rates can go up to 100%, but not above
+        assert!(c.check_contributions(10_000, 10_000).is_ok());
+        assert!(c.check_contributions(10_001, 0).is_err());
+        assert!(c.check_contributions(0, 10_001).is_err());
+
+        c.max_payment_contribution = Some(500);
+        c.max_vote_contribution = Some(3_000);
+        assert!(c.check_contributions(500, 3_000).is_ok());
+        assert!(c.check_contributions(501, 3_000).is_err());
+        assert!(c.check_contributions(500, 3_001).is_err());
+
+        // Queued changes are checked too, and rejected ones aren't queued
+        assert!(c.queue_change(0, |p| p.payment_contribution = 501).is_err());
+        assert_eq!(c.payment_contribution, 100);
+        assert!(c.pending_change.is_none());
+    }
+
+    #[test]
+    fn grant() {
+        let mut g = Grant {
+            is_initialized: true,
+            charter: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            amount: 1_000,
+            claimed: 0,
+            vesting: Some(GrantVesting {
+                starts_at: 100,
+                ends_at: 200,
+            }),
+            nonce: 0,
+            payer: Pubkey::new_unique(),
+        };
+        let mut buf = Vec::new();
+        g.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), Grant::SPACE);
+
+        assert!(Grant::check_terms(0, None).is_err());
+        assert!(Grant::check_terms(1, g.vesting).is_ok());
+        assert!(Grant::check_terms(1, Some(GrantVesting { starts_at: 200, ends_at: 200 })).is_err());
+
+        // Vests linearly between starts_at and ends_at
+        assert_eq!(g.vested_at(0).unwrap(), 0);
+        assert_eq!(g.vested_at(100).unwrap(), 0);
+        assert_eq!(g.vested_at(125).unwrap(), 250);
+        assert_eq!(g.vested_at(199).unwrap(), 990);
+        assert_eq!(g.vested_at(200).unwrap(), 1_000);
+        assert_eq!(g.vested_at(i64::MAX).unwrap(), 1_000);
+
+        // Without a schedule, it's all vested right away
+        g.vesting = None;
+        assert_eq!(g.vested_at(0).unwrap(), 1_000);
+
+        // Cancelling a grant without a schedule revokes what isn't claimed
+        g.claimed = 400;
+        g.cancel(0).unwrap();
+        assert_eq!(g.amount, 400);
+
+        // Cancelling a vesting grant keeps what's vested
+        g.amount = 1_000;
+        g.vesting = Some(GrantVesting {
+            starts_at: 100,
+            ends_at: 200,
+        });
+        g.cancel(150).unwrap();
+        assert_eq!(g.amount, 500);
+        assert!(g.vesting.is_none());
+    }
+
+    #[test]
+    fn proposal() {
+        let mut p = Proposal {
+            is_initialized: true,
+            governance: Pubkey::new_unique(),
+            proposer: Pubkey::new_unique(),
+            index: 0,
+            action: ProposalAction::InitGrant {
+                treasury: Pubkey::new_unique(),
+                recipient: Pubkey::new_unique(),
+                nonce: 0,
+                amount: u64::MAX,
+                vesting: Some(GrantVesting {
+                    starts_at: 0,
+                    ends_at: 1,
+                }),
+            },
+            quorum: 100,
+            threshold: 5_000,
+            voting_ends_at: 1_000,
+            executable_until: 2_000,
+            votes_for: 0,
+            votes_against: 0,
+            is_executed: false,
+        };
+        let mut buf = Vec::new();
+        p.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), Proposal::SPACE);
+
+        // Nobody voted
+        assert!(!p.has_passed());
+
+        // Below quorum
+        p.votes_for = 99;
+        assert!(!p.has_passed());
+
+        // Exactly half is enough with a 50% threshold
+        p.votes_for = 50;
+        p.votes_against = 50;
+        assert!(p.has_passed());
+        p.votes_against = 51;
+        assert!(!p.has_passed());
+
+        // Doesn't overflow on huge votes
+        p.votes_for = u64::MAX;
+        p.votes_against = u64::MAX;
+        assert!(p.has_passed());
+    }
+
+    #[test]
+    fn vote_record() {
+        let v = VoteRecord {
+            is_initialized: true,
+            proposal: Pubkey::new_unique(),
+            voter: Pubkey::new_unique(),
+            escrow: Pubkey::new_unique(),
+            amount: 10,
+            approve: true,
+        };
+        let mut buf = Vec::new();
+        v.try_serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), VoteRecord::SPACE);
+    }
+
+    #[test]
+    fn wallet_purchases() {
+        let w = WalletPurchases {
+            is_initialized: true,
+            listing: Pubkey::new_unique(),
+            purchaser: Pubkey::new_unique(),
+            quantity: 3,
+        };
+        let mut buf = Vec::new();
+        w.try_serialize(&mut buf).unwrap();
+        let len = buf.len();
+        WalletPurchases::try_deserialize(&mut buf.as_slice()).unwrap();
+
+        assert!(len <= WalletPurchases::SPACE);
     }
 }
 
@@ -57,7 +550,8 @@ pub struct Receipt {
 
     // The token account to send the listing tokens to
     // It's possible to purchase the game for another person,
-    // So this is not necessarily the purchaser's token account
+    // So this is not necessarily the purchaser's token account.
+    // To buy for someone without knowing their inventory, see Gift.
     pub inventory: Pubkey,
 
     // The user that purchased the listing
@@ -76,6 +570,91 @@ pub struct Receipt {
     // The price when they bought the listing. We store this here
     // because the price could be updated in between purchase and cash.
     pub price: u64,
+
+    // The unix timestamp of when the trial started
+    pub started_at: i64,
+
+    // The listing's refund window, in seconds, when the trial started.
+    // We store this here so the lister can't shorten the window on
+    // trials that are already running. Trials can't start without a
+    // window, so 0 means the receipt is from before refund windows
+    // existed, and has to be migrated with MigrateReceipt.
+    pub refund_window: i64,
+}
+
+impl Receipt {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for inventory
+    // 32 for purchaser
+    // 32 + 1 for cashier
+    // 32 for escrow
+    // 8 for quantity
+    // 8 for price
+    // 8 for started_at
+    // 8 for refund_window
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 32 + 8 + 8 + 8 + 8;
+
+    // Whether the purchaser can still refund this trial at the unix timestamp "now".
+    // Once this is false, anyone can finish the trial.
+    pub fn is_refundable_at(&self, now: i64) -> bool {
+        now <= self.started_at.saturating_add(self.refund_window)
+    }
+
+    pub fn needs_migration(&self) -> bool {
+        self.refund_window == 0
+    }
+}
+
+// A durable proof-of-purchase for a sale that didn't go through a trial.
+// Unlike a Receipt, this is never closed by the program, so it outlives
+// the listing tokens it records.
+#[account]
+pub struct PurchaseReceipt {
+    /// Set to "true" by the program when Purchase is run with a receipt
+    pub is_initialized: bool,
+
+    // The listing that was purchased
+    pub listing: Pubkey,
+
+    // The token account the listing tokens were sent to
+    pub inventory: Pubkey,
+
+    // The user that purchased the listing
+    pub purchaser: Pubkey,
+
+    // The cashier, if the sale went through one.
+    pub cashier: Option<Pubkey>,
+
+    // The amount of the listing token that was purchased
+    pub quantity: u64,
+
+    // The price of 1 listing token at the time of purchase
+    pub price: u64,
+
+    // When the purchase happened
+    pub slot: u64,
+    pub timestamp: i64,
+
+    // The purchaser-chosen nonce used to derive this receipt's address
+    pub nonce: u64,
+}
+
+impl PurchaseReceipt {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for inventory
+    // 32 for purchaser
+    // 32 + 1 for cashier
+    // 8 for quantity
+    // 8 for price
+    // 8 for slot
+    // 8 for timestamp
+    // 8 for nonce
+    // 64 for future versions
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + (32 + 1) + 8 + 8 + 8 + 8 + 8 + 64;
 }
 
 #[account]
@@ -123,12 +702,509 @@ pub struct Listing {
     // in-app purchases, and so on.
     pub is_consumable: bool,
 
-    // A % of the sale that gets split between cashier and the lister
-    pub cashier_split: f64,
+    // The % of the sale (after the charter's contribution) that goes
+    // to the cashier, in basis points. The lister keeps the rest.
+    pub cashier_split: u64,
 
     // The URI for where metadata can be found for this listing.
     // Example: "ipns://examplehere", "https://example.com/metadata.json"
     pub uri: String,
+
+    // The layout version of this account. Listings created before
+    // rates were stored as basis points are version 0, and must be
+    // migrated with MigrateListing before they can be purchased.
+    pub version: u8,
+
+    // How long, in seconds, a purchaser has to refund a trial of this
+    // listing. After that, anyone can finish the trial and pay the lister.
+    // If 0, trials can't be started.
+    pub refund_window: i64,
+
+    // The most listing tokens that can ever be sold. If 0, there's no limit.
+    pub max_supply: u64,
+
+    // The most listing tokens a single purchaser can buy. If 0, there's no limit.
+    pub max_per_wallet: u64,
+
+    // The number of listing tokens sold so far, including trials
+    // that haven't been refunded.
+    pub supply: u64,
+
+    // A scheduled sale. From sale_starts_at until (but not including)
+    // sale_ends_at, as unix timestamps, the listing sells for sale_price
+    // instead of price. If sale_ends_at is 0, there's no sale.
+    pub sale_price: u64,
+    pub sale_starts_at: i64,
+    pub sale_ends_at: i64,
+
+    // If true, the lister's share of each sale is split between the
+    // recipients in this listing's ListingSplits, instead of all going
+    // to the payment_deposit.
+    pub has_splits: bool,
+
+    // If set, only purchasers who own this other listing's token can buy
+    // this listing. Used for DLC, expansions, and season passes.
+    pub requires: Option<Pubkey>,
+
+    // If not 0, this listing is a subscription, and each listing token
+    // bought adds this many seconds to the inventory's Subscription.
+    pub subscription_period: i64,
+
+    // The price of 1 listing token when renewing a subscription. If 0,
+    // renewals cost the same as the first purchase.
+    pub renewal_price: u64,
+
+    // If true, owners of this listing's token can resell it to other
+    // users through a ResaleOffer.
+    pub is_resellable: bool,
+
+    // The lister's royalty on resales, in basis points of what's left
+    // after the charter's contribution.
+    pub resale_royalty: u64,
+
+    // The number of trials that haven't been finished or refunded, and
+    // gifts that haven't been claimed or reclaimed. A listing can't be
+    // closed while either is above 0.
+    //
+    // Trials started before this was tracked are counted once their
+    // receipts are migrated.
+    pub open_trials: u64,
+    pub open_gifts: u64,
+
+    // The authority this listing is being handed to, if any. The
+    // authority only changes once this key signs AcceptListingAuthority.
+    pub pending_authority: Option<Pubkey>,
+
+    // 0 for listings created by InitListing, since open_trials has counted
+    // every trial of the listing. Listings migrated from the legacy layout
+    // may have trials that aren't counted until their receipts migrate, so
+    // they can't be closed until LEGACY_REFUND_WINDOW after the listing
+    // migrated. Closing gives up payment for trials that still haven't.
+    pub legacy_trials_until: i64,
+}
+
+impl Listing {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 1 for is_available
+    // 1 for is_suspended
+    // 32 for charter
+    // 32 for authority
+    // 32 for payment_deposit
+    // 32 for vote_deposit
+    // 8 for price
+    // 32 for mint
+    // 1 for is_refundable
+    // 1 for is_consumable
+    // 8 for cashier_split
+    // 256 for metadata URI
+    // 1 for version
+    // 8 for refund_window
+    // 8 for max_supply
+    // 8 for max_per_wallet
+    // 8 for supply
+    // 8 for sale_price
+    // 8 for sale_starts_at
+    // 8 for sale_ends_at
+    // 1 for has_splits
+    // 32 + 1 for requires
+    // 8 for subscription_period
+    // 8 for renewal_price
+    // 1 for is_resellable
+    // 8 for resale_royalty
+    // 8 for open_trials
+    // 8 for open_gifts
+    // 32 + 1 for pending_authority
+    // 8 for legacy_trials_until
+    // 128 for future versions
+    pub const SPACE: usize = 8 + 1 + 1 + 32 + 32 + 32 + 32 + 8 + 32 + 1 + 1 + 8 + 256 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + (32 + 1) + 8 + 8 + 1 + 8 + 8 + 8 + (32 + 1) + 8 + 128;
+
+    // The price of 1 listing token at the unix timestamp "now".
+    pub fn price_at(&self, now: i64) -> u64 {
+        if self.sale_starts_at <= now && now < self.sale_ends_at {
+            return self.sale_price;
+        }
+        self.price
+    }
+}
+
+// One recipient of a listing's revenue
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub struct SplitRecipient {
+    // The token account to pay, in the listing's payment mint
+    pub deposit: Pubkey,
+
+    // The recipient's share of the lister's revenue, in basis points
+    pub share: u64,
+}
+
+// How a listing's revenue is split between several parties, such as a
+// studio, its publisher, and its contractors. Lives at the PDA
+// ["listing_splits", listing].
+//
+// The shares add up to 100%, and apply to what's left of a sale after
+// the charter's contribution and the cashier's split.
+#[account]
+pub struct ListingSplits {
+    pub is_initialized: bool,
+
+    // The listing whose revenue is split
+    pub listing: Pubkey,
+
+    pub recipients: Vec<SplitRecipient>,
+}
+
+impl ListingSplits {
+    pub const MAX_RECIPIENTS: usize = 8;
+
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 4 for the length of recipients
+    // 40 for each recipient
+    pub const SPACE: usize = 8 + 1 + 32 + 4 + (32 + 8) * ListingSplits::MAX_RECIPIENTS;
+}
+
+// Several listings sold together at one price, like a game and its
+// soundtrack. Lives at the PDA ["bundle", authority, nonce].
+//
+// Every listing's authority agrees to the bundle when it's created, so its
+// listings and price can't change afterwards. To change them, make a new
+// bundle and make this one unavailable.
+#[account]
+pub struct Bundle {
+    pub is_initialized: bool,
+
+    // The entity that's allowed to make this bundle unavailable
+    pub authority: Pubkey,
+
+    // The charter that every listing in the bundle belongs to
+    pub charter: Pubkey,
+
+    pub listings: Vec<Pubkey>,
+
+    // How the bundle's price is split between its listings, one weight per
+    // listing in the same order. These are agreed to by every listing's
+    // authority, so no one listing can change its part afterwards.
+    pub weights: Vec<u64>,
+
+    // Lamports required to purchase 1 of each listing in the bundle.
+    // Split between the listings in proportion to their weights.
+    pub price: u64,
+
+    // If "false", this bundle cannot be bought. Its listings
+    // can still be bought on their own.
+    pub is_available: bool,
+
+    // The nonce used to derive this bundle's address
+    pub nonce: u64,
+
+    // The URI for where metadata can be found for this bundle.
+    pub uri: String,
+}
+
+impl Bundle {
+    // Each listing adds 7 accounts to a bundle purchase, so bundles are
+    // kept small enough for a purchase to fit in one transaction.
+    pub const MAX_LISTINGS: usize = 3;
+
+    pub const MAX_URI_LENGTH: usize = 256;
+
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for authority
+    // 32 for charter
+    // 4 + 32 for each listing
+    // 4 + 8 for each weight
+    // 8 for price
+    // 1 for is_available
+    // 8 for nonce
+    // 4 + 256 for uri
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + (4 + 32 * Bundle::MAX_LISTINGS) + (4 + 8 * Bundle::MAX_LISTINGS) + 8 + 1 + 8 + (4 + Bundle::MAX_URI_LENGTH);
+}
+
+// A discount on a listing, created by the listing's authority and
+// redeemed by passing it to a purchase.
+#[account]
+pub struct Coupon {
+    pub is_initialized: bool,
+
+    // The listing this coupon discounts
+    pub listing: Pubkey,
+
+    // The discount, as either a fixed amount off the price of 1 listing
+    // token, or a % off it in basis points. Only one of these is set.
+    pub discount_amount: u64,
+    pub discount_rate: u64,
+
+    // The number of listing tokens that can be bought with this coupon,
+    // across all purchases. If 0, there's no limit.
+    pub max_uses: u64,
+
+    // The number of listing tokens that have been bought with this coupon.
+    pub uses: u64,
+
+    // The unix timestamp after which this coupon can't be used. If 0, it never expires.
+    pub expires_at: i64,
+
+    // If set, only this user can redeem the coupon.
+    pub purchaser: Option<Pubkey>,
+
+    // The nonce used to derive this coupon's address
+    pub nonce: u64,
+}
+
+impl Coupon {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 8 for discount_amount
+    // 8 for discount_rate
+    // 8 for max_uses
+    // 8 for uses
+    // 8 for expires_at
+    // 32 + 1 for purchaser
+    // 8 for nonce
+    pub const SPACE: usize = 8 + 1 + 32 + 8 + 8 + 8 + 8 + 8 + (32 + 1) + 8;
+
+    // The price of 1 listing token after this coupon's discount.
+    pub fn discounted_price(&self, price: u64) -> Result<u64> {
+        if self.discount_rate != 0 {
+            let (kept, _) = math::split(price, self.discount_rate)?;
+            return Ok(kept);
+        }
+        Ok(price.saturating_sub(self.discount_amount))
+    }
+}
+
+// When an inventory's subscription to a listing runs out. Lives at the
+// PDA ["subscription", inventory], and is extended by every purchase of
+// a subscription listing into that inventory.
+#[account]
+pub struct Subscription {
+    pub is_initialized: bool,
+
+    // The subscription listing
+    pub listing: Pubkey,
+
+    // The inventory that holds the subscription's listing tokens
+    pub inventory: Pubkey,
+
+    // The purchaser who owns the inventory
+    pub purchaser: Pubkey,
+
+    // The unix timestamp when the subscription stops being active
+    pub expires_at: i64,
+}
+
+impl Subscription {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for inventory
+    // 32 for purchaser
+    // 8 for expires_at
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + 8;
+
+    pub fn is_active_at(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+
+    // Adds "periods" periods to the subscription, starting from when it
+    // expires if it's still active, or from "now" if it isn't.
+    pub fn extend(&mut self, now: i64, period: i64, periods: u64) -> Result<()> {
+        let periods = i64::try_from(periods).map_err(|_| error!(StrangemoodError::ArithmeticOverflow))?;
+        let length = period
+            .checked_mul(periods)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        self.expires_at = cmp::max(now, self.expires_at)
+            .checked_add(length)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+// A purchaser's opt-in to renewing a subscription automatically. Lives at
+// the PDA ["auto_renewal", subscription].
+//
+// The purchaser approves the PDA ["token_authority", payment] as a delegate
+// over their payment account, and the allowance they approve is the most
+// that can ever be charged for renewals from that payment account.
+#[account]
+pub struct AutoRenewal {
+    pub is_initialized: bool,
+
+    // The subscription to renew
+    pub subscription: Pubkey,
+
+    // The subscription's purchaser, who gets this account's rent back
+    pub purchaser: Pubkey,
+
+    // The token account that renewals are paid from
+    pub payment: Pubkey,
+}
+
+impl AutoRenewal {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for subscription
+    // 32 for purchaser
+    // 32 for payment
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32;
+}
+
+// Counts the AutoRenewals that renew from a payment account, at the PDA
+// ["auto_renewal_payment", payment]. They all share the payment account's
+// one delegate, so it's only revoked once the last of them is cancelled.
+#[account]
+pub struct AutoRenewalPayment {
+    pub is_initialized: bool,
+
+    // The token account that renewals are paid from
+    pub payment: Pubkey,
+
+    // The payment account's owner, who gets this account's rent back
+    pub purchaser: Pubkey,
+
+    // The number of AutoRenewals that renew from the payment account
+    pub renewals: u64,
+}
+
+impl AutoRenewalPayment {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for payment
+    // 32 for purchaser
+    // 8 for renewals
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8;
+}
+
+// An offer to resell listing tokens out of a seller's inventory, at the
+// PDA ["resale_offer", inventory]. Buyers can fill part of it, and it's
+// closed once it's filled.
+#[account]
+pub struct ResaleOffer {
+    pub is_initialized: bool,
+
+    // The listing whose tokens are for sale
+    pub listing: Pubkey,
+
+    // The owner of the inventory, who gets this account's rent back
+    pub seller: Pubkey,
+
+    // The seller's inventory, which the listing tokens are moved out of
+    pub inventory: Pubkey,
+
+    // The token account that the seller is paid into, in the listing's payment mint
+    pub deposit: Pubkey,
+
+    // The price of 1 listing token, before the charter's contribution
+    // and the lister's royalty
+    pub price: u64,
+
+    // The number of listing tokens left for sale
+    pub quantity: u64,
+}
+
+impl ResaleOffer {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for seller
+    // 32 for inventory
+    // 32 for deposit
+    // 8 for price
+    // 8 for quantity
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8;
+}
+
+// Listing tokens that were bought for someone else, at the PDA
+// ["gift", purchaser, nonce]. The tokens are minted when the gift is
+// claimed, or back to the purchaser if it isn't claimed in time.
+#[account]
+pub struct Gift {
+    pub is_initialized: bool,
+
+    // The listing that was bought
+    pub listing: Pubkey,
+
+    // Who bought the gift, and gets this account's rent back
+    pub purchaser: Pubkey,
+
+    // If set, only this user can claim the gift
+    pub recipient: Option<Pubkey>,
+
+    // If set, anyone who holds the secret key of this public key can claim
+    // the gift, by having it sign the claim. Its signature covers the whole
+    // transaction, including who the gift is claimed for, so a claim that's
+    // seen before it lands can't be redirected to someone else the way a
+    // revealed secret could. Exactly one of recipient and secret_key is set.
+    pub secret_key: Option<Pubkey>,
+
+    // The number of listing tokens to mint to whoever claims it
+    pub quantity: u64,
+
+    // The unix timestamp after which the gift can't be claimed, and
+    // the purchaser can reclaim it instead.
+    pub expires_at: i64,
+
+    // The nonce used to derive this gift's address
+    pub nonce: u64,
+}
+
+impl Gift {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for purchaser
+    // 32 + 1 for recipient
+    // 32 + 1 for secret_key
+    // 8 for quantity
+    // 8 for expires_at
+    // 8 for nonce
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + (32 + 1) + (32 + 1) + 8 + 8 + 8;
+
+    // Checks that "claimer" can claim this gift, given the keys that
+    // signed the claim, if the gift was locked with a secret key.
+    pub fn check_claim(&self, claimer: &Pubkey, signers: &[Pubkey]) -> Result<()> {
+        if let Some(recipient) = self.recipient {
+            if recipient != *claimer {
+                return Err(error!(StrangemoodError::GiftHasUnexpectedRecipient));
+            }
+            return Ok(());
+        }
+
+        match self.secret_key {
+            Some(secret_key) if signers.contains(&secret_key) => Ok(()),
+            _ => Err(error!(StrangemoodError::GiftSecretIsInvalid)),
+        }
+    }
+}
+
+// How many tokens of a listing a purchaser has bought, for
+// listings with a max_per_wallet.
+#[account]
+pub struct WalletPurchases {
+    pub is_initialized: bool,
+
+    // The listing that was purchased
+    pub listing: Pubkey,
+
+    // The user that purchased the listing
+    pub purchaser: Pubkey,
+
+    // The number of listing tokens they've bought, including trials
+    // that haven't been refunded.
+    pub quantity: u64,
+}
+
+impl WalletPurchases {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for listing
+    // 32 for purchaser
+    // 8 for quantity
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8;
 }
 
 #[account]
@@ -138,17 +1214,15 @@ pub struct Charter {
     // The amount of voting tokens to give to a user per
     // 1.0 wrapped SOL contributed via community account contributions.
     //
-    // Note that Borsh doesn't support floats, and so we carry over the pattern
-    // used in the token program of having an "amount" and a "decimals".
-    // So an "amount" of 100 and a "decimals" of 3 would be 0.1
-    pub expansion_rate: f64,
+    // Stored in basis points, so an expansion rate of 10.0 is 100_000.
+    pub expansion_rate: u64,
 
-    // The % of each purchase that goes to the community account.
-    pub payment_contribution: f64,
+    // The % of each purchase that goes to the community account, in basis points.
+    pub payment_contribution: u64,
 
     // The % of each vote token minting goes back to the governance to fund
-    // new ecosystem projects
-    pub vote_contribution: f64,
+    // new ecosystem projects, in basis points.
+    pub vote_contribution: u64,
 
     // The pubkey of the keypair that can modify this charter.
     // If this points to a system account, then this is basically
@@ -173,6 +1247,113 @@ pub struct Charter {
     // The URL host where off-chain services can be found for this governance.
     // Example: "https://strangemood.org", "http://localhost:3000", "https://api.strangemood.org:4040"
     pub uri: String,
+
+    // The layout version of this account. Charters created before
+    // rates were stored as basis points are version 0, and must be
+    // migrated with MigrateCharter before they can be used.
+    pub version: u8,
+
+    // The authority this charter is being handed to, if any, like a
+    // new governance program. It has to sign to accept the charter.
+    pub pending_authority: Option<Pubkey>,
+
+    // The minimum number of seconds between when a change to the rates
+    // above is queued and when it takes effect, so listers have notice
+    // to move to a different charter with SetListingCharter first.
+    pub change_delay: i64,
+
+    // The queued change, if any. Purchases use it as soon as it takes
+    // effect, and it's written into the fields above by the next change.
+    pub pending_change: Option<PendingCharterChange>,
+
+    // Hard caps on the contribution rates, in basis points. They can
+    // only ever be lowered, so listers know the most they'll be charged.
+    // If unset, the cap is 100%.
+    pub max_payment_contribution: Option<u64>,
+    pub max_vote_contribution: Option<u64>,
+}
+
+impl Charter {
+    // The space that InitCharter allocates for the uri
+    pub const MAX_URI_LENGTH: usize = 256;
+
+    // 8 for the tag
+    // 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 256 for the charter
+    // 1 for version
+    // 32 + 1 for pending_authority
+    // 8 for change_delay
+    // 8 * 5 + 1 for pending_change
+    // 8 + 1 for max_payment_contribution
+    // 8 + 1 for max_vote_contribution
+    // 256 as a buffer for future versions
+    pub const SPACE: usize = 8 + 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 256 + 1 + (32 + 1) + 8 + (8 * 5 + 1) + (8 + 1) + (8 + 1) + 256;
+
+    // The charter as of the unix timestamp "now", with the pending
+    // change applied if it's taken effect.
+    pub fn active_at(&self, now: i64) -> Charter {
+        let mut charter = self.clone();
+        if let Some(change) = self.pending_change {
+            if now >= change.effective_at {
+                charter.expansion_rate = change.expansion_rate;
+                charter.payment_contribution = change.payment_contribution;
+                charter.vote_contribution = change.vote_contribution;
+                charter.change_delay = change.change_delay;
+                charter.pending_change = None;
+            }
+        }
+        charter
+    }
+
+    // Checks that contribution rates are within the charter's caps.
+    pub fn check_contributions(&self, payment_contribution: u64, vote_contribution: u64) -> Result<()> {
+        if payment_contribution > self.max_payment_contribution.unwrap_or(math::BASIS_POINTS) {
+            return Err(error!(StrangemoodError::PaymentContributionIsInvalid));
+        }
+        if vote_contribution > self.max_vote_contribution.unwrap_or(math::BASIS_POINTS) {
+            return Err(error!(StrangemoodError::VoteContributionIsInvalid));
+        }
+        Ok(())
+    }
+
+    // Queues a change on top of any change that's already queued, to take
+    // effect after the charter's change_delay. Returns the queued change,
+    // which has already been applied if the delay is 0.
+    pub fn queue_change(
+        &mut self,
+        now: i64,
+        f: impl FnOnce(&mut PendingCharterChange),
+    ) -> Result<PendingCharterChange> {
+        *self = self.active_at(now);
+
+        let mut change = self.pending_change.unwrap_or(PendingCharterChange {
+            expansion_rate: self.expansion_rate,
+            payment_contribution: self.payment_contribution,
+            vote_contribution: self.vote_contribution,
+            change_delay: self.change_delay,
+            effective_at: now,
+        });
+        f(&mut change);
+        self.check_contributions(change.payment_contribution, change.vote_contribution)?;
+        change.effective_at = now
+            .checked_add(self.change_delay)
+            .ok_or(StrangemoodError::ArithmeticOverflow)?;
+
+        self.pending_change = Some(change);
+        *self = self.active_at(now);
+        Ok(change)
+    }
+}
+
+// A change to a charter that takes effect at effective_at
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub struct PendingCharterChange {
+    pub expansion_rate: u64,
+    pub payment_contribution: u64,
+    pub vote_contribution: u64,
+    pub change_delay: i64,
+
+    // A unix timestamp
+    pub effective_at: i64,
 }
 
 // An charter-approved deposit account. There is only one treasury per mint and charter.
@@ -192,8 +1373,13 @@ pub struct CharterTreasury {
     pub mint: Pubkey,
 
     // Increases or decreases the amount of voting tokens.
-    // distributed based on this deposit type.
-    pub scalar: f64,
+    // distributed based on this deposit type, in basis points.
+    pub scalar: u64,
+
+    // The layout version of this account. Treasuries created before
+    // the scalar was stored as basis points are version 0, and must be
+    // migrated with MigrateCharterTreasury before they can be used.
+    pub version: u8,
 }
 
 // A staked client that can receive a bounty if they initiate a sale.
@@ -218,6 +1404,14 @@ pub struct Cashier {
     // The URI for where metadata can be found for this charter.
     // Example: "ipns://examplehere", "https://example.com/metadata.json"
     pub uri: String,
+
+    // The authority this cashier is being handed to, if any.
+    pub pending_authority: Option<Pubkey>,
+}
+
+impl Cashier {
+    // The space that InitCashier allocates for the uri
+    pub const MAX_URI_LENGTH: usize = 256;
 }
 
 // A treasury owned by the cashier. There is only one mint per cashier
@@ -243,3 +1437,316 @@ pub struct CashierTreasury {
     // The last epoch the cashier has withdrawn from their deposit.
     pub last_withdraw_at: u64,
 }
+
+// A charter's native governance, at the PDA ["governance", charter].
+// Once initialized, this account is the charter's authority, so the
+// charter only changes when a Proposal passes a token-weighted vote.
+#[account]
+pub struct Governance {
+    pub is_initialized: bool,
+
+    // The charter this governs
+    pub charter: Pubkey,
+
+    // The minimum number of charter tokens that have to vote, for or
+    // against, for a proposal to pass.
+    pub quorum: u64,
+
+    // The share of the votes cast that have to be in favor for a
+    // proposal to pass, in basis points.
+    pub threshold: u64,
+
+    // The number of seconds a proposal is open for voting
+    pub voting_period: i64,
+
+    // The number of proposals made so far, used to derive the next
+    // proposal's address.
+    pub proposal_count: u64,
+}
+
+impl Governance {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for charter
+    // 8 for quorum
+    // 8 for threshold
+    // 8 for voting_period
+    // 8 for proposal_count
+    pub const SPACE: usize = 8 + 1 + 32 + 8 + 8 + 8 + 8;
+
+    // The number of seconds a passed proposal can be executed for, once
+    // voting ends
+    pub const EXECUTION_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+    // A threshold of 0 would pass anything, even with every vote against.
+    pub fn check_config(threshold: u64, voting_period: i64) -> Result<()> {
+        if threshold == 0 || threshold > math::BASIS_POINTS || voting_period <= 0 {
+            return Err(error!(StrangemoodError::GovernanceConfigIsInvalid));
+        }
+        Ok(())
+    }
+}
+
+// A change to a charter that a Proposal carries out if it passes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Debug)]
+pub enum ProposalAction {
+    SetCharterExpansionRate {
+        expansion_rate: u64,
+    },
+    SetCharterContributionRate {
+        payment_contribution: u64,
+        vote_contribution: u64,
+    },
+    SetCharterContributionCaps {
+        max_payment_contribution: u64,
+        max_vote_contribution: u64,
+    },
+    SetCharterChangeDelay {
+        change_delay: i64,
+    },
+    SetListingSuspension {
+        listing: Pubkey,
+        suspended: bool,
+    },
+
+    // Transfers tokens out of the charter's reserve, which has to be
+    // owned by the governance account.
+    SpendReserve {
+        destination: Pubkey,
+        amount: u64,
+    },
+
+    // Grants funds from one of the charter's treasuries, like InitGrant.
+    InitGrant {
+        treasury: Pubkey,
+        recipient: Pubkey,
+        nonce: u64,
+        amount: u64,
+        vesting: Option<GrantVesting>,
+    },
+
+    // Cancels one of the charter's grants, like CancelGrant.
+    CancelGrant {
+        grant: Pubkey,
+    },
+
+    // Proposes handing the charter to a different authority, which
+    // still has to accept it with AcceptCharterAuthority.
+    SetCharterAuthority {
+        authority: Pubkey,
+    },
+    SetGovernanceConfig {
+        quorum: u64,
+        threshold: u64,
+        voting_period: i64,
+    },
+}
+
+impl ProposalAction {
+    // The largest variant is InitGrant
+    pub const SPACE: usize = 1 + 32 + 32 + 8 + 8 + (1 + 8 + 8);
+}
+
+// A proposed change to a charter, at the PDA
+// ["proposal", governance, index].
+#[account]
+pub struct Proposal {
+    pub is_initialized: bool,
+
+    // The governance this was proposed to
+    pub governance: Pubkey,
+
+    // Who made the proposal, and gets this account's rent back
+    pub proposer: Pubkey,
+
+    // The governance's proposal_count when this was proposed
+    pub index: u64,
+
+    pub action: ProposalAction,
+
+    // The governance's quorum and threshold when this was proposed,
+    // so changing them doesn't change proposals that are already open.
+    pub quorum: u64,
+    pub threshold: u64,
+
+    // The unix timestamp when voting closes, and the proposal can be executed
+    pub voting_ends_at: i64,
+
+    // The unix timestamp after which a passed proposal can no longer be
+    // executed, so a stale one can't be carried out long after the vote.
+    pub executable_until: i64,
+
+    // The charter tokens locked in votes for and against this proposal
+    pub votes_for: u64,
+    pub votes_against: u64,
+
+    pub is_executed: bool,
+}
+
+impl Proposal {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for governance
+    // 32 for proposer
+    // 8 for index
+    // ProposalAction::SPACE for action
+    // 8 for quorum
+    // 8 for threshold
+    // 8 for voting_ends_at
+    // 8 for executable_until
+    // 8 for votes_for
+    // 8 for votes_against
+    // 1 for is_executed
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 8 + ProposalAction::SPACE + 8 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    // True if enough votes were cast, and enough of them were in favor.
+    pub fn has_passed(&self) -> bool {
+        let total = self.votes_for as u128 + self.votes_against as u128;
+        if total == 0 || total < self.quorum as u128 {
+            return false;
+        }
+        self.votes_for as u128 * math::BASIS_POINTS as u128 >= total * self.threshold as u128
+    }
+}
+
+// A vote on a proposal, at the PDA ["vote", proposal, voter].
+//
+// Votes aren't weighted by a snapshot of balances taken when the
+// proposal was made. Solana has no history of token balances to take one
+// from, so a vote is weighted by the charter tokens it locks in an escrow
+// until it's withdrawn instead. The same tokens can't be counted twice on
+// a proposal while they're locked, but tokens bought after a proposal
+// was made can still vote on it, and withdrawing a vote before voting
+// ends retracts it.
+#[account]
+pub struct VoteRecord {
+    pub is_initialized: bool,
+
+    pub proposal: Pubkey,
+
+    // Who voted, and gets the tokens and this account's rent back
+    pub voter: Pubkey,
+
+    // The token account, with the authority ["token_authority", escrow],
+    // that holds the locked charter tokens
+    pub escrow: Pubkey,
+
+    // The number of charter tokens locked, which is this vote's weight
+    pub amount: u64,
+
+    pub approve: bool,
+}
+
+impl VoteRecord {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for proposal
+    // 32 for voter
+    // 32 for escrow
+    // 8 for amount
+    // 1 for approve
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + 8 + 1;
+}
+
+// A linear vesting schedule. Nothing vests before starts_at, and
+// everything has vested by ends_at. Both are unix timestamps.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub struct GrantVesting {
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+// A grant of funds from a charter treasury to an ecosystem project, at
+// the PDA ["grant", charter, nonce]. A grant closes once it's fully
+// claimed, or cancelled with nothing left to claim; its events are the
+// record of what it paid. Grants are paid from the treasury's deposit,
+// which has to be owned by the PDA ["grant_authority", treasury].
+#[account]
+pub struct Grant {
+    pub is_initialized: bool,
+
+    pub charter: Pubkey,
+
+    // The charter treasury whose deposit pays the grant. The deposit has
+    // to be owned by the PDA ["token_authority", deposit].
+    pub treasury: Pubkey,
+
+    // The mint of the treasury, and of the grant
+    pub mint: Pubkey,
+
+    // The wallet the grant is paid to
+    pub recipient: Pubkey,
+
+    // The total amount granted
+    pub amount: u64,
+
+    // The amount the recipient has claimed so far
+    pub claimed: u64,
+
+    // If set, the grant vests over time rather than all at once
+    pub vesting: Option<GrantVesting>,
+
+    // The nonce used to derive this grant's address
+    pub nonce: u64,
+
+    // Who paid this account's rent, and gets it back when it closes
+    pub payer: Pubkey,
+}
+
+impl Grant {
+    // 8 for the tag
+    // 1 for is_initialized
+    // 32 for charter
+    // 32 for treasury
+    // 32 for mint
+    // 32 for recipient
+    // 8 for amount
+    // 8 for claimed
+    // 8 + 8 + 1 for vesting
+    // 8 for nonce
+    // 32 for payer
+    pub const SPACE: usize = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + (8 + 8 + 1) + 8 + 32;
+
+    // A grant needs an amount, and a vesting schedule that ends after it starts.
+    pub fn check_terms(amount: u64, vesting: Option<GrantVesting>) -> Result<()> {
+        if amount == 0 {
+            return Err(error!(StrangemoodError::GrantIsInvalid));
+        }
+        if let Some(vesting) = vesting {
+            if vesting.ends_at <= vesting.starts_at {
+                return Err(error!(StrangemoodError::GrantIsInvalid));
+            }
+        }
+        Ok(())
+    }
+
+    // Stops the grant from paying out any more than what's vested at
+    // the unix timestamp "now". A grant without a vesting schedule is
+    // vested as soon as it's made, so this revokes what isn't claimed.
+    pub fn cancel(&mut self, now: i64) -> Result<()> {
+        self.amount = match self.vesting {
+            Some(_) => self.vested_at(now)?,
+            None => self.claimed,
+        };
+        self.vesting = None;
+        Ok(())
+    }
+
+    // The amount that's vested at the unix timestamp "now", claimed or not.
+    pub fn vested_at(&self, now: i64) -> Result<u64> {
+        let vesting = match self.vesting {
+            Some(vesting) => vesting,
+            None => return Ok(self.amount),
+        };
+        if now <= vesting.starts_at {
+            return Ok(0);
+        }
+        if now >= vesting.ends_at {
+            return Ok(self.amount);
+        }
+
+        let elapsed = (now - vesting.starts_at) as u64;
+        let duration = (vesting.ends_at - vesting.starts_at) as u64;
+        math::mul_div_saturating(self.amount, elapsed, duration)
+    }
+}
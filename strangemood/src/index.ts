@@ -109,6 +109,19 @@ async function asCharterInfo(
   };
 }
 
+// The payment contribution purchases pay right now, which is the
+// charter's queued change once it takes effect.
+function activePaymentContribution(charter: Charter): anchor.BN {
+  const now = Math.floor(Date.now() / 1000);
+  if (
+    charter.pendingChange &&
+    charter.pendingChange.effectiveAt.toNumber() <= now
+  ) {
+    return charter.pendingChange.paymentContribution;
+  }
+  return charter.paymentContribution;
+}
+
 async function asCharterTreasuryInfo(
   program: any,
   charter: PublicKey,
@@ -181,7 +194,7 @@ async function getOrCreateAssociatedTokenAccount(args: {
   };
 }
 
-async function maybeFundWrappedSolAccount({
+async function maybeCreateWrappedSolAccount({
   program,
   deposit,
   payment,
@@ -214,7 +227,9 @@ async function maybeFundWrappedSolAccount({
       );
     }
 
-    // Create the payment account if it doesn't exist
+    // Create the payment account if it doesn't exist. If it doesn't
+    // have enough wrapped SOL in it, the program makes up the difference
+    // from the signer's SOL during the purchase.
     if (!(await program.provider.connection.getAccountInfo(payment))) {
       instructions.push(
         createAssociatedTokenAccountInstruction(
@@ -224,42 +239,117 @@ async function maybeFundWrappedSolAccount({
           deposit.mint
         )
       );
+    }
+  }
+  return instructions;
+}
 
-      // And fund it with the wrapped sol from the signer
-      instructions.push(
-        SystemProgram.transfer({
-          fromPubkey: signer,
-          toPubkey: payment,
-          lamports: total.toNumber(),
-        })
-      );
+// Listings with a max_per_wallet track each purchaser's purchases in
+// a PDA, which is passed after the instruction's other accounts.
+async function walletPurchasesAccounts(
+  program: any,
+  listingInfo: AccountInfo<Listing>,
+  signer: PublicKey
+) {
+  const maxPerWallet = listingInfo.account.maxPerWallet;
+  if (!maxPerWallet || maxPerWallet.isZero()) {
+    return [];
+  }
+  const [walletPurchases] = await pda.wallet_purchases(
+    program.programId,
+    listingInfo.publicKey,
+    signer
+  );
+  return [{ pubkey: walletPurchases, isWritable: true, isSigner: false }];
+}
 
-      instructions.push(splToken.createSyncNativeInstruction(payment));
-    } else {
-      // If they don't have enough wrapped sol, fund the account with the difference
-      const paymentAccount = await splToken.getAccount(
-        program.provider.connection,
-        payment
-      );
-      const remaining = total.sub(
-        new anchor.BN(paymentAccount.amount.toString())
-      );
+// Listings that split their revenue need their ListingSplits, and every
+// recipient's token account, passed after the instruction's other accounts.
+async function listingSplitsAccounts(
+  program: any,
+  listingInfo: AccountInfo<Listing>
+) {
+  if (!listingInfo.account.hasSplits) {
+    return [];
+  }
+  const [listingSplits] = await pda.listing_splits(
+    program.programId,
+    listingInfo.publicKey
+  );
+  const splits = await program.account.listingSplits.fetch(listingSplits);
+  return [listingSplits, ...splits.recipients.map((r) => r.deposit)].map(
+    (pubkey: PublicKey) => ({ pubkey, isWritable: true, isSigner: false })
+  );
+}
 
-      // If we don't have enough funds in the wrapped SOL account, make
-      // up the difference by funding it from the signer
-      if (remaining.gt(new anchor.BN(0))) {
-        instructions.push(
-          SystemProgram.transfer({
-            fromPubkey: signer,
-            toPubkey: payment,
-            lamports: remaining.toNumber(),
-          })
-        );
-        instructions.push(splToken.createSyncNativeInstruction(payment));
-      }
-    }
+// Listings that require another listing need that listing, and the
+// signer's inventory of it, passed after the instruction's other accounts.
+async function requirementAccounts(
+  program: any,
+  listingInfo: AccountInfo<Listing>,
+  signer: PublicKey
+) {
+  if (!listingInfo.account.requires) {
+    return [];
   }
-  return instructions;
+  const parent = await program.account.listing.fetch(
+    listingInfo.account.requires
+  );
+  const inventory = await getAssociatedTokenAddress(parent.mint, signer);
+  return [listingInfo.account.requires, inventory].map(
+    (pubkey: PublicKey) => ({ pubkey, isWritable: false, isSigner: false })
+  );
+}
+
+// Subscription listings need the inventory's Subscription passed after
+// the instruction's other accounts. It's created on the first purchase.
+async function subscriptionAccounts(
+  program: any,
+  listingInfo: AccountInfo<Listing>,
+  inventory: PublicKey
+) {
+  const period = listingInfo.account.subscriptionPeriod;
+  if (!period || period.isZero()) {
+    return [];
+  }
+  const [subscription] = await pda.subscription(program.programId, inventory);
+  return [{ pubkey: subscription, isWritable: true, isSigner: false }];
+}
+
+// The signer's subscription to a listing, the payment account it renews
+// from, its AutoRenewal, and the payment account's AutoRenewalPayment.
+async function autoRenewalAccounts(
+  program: any,
+  listingInfo: AccountInfo<Listing>,
+  signer: PublicKey
+) {
+  const inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    signer
+  );
+  const [subscription] = await pda.subscription(program.programId, inventory);
+  const deposit = await splToken.getAccount(
+    program.provider.connection,
+    listingInfo.account.paymentDeposit
+  );
+  const payment = await getAssociatedTokenAddress(deposit.mint, signer);
+  const [autoRenewal] = await pda.auto_renewal(
+    program.programId,
+    subscription
+  );
+  const [autoRenewalPayment] = await pda.auto_renewal_payment(
+    program.programId,
+    payment
+  );
+  return { subscription, payment, autoRenewal, autoRenewalPayment };
+}
+
+// A coupon is passed after the instruction's other accounts, too.
+function couponAccounts(coupon?: PublicKey) {
+  if (!coupon) {
+    return [];
+  }
+  return [{ pubkey: coupon, isWritable: true, isSigner: false }];
 }
 
 async function purchaseWithoutCashier(args: {
@@ -267,6 +357,7 @@ async function purchaseWithoutCashier(args: {
   signer: PublicKey;
   listing: AccountInfo<Listing> | PublicKey;
   quantity: anchor.BN;
+  coupon?: PublicKey;
 }) {
   let instructions = [];
   let listingInfo = await asListingInfo(args.program, args.listing);
@@ -297,7 +388,7 @@ async function purchaseWithoutCashier(args: {
   );
   let payment = await getAssociatedTokenAddress(deposit.mint, args.signer);
 
-  const maybeFundWrappedSolInstructions = await maybeFundWrappedSolAccount({
+  const maybeCreateWrappedSolInstructions = await maybeCreateWrappedSolAccount({
     program: args.program,
     deposit,
     payment,
@@ -306,7 +397,7 @@ async function purchaseWithoutCashier(args: {
     quantity: args.quantity,
   });
 
-  instructions.push(...maybeFundWrappedSolInstructions);
+  instructions.push(...maybeCreateWrappedSolInstructions);
 
   // Setup PDAs
   let [_, listingBump] = await pda.listing(
@@ -333,7 +424,12 @@ async function purchaseWithoutCashier(args: {
       listingMintAuthorityBump,
       charterMintAuthorityBump,
       inventoryDelegateBump,
-      args.quantity
+      args.quantity,
+      // Fail rather than pay more than the price the buyer was shown
+      listingInfo.account.price.mul(args.quantity),
+      activePaymentContribution(charterInfo.account),
+      null,
+      args.coupon || null
     )
     .accounts({
       payment: payment,
@@ -352,6 +448,13 @@ async function purchaseWithoutCashier(args: {
       charterMintAuthority: charterMintAuthority,
       purchaser: args.signer,
     })
+    .remainingAccounts([
+      ...(await walletPurchasesAccounts(args.program, listingInfo, args.signer)),
+      ...(await listingSplitsAccounts(args.program, listingInfo)),
+      ...(await requirementAccounts(args.program, listingInfo, args.signer)),
+      ...(await subscriptionAccounts(args.program, listingInfo, inventory)),
+      ...couponAccounts(args.coupon),
+    ])
     .instruction();
 
   instructions.push(ix);
@@ -367,6 +470,7 @@ async function purchaseWithCashier(args: {
   listing: AccountInfo<Listing> | PublicKey;
   quantity: anchor.BN;
   cashier: AccountInfo<Cashier> | PublicKey;
+  coupon?: PublicKey;
 }) {
   let instructions = [];
   let listingInfo = await asListingInfo(args.program, args.listing);
@@ -398,7 +502,7 @@ async function purchaseWithCashier(args: {
   );
   let payment = await getAssociatedTokenAddress(deposit.mint, args.signer);
 
-  const maybeFundWrappedSolInstructions = await maybeFundWrappedSolAccount({
+  const maybeCreateWrappedSolInstructions = await maybeCreateWrappedSolAccount({
     program: args.program,
     deposit,
     payment,
@@ -406,7 +510,7 @@ async function purchaseWithCashier(args: {
     signer: args.signer,
     quantity: args.quantity,
   });
-  instructions.push(...maybeFundWrappedSolInstructions);
+  instructions.push(...maybeCreateWrappedSolInstructions);
 
   // Setup PDAs
   let [_, listingBump] = await pda.listing(
@@ -439,7 +543,12 @@ async function purchaseWithCashier(args: {
       listingMintAuthorityBump,
       charterMintAuthorityBump,
       inventoryDelegateBump,
-      args.quantity
+      args.quantity,
+      // Fail rather than pay more than the price the buyer was shown
+      listingInfo.account.price.mul(args.quantity),
+      activePaymentContribution(charterInfo.account),
+      null,
+      args.coupon || null
     )
     .accounts({
       cashier: cashierInfo.publicKey,
@@ -461,6 +570,13 @@ async function purchaseWithCashier(args: {
       charterMintAuthority: charterMintAuthority,
       purchaser: args.signer,
     })
+    .remainingAccounts([
+      ...(await walletPurchasesAccounts(args.program, listingInfo, args.signer)),
+      ...(await listingSplitsAccounts(args.program, listingInfo)),
+      ...(await requirementAccounts(args.program, listingInfo, args.signer)),
+      ...(await subscriptionAccounts(args.program, listingInfo, inventory)),
+      ...couponAccounts(args.coupon),
+    ])
     .instruction();
 
   instructions.push(ix);
@@ -476,6 +592,9 @@ export async function purchase(args: {
   listing: AccountInfo<Listing> | PublicKey;
   quantity: anchor.BN;
   cashier?: AccountInfo<Cashier> | PublicKey;
+
+  // A coupon for this listing, to discount the purchase
+  coupon?: PublicKey;
 }) {
   if (args.cashier) {
     return purchaseWithCashier({
@@ -484,125 +603,151 @@ export async function purchase(args: {
       listing: args.listing,
       quantity: args.quantity,
       cashier: args.cashier,
+      coupon: args.coupon,
     });
   } else {
     return purchaseWithoutCashier(args);
   }
 }
 
-export async function initListing(args: {
+export async function purchaseBundle(args: {
   program: any;
   signer: PublicKey;
+  bundle: PublicKey;
+  quantity: anchor.BN;
+}) {
+  let instructions = [];
+  let bundle = await args.program.account.bundle.fetch(args.bundle);
+  let charterInfo = await asCharterInfo(args.program, bundle.charter);
+
+  let listingMintAuthorityBumps = [];
+  let inventoryDelegateBumps = [];
+  let itemAccounts = [];
+  let otherAccounts = [];
+  let deposit: splToken.Account;
+  for (const listing of bundle.listings) {
+    let listingInfo = await asListingInfo(args.program, listing);
+    deposit = await splToken.getAccount(
+      args.program.provider.connection,
+      listingInfo.account.paymentDeposit
+    );
 
-  // In lamports
-  price: anchor.BN;
-
-  // The decimals on the underlying mint.
-  decimals?: number;
-
-  // Example: "ipfs://my-cid"
-  uri: string;
-
-  // Can the lister burn tokens?
-  isConsumable: boolean;
-
-  // Can the purchaser refund?
-  isRefundable: boolean;
-
-  // Can this listing be purchased?
-  isAvailable: boolean;
-
-  // The percentage of the sale that goes to the marketplace (cashier)
-  // that caused the sale. A value of 0.4 means 40% goes to the cashier.
-  // Must be between 0.0 and 1.0.
-  //
-  // To "opt out" of cashier splits, set this to 0.0.
-  cashierSplit: number;
+    // Create an inventory if it doesn't exist
+    let inventory = await getAssociatedTokenAddress(
+      listingInfo.account.mint,
+      args.signer
+    );
+    if (!(await args.program.provider.connection.getAccountInfo(inventory))) {
+      instructions.push(
+        createAssociatedTokenAccountInstruction(
+          args.signer,
+          inventory,
+          args.signer,
+          listingInfo.account.mint
+        )
+      );
+    }
 
-  // the mint to be paid in.
-  currency: PublicKey;
+    let [inventoryDelegate, inventoryDelegateBump] = await pda.token_authority(
+      args.program.programId,
+      inventory
+    );
+    let [listingMintAuthority, listingMintAuthorityBump] =
+      await pda.mint_authority(args.program.programId, listingInfo.account.mint);
+    listingMintAuthorityBumps.push(listingMintAuthorityBump);
+    inventoryDelegateBumps.push(inventoryDelegateBump);
+
+    // Each listing's accounts are passed in the bundle's order
+    itemAccounts.push(
+      ...[
+        listingInfo.publicKey,
+        listingInfo.account.mint,
+        listingMintAuthority,
+        listingInfo.account.paymentDeposit,
+        listingInfo.account.voteDeposit,
+        inventory,
+        inventoryDelegate,
+      ].map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+    );
+    otherAccounts.push(
+      ...(await walletPurchasesAccounts(args.program, listingInfo, args.signer)),
+      ...(await listingSplitsAccounts(args.program, listingInfo)),
+      // DLC can be bought in a bundle with the listing it requires
+      ...(listingInfo.account.requires &&
+      bundle.listings.some((l) => l.equals(listingInfo.account.requires))
+        ? []
+        : await requirementAccounts(args.program, listingInfo, args.signer))
+    );
+  }
 
-  // The charter this listing is associated with
-  charter: AccountInfo<Charter> | PublicKey;
-}) {
-  if (args.cashierSplit > 1 || args.cashierSplit < 0) {
-    throw new Error("cashierSplit must be between 0.0 and 1.0");
+  // Create the payment account if it's wrapped SOL and doesn't exist yet. The
+  // program makes up any difference from the signer's SOL during the purchase.
+  let payment = await getAssociatedTokenAddress(deposit.mint, args.signer);
+  if (
+    deposit.mint.toString() === splToken.NATIVE_MINT.toString() &&
+    !(await args.program.provider.connection.getAccountInfo(payment))
+  ) {
+    instructions.push(
+      createAssociatedTokenAccountInstruction(
+        args.signer,
+        payment,
+        args.signer,
+        deposit.mint
+      )
+    );
   }
 
-  let instructions = [];
-  const listingMint = Keypair.generate();
-  const charterInfo = await asCharterInfo(args.program, args.charter);
+  let [charterMintAuthority, charterMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, charterInfo.account.mint);
   let charterTreasuryInfo = await asCharterTreasuryInfo(
     args.program,
     charterInfo.publicKey,
-    args.currency
-  );
-
-  const paymentDeposit = await getOrCreateAssociatedTokenAccount({
-    program: args.program,
-    mint: args.currency,
-    signer: args.signer,
-  });
-  instructions.push(...paymentDeposit.instructions);
-  const voteDeposit = await getOrCreateAssociatedTokenAccount({
-    program: args.program,
-    mint: charterInfo.account.mint,
-    signer: args.signer,
-  });
-  instructions.push(...voteDeposit.instructions);
-
-  const [mint_authority, mint_authority_bump] = await pda.mint_authority(
-    args.program.programId,
-    listingMint.publicKey
-  );
-  const [listing_pda, _] = await pda.listing(
-    args.program.programId,
-    listingMint.publicKey
+    deposit.mint
   );
 
   let ix = await args.program.methods
-    .initListing(
-      mint_authority_bump,
-      args.decimals || 0,
-      args.price,
-      args.isRefundable,
-      args.isConsumable,
-      args.isAvailable,
-      args.cashierSplit,
-      args.uri
+    .purchaseBundle(
+      charterMintAuthorityBump,
+      Buffer.from(listingMintAuthorityBumps),
+      Buffer.from(inventoryDelegateBumps),
+      args.quantity,
+      // Fail rather than pay more than the price the buyer was shown
+      bundle.price.mul(args.quantity),
+      activePaymentContribution(charterInfo.account)
     )
     .accounts({
-      listing: listing_pda,
-      mintAuthority: mint_authority,
-      mint: listingMint.publicKey,
-      paymentDeposit: paymentDeposit.account,
-      voteDeposit: voteDeposit.account,
+      bundle: args.bundle,
+      payment: payment,
       charter: charterInfo.publicKey,
       charterTreasury: charterTreasuryInfo.publicKey,
-      user: args.signer,
+      charterTreasuryDeposit: charterTreasuryInfo.account.deposit,
+      charterReserve: charterInfo.account.reserve,
+      charterMint: charterInfo.account.mint,
+      charterMintAuthority: charterMintAuthority,
+      purchaser: args.signer,
     })
-    .signers([listingMint])
+    .remainingAccounts([...itemAccounts, ...otherAccounts])
     .instruction();
 
   instructions.push(ix);
 
   return {
     instructions,
-    signers: [listingMint],
-    listing: listing_pda,
   };
 }
 
-export async function setListingUri(args: {
+export async function setListingRequirement(args: {
   program: any;
   signer: PublicKey;
   listing: AccountInfo<Listing> | PublicKey;
-  uri: string;
+
+  // The listing that purchasers must already own, or null to remove it
+  requires: PublicKey | null;
 }) {
   let instructions = [];
 
   let ix = await args.program.methods
-    .setListingUri(args.uri)
+    .setListingRequirement(args.requires)
     .accounts({
       listing: args.listing,
       authority: args.signer,
@@ -616,16 +761,22 @@ export async function setListingUri(args: {
   };
 }
 
-export async function setListingAvailability(args: {
+export async function setListingSubscription(args: {
   program: any;
   signer: PublicKey;
   listing: AccountInfo<Listing> | PublicKey;
-  isAvailable: boolean;
+
+  // In seconds, for each listing token bought. 0 stops selling the
+  // listing as a subscription.
+  subscriptionPeriod: anchor.BN;
+
+  // In lamports. 0 means renewals cost the listing's price.
+  renewalPrice: anchor.BN;
 }) {
   let instructions = [];
 
   let ix = await args.program.methods
-    .setListingAvailability(args.isAvailable)
+    .setListingSubscription(args.subscriptionPeriod, args.renewalPrice)
     .accounts({
       listing: args.listing,
       authority: args.signer,
@@ -639,231 +790,2060 @@ export async function setListingAvailability(args: {
   };
 }
 
-export async function initCharter(args: {
+// Whether the signer's subscription to a listing is active, by the
+// cluster's clock. Purchasing the listing again renews it.
+export async function isSubscriptionActive(args: {
   program: any;
-  authority: PublicKey;
-  reserve: PublicKey;
-  mint: PublicKey;
   signer: PublicKey;
-  expansion: number;
-  paymentContribution: number;
-  voteContribution: number;
-  withdrawPeriod: anchor.BN;
-  stakeWithdrawAmount: anchor.BN;
-  uri: string;
+  listing: AccountInfo<Listing> | PublicKey;
 }) {
-  const [charter_pda, _] = await pda.charter(args.program.programId, args.mint);
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    args.signer
+  );
+  let [subscription] = await pda.subscription(
+    args.program.programId,
+    inventory
+  );
+  if (!(await args.program.provider.connection.getAccountInfo(subscription))) {
+    return false;
+  }
+
+  try {
+    await args.program.methods
+      .checkSubscription()
+      .accounts({
+        subscription,
+        listing: listingInfo.publicKey,
+        inventory,
+      })
+      .simulate();
+    return true;
+  } catch (e) {
+    if (e.error?.errorCode?.code === "SubscriptionHasExpired") {
+      return false;
+    }
+    throw e;
+  }
+}
+
+// Buys a listing for someone else, who claims it with claimGift. Pass
+// either the recipient's wallet, or the public key of a new keypair whose
+// secret key you send them.
+export async function purchaseGift(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+  quantity: anchor.BN;
+
+  // Any number that hasn't been used for another of the signer's gifts
+  nonce: anchor.BN;
+  recipient?: PublicKey;
+  secretKey?: PublicKey;
 
+  // A unix timestamp. If the gift isn't claimed by then, the signer can
+  // reclaim it with reclaimGift.
+  expiresAt: anchor.BN;
+}) {
   let instructions = [];
-  const ix = await args.program.methods
-    .initCharter(
-      args.expansion,
-      args.paymentContribution,
-      args.voteContribution,
-      args.withdrawPeriod,
-      args.stakeWithdrawAmount,
-      args.uri
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let charterInfo = await asCharterInfo(
+    args.program,
+    listingInfo.account.charter
+  );
+
+  let deposit = await splToken.getAccount(
+    args.program.provider.connection,
+    listingInfo.account.paymentDeposit
+  );
+  let payment = await getAssociatedTokenAddress(deposit.mint, args.signer);
+  const maybeCreateWrappedSolInstructions = await maybeCreateWrappedSolAccount({
+    program: args.program,
+    deposit,
+    payment,
+    listingInfo,
+    signer: args.signer,
+    quantity: args.quantity,
+  });
+  instructions.push(...maybeCreateWrappedSolInstructions);
+
+  let charterTreasuryInfo = await asCharterTreasuryInfo(
+    args.program,
+    charterInfo.publicKey,
+    deposit.mint
+  );
+  let [charterMintAuthority, charterMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, charterInfo.account.mint);
+  let [gift] = await pda.gift(args.program.programId, args.signer, args.nonce);
+
+  let ix = await args.program.methods
+    .purchaseGift(
+      charterMintAuthorityBump,
+      args.nonce,
+      args.recipient || null,
+      args.secretKey || null,
+      args.expiresAt,
+      args.quantity,
+      // Fail rather than pay more than the price the buyer was shown
+      listingInfo.account.price.mul(args.quantity),
+      activePaymentContribution(charterInfo.account)
     )
     .accounts({
-      charter: charter_pda,
-      mint: args.mint,
-      authority: args.authority,
-      reserve: args.reserve,
-      user: args.signer,
-      systemProgram: SystemProgram.programId,
+      gift,
+      payment,
+      listingsPaymentDeposit: listingInfo.account.paymentDeposit,
+      listingsVoteDeposit: listingInfo.account.voteDeposit,
+      listing: listingInfo.publicKey,
+      charter: charterInfo.publicKey,
+      charterTreasury: charterTreasuryInfo.publicKey,
+      charterTreasuryDeposit: charterTreasuryInfo.account.deposit,
+      charterReserve: charterInfo.account.reserve,
+      charterMint: charterInfo.account.mint,
+      charterMintAuthority,
+      purchaser: args.signer,
     })
+    .remainingAccounts([
+      ...(await walletPurchasesAccounts(args.program, listingInfo, args.signer)),
+      ...(await listingSplitsAccounts(args.program, listingInfo)),
+    ])
     .instruction();
+
   instructions.push(ix);
 
   return {
     instructions,
-    charter: charter_pda,
+    gift,
   };
 }
 
-export async function initCashier(args: {
+// Mints a gift into the signer's inventory, creating it if it doesn't
+// exist. Pass the secret keypair if the gift was locked with one; it has
+// to sign the transaction too.
+export async function claimGift(args: {
   program: any;
-  uri: string;
-  charter: AccountInfo<Charter> | PublicKey;
-  authority: PublicKey;
+  signer: PublicKey;
+  gift: PublicKey;
+  secret?: anchor.web3.Keypair;
 }) {
-  const charterInfo = await asCharterInfo(args.program, args.charter);
-  const stake = Keypair.generate();
-  const [cashier_pda, cashier_bump] = await pda.cashier(
-    args.program.programId,
-    stake.publicKey
-  );
-  const [stakeAuthority, stake_authority_bump] = await pda.token_authority(
+  let instructions = [];
+  let gift = await args.program.account.gift.fetch(args.gift);
+  let listingInfo = await asListingInfo(args.program, gift.listing);
+
+  // claimGift creates the inventory if the signer doesn't have one
+  let inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    args.signer
+  );
+  let [inventoryDelegate, inventoryDelegateBump] = await pda.token_authority(
     args.program.programId,
-    stake.publicKey
+    inventory
   );
+  let [listingMintAuthority, listingMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, listingInfo.account.mint);
 
-  let instructions = [];
   let ix = await args.program.methods
-    .initCashier(stake_authority_bump, args.uri)
+    .claimGift(listingMintAuthorityBump, inventoryDelegateBump)
     .accounts({
-      cashier: cashier_pda,
-      stake: stake.publicKey,
-      stakeAuthority,
-      charter: charterInfo.publicKey,
-      charterMint: charterInfo.account.mint,
-      authority: args.authority,
-      clock: SYSVAR_CLOCK_PUBKEY,
+      gift: args.gift,
+      purchaser: gift.purchaser,
+      listing: listingInfo.publicKey,
+      listingMint: listingInfo.account.mint,
+      listingMintAuthority,
+      inventory,
+      inventoryDelegate,
+      recipient: args.signer,
     })
-    .signers([stake])
+    .remainingAccounts([
+      ...(await requirementAccounts(args.program, listingInfo, args.signer)),
+      ...(args.secret
+        ? [
+            {
+              pubkey: args.secret.publicKey,
+              isWritable: false,
+              isSigner: true,
+            },
+          ]
+        : []),
+    ])
     .instruction();
+
   instructions.push(ix);
 
   return {
     instructions,
-    signers: [stake],
+    signers: args.secret ? [args.secret] : [],
   };
 }
 
-export async function initCashierTreasury(args: {
+// Mints an expired, unclaimed gift into the signer's own inventory.
+export async function reclaimGift(args: {
   program: any;
-  charter: AccountInfo<Charter> | PublicKey;
-  cashier: AccountInfo<Cashier> | PublicKey;
-  mint: PublicKey;
-  deposit: PublicKey;
+  signer: PublicKey;
+  gift: PublicKey;
 }) {
-  const cashierInfo = await asCashierInfo(args.program, args.cashier);
-  const charterInfo = await asCharterInfo(args.program, args.charter);
-  const charterTreasuryInfo = await asCharterTreasuryInfo(
-    args.program,
-    charterInfo.publicKey,
-    args.mint
-  );
+  let instructions = [];
+  let gift = await args.program.account.gift.fetch(args.gift);
+  let listingInfo = await asListingInfo(args.program, gift.listing);
 
-  const escrow = Keypair.generate();
-  const [escrow_authority, bump] = await pda.token_authority(
-    args.program.programId,
-    escrow.publicKey
+  let inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    args.signer
   );
-  const [cashier_treasury_pda, _] = await pda.treasury(
+  if (!(await args.program.provider.connection.getAccountInfo(inventory))) {
+    instructions.push(
+      createAssociatedTokenAccountInstruction(
+        args.signer,
+        inventory,
+        args.signer,
+        listingInfo.account.mint
+      )
+    );
+  }
+  let [inventoryDelegate, inventoryDelegateBump] = await pda.token_authority(
     args.program.programId,
-    cashierInfo.publicKey,
-    args.mint
+    inventory
   );
+  let [listingMintAuthority, listingMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, listingInfo.account.mint);
+
+  let ix = await args.program.methods
+    .reclaimGift(listingMintAuthorityBump, inventoryDelegateBump)
+    .accounts({
+      gift: args.gift,
+      listing: listingInfo.publicKey,
+      listingMint: listingInfo.account.mint,
+      listingMintAuthority,
+      inventory,
+      inventoryDelegate,
+      purchaser: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingResale(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+  isResellable: boolean;
 
+  // In basis points of what's left of a resale after the charter's
+  // contribution. 2_500 is 25%.
+  resaleRoyalty: anchor.BN;
+}) {
   let instructions = [];
+
   let ix = await args.program.methods
-    .initCashierTreasury(bump)
+    .setListingResale(args.isResellable, args.resaleRoyalty)
     .accounts({
-      cashierTreasury: cashier_treasury_pda,
-      cashier: cashierInfo.publicKey,
-      charterTreasury: charterTreasuryInfo.publicKey,
-      charter: charterTreasuryInfo.publicKey,
-      deposit: args.deposit,
-      escrow: escrow.publicKey,
-      escrowAuthority: escrow_authority,
-      mint: args.mint,
-      clock: SYSVAR_CLOCK_PUBKEY,
-      authority: args.program.provider.wallet.publicKey,
+      listing: args.listing,
+      authority: args.signer,
     })
-    .signers([escrow])
     .instruction();
 
   instructions.push(ix);
 
   return {
     instructions,
-    signers: [escrow],
   };
 }
 
-export async function initCharterTreasury(args: {
+// Offers listing tokens from the signer's inventory for resale, paid
+// into the signer's associated token account of the payment mint.
+export async function initResaleOffer(args: {
   program: any;
-  charter: AccountInfo<Charter> | PublicKey;
-  mint: PublicKey;
-  deposit: PublicKey;
-  scalar: number;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // In lamports, for 1 listing token
+  price: anchor.BN;
+  quantity: anchor.BN;
 }) {
-  if (args.scalar < 0) {
-    throw new Error("scalar must be greater than 0");
+  let instructions = [];
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    args.signer
+  );
+  let deposit = await splToken.getAccount(
+    args.program.provider.connection,
+    listingInfo.account.paymentDeposit
+  );
+  let sellerDeposit = await getAssociatedTokenAddress(
+    deposit.mint,
+    args.signer
+  );
+  if (
+    !(await args.program.provider.connection.getAccountInfo(sellerDeposit))
+  ) {
+    instructions.push(
+      createAssociatedTokenAccountInstruction(
+        args.signer,
+        sellerDeposit,
+        args.signer,
+        deposit.mint
+      )
+    );
   }
-  const charterInfo = await asCharterInfo(args.program, args.charter);
-
-  const [treasury_pda, _] = await pda.treasury(
+  let [resaleOffer] = await pda.resale_offer(
     args.program.programId,
-    charterInfo.publicKey,
-    args.mint
+    inventory
   );
 
   let ix = await args.program.methods
-    .initCharterTreasury(args.scalar)
+    .initResaleOffer(args.price, args.quantity)
     .accounts({
-      treasury: treasury_pda,
-      mint: args.mint,
-      deposit: args.deposit,
-      charter: charterInfo.publicKey,
+      resaleOffer,
+      listing: listingInfo.publicKey,
+      inventory,
+      deposit: sellerDeposit,
+      seller: args.signer,
     })
     .instruction();
 
-  let instructions = [ix];
+  instructions.push(ix);
 
   return {
     instructions,
-    treasury: treasury_pda,
   };
 }
 
-export async function setCharterTreasuryScalar(args: {
+export async function cancelResaleOffer(args: {
   program: any;
   signer: PublicKey;
-  charter: AccountInfo<Charter> | PublicKey;
-  mint: PublicKey;
-  scalar: number;
+  resaleOffer: PublicKey;
 }) {
-  if (args.scalar < 0) {
-    throw new Error("scalar must be greater than 0");
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .cancelResaleOffer()
+    .accounts({
+      resaleOffer: args.resaleOffer,
+      seller: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function purchaseResale(args: {
+  program: any;
+  signer: PublicKey;
+  resaleOffer: PublicKey;
+  quantity: anchor.BN;
+}) {
+  let instructions = [];
+  let offer = await args.program.account.resaleOffer.fetch(args.resaleOffer);
+  let listingInfo = await asListingInfo(args.program, offer.listing);
+  let charterInfo = await asCharterInfo(
+    args.program,
+    listingInfo.account.charter
+  );
+
+  // Create an inventory if it doesn't exist
+  let inventory = await getAssociatedTokenAddress(
+    listingInfo.account.mint,
+    args.signer
+  );
+  if (!(await args.program.provider.connection.getAccountInfo(inventory))) {
+    instructions.push(
+      createAssociatedTokenAccountInstruction(
+        args.signer,
+        inventory,
+        args.signer,
+        listingInfo.account.mint
+      )
+    );
   }
-  const charterInfo = await asCharterInfo(args.program, args.charter);
 
-  const [treasury_pda, _] = await pda.treasury(
-    args.program.programId,
+  let deposit = await splToken.getAccount(
+    args.program.provider.connection,
+    listingInfo.account.paymentDeposit
+  );
+  let payment = await getAssociatedTokenAddress(deposit.mint, args.signer);
+  let charterTreasuryInfo = await asCharterTreasuryInfo(
+    args.program,
     charterInfo.publicKey,
-    args.mint
+    deposit.mint
+  );
+
+  let [inventoryDelegate, inventoryDelegateBump] = await pda.token_authority(
+    args.program.programId,
+    inventory
   );
+  let [sellerInventoryDelegate, sellerInventoryDelegateBump] =
+    await pda.token_authority(args.program.programId, offer.inventory);
+  let [listingMintAuthority, listingMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, listingInfo.account.mint);
+  let [charterMintAuthority, charterMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, charterInfo.account.mint);
 
   let ix = await args.program.methods
-    .setCharterTreasuryScalar(args.scalar)
+    .purchaseResale(
+      listingMintAuthorityBump,
+      charterMintAuthorityBump,
+      sellerInventoryDelegateBump,
+      inventoryDelegateBump,
+      args.quantity,
+      // Fail rather than pay more than the price the buyer was shown
+      offer.price.mul(args.quantity),
+      activePaymentContribution(charterInfo.account)
+    )
     .accounts({
-      treasury: treasury_pda,
+      resaleOffer: args.resaleOffer,
+      seller: offer.seller,
+      sellerInventory: offer.inventory,
+      sellerInventoryDelegate,
+      sellerDeposit: offer.deposit,
+      payment,
+      inventory,
+      inventoryDelegate,
+      listingsPaymentDeposit: listingInfo.account.paymentDeposit,
+      listingsVoteDeposit: listingInfo.account.voteDeposit,
+      listing: listingInfo.publicKey,
+      listingMint: listingInfo.account.mint,
+      listingMintAuthority,
       charter: charterInfo.publicKey,
-      authority: args.program.provider.wallet.publicKey,
+      charterTreasury: charterTreasuryInfo.publicKey,
+      charterTreasuryDeposit: charterTreasuryInfo.account.deposit,
+      charterReserve: charterInfo.account.reserve,
+      charterMint: charterInfo.account.mint,
+      charterMintAuthority,
+      purchaser: args.signer,
     })
+    .remainingAccounts([
+      ...(await listingSplitsAccounts(args.program, listingInfo)),
+      ...(await requirementAccounts(args.program, listingInfo, args.signer)),
+    ])
     .instruction();
 
-  let instructions = [ix];
+  instructions.push(ix);
 
   return {
     instructions,
-    treasury: treasury_pda,
   };
 }
 
-export async function setCharterReserve(args: {
+// Renews the signer's subscription to a listing automatically, from
+// their associated token account of the listing's payment mint, for up
+// to "allowance" in total. Anyone can then crank renewSubscription.
+export async function enableAutoRenew(args: {
   program: any;
   signer: PublicKey;
-  charter: AccountInfo<Charter> | PublicKey;
-  reserve: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // In lamports. The payment account only has one delegate, so this is
+  // shared by every subscription that renews from it.
+  allowance: anchor.BN;
 }) {
-  const charterInfo = await asCharterInfo(args.program, args.charter);
+  let instructions = [];
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let { subscription, payment, autoRenewal, autoRenewalPayment } =
+    await autoRenewalAccounts(args.program, listingInfo, args.signer);
+  let [paymentDelegate, paymentDelegateBump] = await pda.token_authority(
+    args.program.programId,
+    payment
+  );
 
   let ix = await args.program.methods
-    .setCharterReserve()
+    .enableAutoRenew(paymentDelegateBump, args.allowance)
     .accounts({
-      charter: charterInfo.publicKey,
-      authority: args.program.provider.wallet.publicKey,
-      reserve: args.reserve,
+      autoRenewal,
+      subscription,
+      payment,
+      paymentDelegate,
+      autoRenewalPayment,
+      purchaser: args.signer,
     })
     .instruction();
 
-  let instructions = [ix];
+  instructions.push(ix);
 
   return {
     instructions,
-    charter: charterInfo.publicKey,
+  };
+}
+
+// Stops the signer's subscription to a listing from renewing. The
+// payment account's delegate is revoked once no subscription renews from it.
+export async function cancelAutoRenew(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+}) {
+  let instructions = [];
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let { subscription, payment, autoRenewal, autoRenewalPayment } =
+    await autoRenewalAccounts(args.program, listingInfo, args.signer);
+
+  let ix = await args.program.methods
+    .cancelAutoRenew()
+    .accounts({
+      autoRenewal,
+      autoRenewalPayment,
+      subscription,
+      payment,
+      purchaser: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+// Renews an expired subscription that has auto-renew enabled. The signer
+// only pays the transaction fee.
+//
+// If the listing can't be renewed, or the renewal can't be paid for, this
+// doesn't charge anything, and auto-renew stays on so it can be tried again.
+export async function renewSubscription(args: {
+  program: any;
+  signer: PublicKey;
+  subscription: PublicKey;
+}) {
+  let instructions = [];
+  let subscription = await args.program.account.subscription.fetch(
+    args.subscription
+  );
+  let listingInfo = await asListingInfo(args.program, subscription.listing);
+  let charterInfo = await asCharterInfo(
+    args.program,
+    listingInfo.account.charter
+  );
+  let [autoRenewal] = await pda.auto_renewal(
+    args.program.programId,
+    args.subscription
+  );
+  let autoRenewalAccount = await args.program.account.autoRenewal.fetch(
+    autoRenewal
+  );
+  let deposit = await splToken.getAccount(
+    args.program.provider.connection,
+    listingInfo.account.paymentDeposit
+  );
+  let charterTreasuryInfo = await asCharterTreasuryInfo(
+    args.program,
+    charterInfo.publicKey,
+    deposit.mint
+  );
+  let [paymentDelegate, paymentDelegateBump] = await pda.token_authority(
+    args.program.programId,
+    autoRenewalAccount.payment
+  );
+  let [charterMintAuthority, charterMintAuthorityBump] =
+    await pda.mint_authority(args.program.programId, charterInfo.account.mint);
+
+  let ix = await args.program.methods
+    .renewSubscription(charterMintAuthorityBump, paymentDelegateBump)
+    .accounts({
+      subscription: args.subscription,
+      autoRenewal,
+      payment: autoRenewalAccount.payment,
+      paymentDelegate,
+      listingsPaymentDeposit: listingInfo.account.paymentDeposit,
+      listingsVoteDeposit: listingInfo.account.voteDeposit,
+      listing: listingInfo.publicKey,
+      charter: charterInfo.publicKey,
+      charterTreasury: charterTreasuryInfo.publicKey,
+      charterTreasuryDeposit: charterTreasuryInfo.account.deposit,
+      charterReserve: charterInfo.account.reserve,
+      charterMint: charterInfo.account.mint,
+      charterMintAuthority,
+    })
+    .remainingAccounts(
+      await listingSplitsAccounts(args.program, listingInfo)
+    )
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function initBundle(args: {
+  program: any;
+  signer: PublicKey;
+
+  // Any number that hasn't been used for another of the signer's bundles
+  nonce: anchor.BN;
+
+  // The signer must be the authority of at least one of the listings. The
+  // authorities of the rest must also sign the transaction.
+  listings: PublicKey[];
+
+  // How the price is split between the listings, one weight per listing.
+  // Defaults to the listings' current prices.
+  weights?: anchor.BN[];
+
+  // In lamports, for 1 of each listing
+  price: anchor.BN;
+  uri: string;
+}) {
+  let instructions = [];
+  let listingInfos = await Promise.all(
+    args.listings.map((l) => asListingInfo(args.program, l))
+  );
+  let weights = args.weights || listingInfos.map((l) => l.account.price);
+  let [bundle] = await pda.bundle(
+    args.program.programId,
+    args.signer,
+    args.nonce
+  );
+
+  // The other listings' authorities sign after the listings
+  let cosigners = listingInfos
+    .map((l) => l.account.authority)
+    .filter((a) => !a.equals(args.signer))
+    .map((pubkey) => ({ pubkey, isWritable: false, isSigner: true }));
+
+  let ix = await args.program.methods
+    .initBundle(args.nonce, args.listings, weights, args.price, args.uri)
+    .accounts({
+      bundle,
+      charter: listingInfos[0].account.charter,
+      authority: args.signer,
+      systemProgram: SystemProgram.programId,
+    })
+    .remainingAccounts([
+      ...args.listings.map((pubkey) => ({
+        pubkey,
+        isWritable: false,
+        isSigner: false,
+      })),
+      ...cosigners,
+    ])
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+    bundle,
+  };
+}
+
+export async function setBundleAvailability(args: {
+  program: any;
+  signer: PublicKey;
+  bundle: PublicKey;
+  isAvailable: boolean;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setBundleAvailability(args.isAvailable)
+    .accounts({
+      bundle: args.bundle,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function initListing(args: {
+  program: any;
+  signer: PublicKey;
+
+  // In lamports
+  price: anchor.BN;
+
+  // The decimals on the underlying mint.
+  decimals?: number;
+
+  // Example: "ipfs://my-cid"
+  uri: string;
+
+  // Can the lister burn tokens?
+  isConsumable: boolean;
+
+  // Can the purchaser refund?
+  isRefundable: boolean;
+
+  // Can this listing be purchased?
+  isAvailable: boolean;
+
+  // The percentage of the sale that goes to the marketplace (cashier)
+  // that caused the sale, in basis points. A value of 4000 means 40%
+  // goes to the cashier. Must be between 0 and 10000.
+  //
+  // To "opt out" of cashier splits, set this to 0.
+  cashierSplit: number;
+
+  // How long, in seconds, a purchaser has to refund a trial. After
+  // that, anyone can finish the trial and pay the lister.
+  //
+  // Defaults to 0, which means trials can't be started.
+  refundWindow?: number;
+
+  // The most tokens that can ever be sold, and the most a single
+  // wallet can buy. Both default to 0, which means there's no limit.
+  maxSupply?: anchor.BN;
+  maxPerWallet?: anchor.BN;
+
+  // the mint to be paid in.
+  currency: PublicKey;
+
+  // The charter this listing is associated with
+  charter: AccountInfo<Charter> | PublicKey;
+}) {
+  if (
+    !Number.isInteger(args.cashierSplit) ||
+    args.cashierSplit > 10_000 ||
+    args.cashierSplit < 0
+  ) {
+    throw new Error("cashierSplit must be between 0 and 10000 basis points");
+  }
+  const refundWindow = args.refundWindow || 0;
+  if (!Number.isInteger(refundWindow) || refundWindow < 0) {
+    throw new Error("refundWindow must be a non-negative number of seconds");
+  }
+
+  let instructions = [];
+  const listingMint = Keypair.generate();
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+  let charterTreasuryInfo = await asCharterTreasuryInfo(
+    args.program,
+    charterInfo.publicKey,
+    args.currency
+  );
+
+  const paymentDeposit = await getOrCreateAssociatedTokenAccount({
+    program: args.program,
+    mint: args.currency,
+    signer: args.signer,
+  });
+  instructions.push(...paymentDeposit.instructions);
+  const voteDeposit = await getOrCreateAssociatedTokenAccount({
+    program: args.program,
+    mint: charterInfo.account.mint,
+    signer: args.signer,
+  });
+  instructions.push(...voteDeposit.instructions);
+
+  const [mint_authority, mint_authority_bump] = await pda.mint_authority(
+    args.program.programId,
+    listingMint.publicKey
+  );
+  const [listing_pda, _] = await pda.listing(
+    args.program.programId,
+    listingMint.publicKey
+  );
+
+  let ix = await args.program.methods
+    .initListing(
+      mint_authority_bump,
+      args.decimals || 0,
+      args.price,
+      args.isRefundable,
+      args.isConsumable,
+      args.isAvailable,
+      new anchor.BN(args.cashierSplit),
+      new anchor.BN(refundWindow),
+      args.maxSupply || new anchor.BN(0),
+      args.maxPerWallet || new anchor.BN(0),
+      args.uri
+    )
+    .accounts({
+      listing: listing_pda,
+      mintAuthority: mint_authority,
+      mint: listingMint.publicKey,
+      paymentDeposit: paymentDeposit.account,
+      voteDeposit: voteDeposit.account,
+      charter: charterInfo.publicKey,
+      charterTreasury: charterTreasuryInfo.publicKey,
+      user: args.signer,
+    })
+    .signers([listingMint])
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+    signers: [listingMint],
+    listing: listing_pda,
+  };
+}
+
+export async function setListingUri(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+  uri: string;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setListingUri(args.uri)
+    .accounts({
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingAvailability(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+  isAvailable: boolean;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setListingAvailability(args.isAvailable)
+    .accounts({
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+// Closes a listing, and returns its rent to the signer. The listing can't
+// have open trials or gifts; settle those first. Its splits, if it has
+// any, are closed with it.
+//
+// Coupons of the listing can't be closed afterwards, so close those first.
+export async function closeListing(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+}) {
+  const listingInfo = await asListingInfo(args.program, args.listing);
+  let instructions = [];
+
+  let remainingAccounts = [];
+  if (listingInfo.account.hasSplits) {
+    let [listingSplits] = await pda.listing_splits(
+      args.program.programId,
+      listingInfo.publicKey
+    );
+    remainingAccounts.push({
+      pubkey: listingSplits,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
+  let ix = await args.program.methods
+    .closeListing()
+    .accounts({
+      listing: listingInfo.publicKey,
+      authority: args.signer,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingRefundWindow(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // In seconds
+  refundWindow: number;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setListingRefundWindow(new anchor.BN(args.refundWindow))
+    .accounts({
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingSale(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // The price during the sale, in lamports
+  salePrice: anchor.BN;
+
+  // Unix timestamps. The sale runs from startsAt until just before endsAt.
+  // Set everything to 0 to cancel a sale.
+  startsAt: anchor.BN;
+  endsAt: anchor.BN;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setListingSale(args.salePrice, args.startsAt, args.endsAt)
+    .accounts({
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingSupplyLimits(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // 0 removes the limit
+  maxSupply: anchor.BN;
+  maxPerWallet: anchor.BN;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .setListingSupplyLimits(args.maxSupply, args.maxPerWallet)
+    .accounts({
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function setListingSplits(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // Token accounts in the listing's payment mint, and their shares of
+  // the lister's revenue in basis points. The shares must add up to 10_000.
+  recipients: { deposit: PublicKey; share: anchor.BN }[];
+}) {
+  let instructions = [];
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let [listingSplits] = await pda.listing_splits(
+    args.program.programId,
+    listingInfo.publicKey
+  );
+
+  let accounts = {
+    listingSplits,
+    listing: listingInfo.publicKey,
+    paymentDeposit: listingInfo.account.paymentDeposit,
+    authority: args.signer,
+  };
+  let remainingAccounts = args.recipients.map((r) => ({
+    pubkey: r.deposit,
+    isWritable: false,
+    isSigner: false,
+  }));
+
+  // The first time a listing's revenue is split, its ListingSplits is created
+  let ix = listingInfo.account.hasSplits
+    ? await args.program.methods
+        .setListingSplits(args.recipients)
+        .accounts(accounts)
+        .remainingAccounts(remainingAccounts)
+        .instruction()
+    : await args.program.methods
+        .initListingSplits(args.recipients)
+        .accounts({ ...accounts, systemProgram: SystemProgram.programId })
+        .remainingAccounts(remainingAccounts)
+        .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function closeListingSplits(args: {
+  program: any;
+  signer: PublicKey;
+  listing: PublicKey;
+}) {
+  let instructions = [];
+  let [listingSplits] = await pda.listing_splits(
+    args.program.programId,
+    args.listing
+  );
+
+  let ix = await args.program.methods
+    .closeListingSplits()
+    .accounts({
+      listingSplits,
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function initCoupon(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+
+  // Any number that hasn't been used for another coupon on this listing
+  nonce: anchor.BN;
+
+  // Set exactly one of these. The amount is in lamports off the price of
+  // 1 listing token, and the rate is in basis points off that price.
+  discountAmount: anchor.BN;
+  discountRate: anchor.BN;
+
+  // Each listing token bought with the coupon is one use.
+  // 0 means unlimited uses, or no expiry
+  maxUses: anchor.BN;
+  expiresAt: anchor.BN;
+
+  // If set, only this wallet can redeem the coupon
+  purchaser: PublicKey | null;
+}) {
+  let instructions = [];
+  let listingInfo = await asListingInfo(args.program, args.listing);
+  let [coupon] = await pda.coupon(
+    args.program.programId,
+    listingInfo.publicKey,
+    args.nonce
+  );
+
+  let ix = await args.program.methods
+    .initCoupon(
+      args.nonce,
+      args.discountAmount,
+      args.discountRate,
+      args.maxUses,
+      args.expiresAt,
+      args.purchaser
+    )
+    .accounts({
+      coupon,
+      listing: listingInfo.publicKey,
+      authority: args.signer,
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+    coupon,
+  };
+}
+
+export async function closeCoupon(args: {
+  program: any;
+  signer: PublicKey;
+  listing: PublicKey;
+  coupon: PublicKey;
+}) {
+  let instructions = [];
+
+  let ix = await args.program.methods
+    .closeCoupon()
+    .accounts({
+      coupon: args.coupon,
+      listing: args.listing,
+      authority: args.signer,
+    })
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+  };
+}
+
+export async function initCharter(args: {
+  program: any;
+  authority: PublicKey;
+  reserve: PublicKey;
+  mint: PublicKey;
+  signer: PublicKey;
+  // All rates are in basis points, so 10000 is 1.0 (or 100%)
+  expansion: number;
+  paymentContribution: number;
+  voteContribution: number;
+  withdrawPeriod: anchor.BN;
+  stakeWithdrawAmount: anchor.BN;
+  uri: string;
+}) {
+  const [charter_pda, _] = await pda.charter(args.program.programId, args.mint);
+
+  let instructions = [];
+  const ix = await args.program.methods
+    .initCharter(
+      new anchor.BN(args.expansion),
+      new anchor.BN(args.paymentContribution),
+      new anchor.BN(args.voteContribution),
+      args.withdrawPeriod,
+      args.stakeWithdrawAmount,
+      args.uri
+    )
+    .accounts({
+      charter: charter_pda,
+      mint: args.mint,
+      authority: args.authority,
+      reserve: args.reserve,
+      user: args.signer,
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+  instructions.push(ix);
+
+  return {
+    instructions,
+    charter: charter_pda,
+  };
+}
+
+export async function initCashier(args: {
+  program: any;
+  uri: string;
+  charter: AccountInfo<Charter> | PublicKey;
+  authority: PublicKey;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+  const stake = Keypair.generate();
+  const [cashier_pda, cashier_bump] = await pda.cashier(
+    args.program.programId,
+    stake.publicKey
+  );
+  const [stakeAuthority, stake_authority_bump] = await pda.token_authority(
+    args.program.programId,
+    stake.publicKey
+  );
+
+  let instructions = [];
+  let ix = await args.program.methods
+    .initCashier(stake_authority_bump, args.uri)
+    .accounts({
+      cashier: cashier_pda,
+      stake: stake.publicKey,
+      stakeAuthority,
+      charter: charterInfo.publicKey,
+      charterMint: charterInfo.account.mint,
+      authority: args.authority,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .signers([stake])
+    .instruction();
+  instructions.push(ix);
+
+  return {
+    instructions,
+    signers: [stake],
+  };
+}
+
+export async function initCashierTreasury(args: {
+  program: any;
+  charter: AccountInfo<Charter> | PublicKey;
+  cashier: AccountInfo<Cashier> | PublicKey;
+  mint: PublicKey;
+  deposit: PublicKey;
+}) {
+  const cashierInfo = await asCashierInfo(args.program, args.cashier);
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+  const charterTreasuryInfo = await asCharterTreasuryInfo(
+    args.program,
+    charterInfo.publicKey,
+    args.mint
+  );
+
+  const escrow = Keypair.generate();
+  const [escrow_authority, bump] = await pda.token_authority(
+    args.program.programId,
+    escrow.publicKey
+  );
+  const [cashier_treasury_pda, _] = await pda.treasury(
+    args.program.programId,
+    cashierInfo.publicKey,
+    args.mint
+  );
+
+  let instructions = [];
+  let ix = await args.program.methods
+    .initCashierTreasury(bump)
+    .accounts({
+      cashierTreasury: cashier_treasury_pda,
+      cashier: cashierInfo.publicKey,
+      charterTreasury: charterTreasuryInfo.publicKey,
+      charter: charterTreasuryInfo.publicKey,
+      deposit: args.deposit,
+      escrow: escrow.publicKey,
+      escrowAuthority: escrow_authority,
+      mint: args.mint,
+      clock: SYSVAR_CLOCK_PUBKEY,
+      authority: args.program.provider.wallet.publicKey,
+    })
+    .signers([escrow])
+    .instruction();
+
+  instructions.push(ix);
+
+  return {
+    instructions,
+    signers: [escrow],
+  };
+}
+
+export async function initCharterTreasury(args: {
+  program: any;
+  charter: AccountInfo<Charter> | PublicKey;
+  mint: PublicKey;
+  deposit: PublicKey;
+
+  // In basis points, so 10000 is 1.0
+  scalar: number;
+}) {
+  if (args.scalar < 0) {
+    throw new Error("scalar must be greater than 0");
+  }
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  const [treasury_pda, _] = await pda.treasury(
+    args.program.programId,
+    charterInfo.publicKey,
+    args.mint
+  );
+
+  let ix = await args.program.methods
+    .initCharterTreasury(new anchor.BN(args.scalar))
+    .accounts({
+      treasury: treasury_pda,
+      mint: args.mint,
+      deposit: args.deposit,
+      charter: charterInfo.publicKey,
+    })
+    .instruction();
+
+  let instructions = [ix];
+
+  return {
+    instructions,
+    treasury: treasury_pda,
+  };
+}
+
+export async function setCharterTreasuryScalar(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  mint: PublicKey;
+  scalar: number;
+}) {
+  if (args.scalar < 0) {
+    throw new Error("scalar must be greater than 0");
+  }
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  const [treasury_pda, _] = await pda.treasury(
+    args.program.programId,
+    charterInfo.publicKey,
+    args.mint
+  );
+
+  let ix = await args.program.methods
+    .setCharterTreasuryScalar(new anchor.BN(args.scalar))
+    .accounts({
+      treasury: treasury_pda,
+      charter: charterInfo.publicKey,
+      authority: args.program.provider.wallet.publicKey,
+    })
+    .instruction();
+
+  let instructions = [ix];
+
+  return {
+    instructions,
+    treasury: treasury_pda,
+  };
+}
+
+// Moves the host where off-chain services for the charter are found.
+export async function setCharterUri(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  uri: string;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .setCharterUri(args.uri)
+    .accounts({
+      charter: charterInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  let instructions = [ix];
+
+  return {
+    instructions,
+    charter: charterInfo.publicKey,
+  };
+}
+
+// Changes how cashiers' stake unlocks: stakeWithdrawAmount
+// every withdrawPeriod epochs.
+export async function setCharterWithdrawSchedule(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  withdrawPeriod?: anchor.BN;
+  stakeWithdrawAmount?: anchor.BN;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+  const accounts = {
+    charter: charterInfo.publicKey,
+    authority: args.signer,
+  };
+
+  let instructions = [];
+  if (args.withdrawPeriod) {
+    instructions.push(
+      await args.program.methods
+        .setCharterWithdrawPeriod(args.withdrawPeriod)
+        .accounts(accounts)
+        .instruction()
+    );
+  }
+  if (args.stakeWithdrawAmount) {
+    instructions.push(
+      await args.program.methods
+        .setCharterStakeWithdrawAmount(args.stakeWithdrawAmount)
+        .accounts(accounts)
+        .instruction()
+    );
+  }
+
+  return {
+    instructions,
+    charter: charterInfo.publicKey,
+  };
+}
+
+export async function setCashierUri(args: {
+  program: any;
+  signer: PublicKey;
+  cashier: AccountInfo<Cashier> | PublicKey;
+  uri: string;
+}) {
+  const cashierInfo = await asCashierInfo(args.program, args.cashier);
+
+  let ix = await args.program.methods
+    .setCashierUri(args.uri)
+    .accounts({
+      cashier: cashierInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  let instructions = [ix];
+
+  return {
+    instructions,
+    cashier: cashierInfo.publicKey,
+  };
+}
+
+// Proposes handing the charter to newAuthority, which
+// has to sign acceptCharterAuthority to take it.
+export async function proposeCharterAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  newAuthority: PublicKey;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .proposeCharterAuthority()
+    .accounts({
+      charter: charterInfo.publicKey,
+      newAuthority: args.newAuthority,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    charter: charterInfo.publicKey,
+  };
+}
+
+export async function acceptCharterAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .acceptCharterAuthority()
+    .accounts({
+      charter: charterInfo.publicKey,
+      newAuthority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    charter: charterInfo.publicKey,
+  };
+}
+
+export async function cancelCharterAuthorityTransfer(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .cancelCharterAuthorityTransfer()
+    .accounts({
+      charter: charterInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    charter: charterInfo.publicKey,
+  };
+}
+
+// Proposes handing the cashier to newAuthority, which
+// has to sign acceptCashierAuthority to take it.
+export async function proposeCashierAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  cashier: AccountInfo<Cashier> | PublicKey;
+  newAuthority: PublicKey;
+}) {
+  const cashierInfo = await asCashierInfo(args.program, args.cashier);
+
+  let ix = await args.program.methods
+    .proposeCashierAuthority()
+    .accounts({
+      cashier: cashierInfo.publicKey,
+      newAuthority: args.newAuthority,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    cashier: cashierInfo.publicKey,
+  };
+}
+
+export async function acceptCashierAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  cashier: AccountInfo<Cashier> | PublicKey;
+}) {
+  const cashierInfo = await asCashierInfo(args.program, args.cashier);
+
+  let ix = await args.program.methods
+    .acceptCashierAuthority()
+    .accounts({
+      cashier: cashierInfo.publicKey,
+      newAuthority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    cashier: cashierInfo.publicKey,
+  };
+}
+
+export async function cancelCashierAuthorityTransfer(args: {
+  program: any;
+  signer: PublicKey;
+  cashier: AccountInfo<Cashier> | PublicKey;
+}) {
+  const cashierInfo = await asCashierInfo(args.program, args.cashier);
+
+  let ix = await args.program.methods
+    .cancelCashierAuthorityTransfer()
+    .accounts({
+      cashier: cashierInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    cashier: cashierInfo.publicKey,
+  };
+}
+
+// Proposes handing the listing to newAuthority, which
+// has to sign acceptListingAuthority to take it.
+export async function proposeListingAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+  newAuthority: PublicKey;
+}) {
+  const listingInfo = await asListingInfo(args.program, args.listing);
+
+  let ix = await args.program.methods
+    .proposeListingAuthority()
+    .accounts({
+      listing: listingInfo.publicKey,
+      newAuthority: args.newAuthority,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    listing: listingInfo.publicKey,
+  };
+}
+
+export async function acceptListingAuthority(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+}) {
+  const listingInfo = await asListingInfo(args.program, args.listing);
+
+  let ix = await args.program.methods
+    .acceptListingAuthority()
+    .accounts({
+      listing: listingInfo.publicKey,
+      newAuthority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    listing: listingInfo.publicKey,
+  };
+}
+
+export async function cancelListingAuthorityTransfer(args: {
+  program: any;
+  signer: PublicKey;
+  listing: AccountInfo<Listing> | PublicKey;
+}) {
+  const listingInfo = await asListingInfo(args.program, args.listing);
+
+  let ix = await args.program.methods
+    .cancelListingAuthorityTransfer()
+    .accounts({
+      listing: listingInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    listing: listingInfo.publicKey,
+  };
+}
+
+// Lowers the most the charter can ever charge, in basis points.
+// Caps can't be raised again.
+export async function setCharterContributionCaps(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  maxPaymentContribution: anchor.BN;
+  maxVoteContribution: anchor.BN;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .setCharterContributionCaps(
+      args.maxPaymentContribution,
+      args.maxVoteContribution
+    )
+    .accounts({
+      charter: charterInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    charter: charterInfo.publicKey,
+  };
+}
+
+// Rate changes wait this many seconds before they take effect.
+// Lowering the delay waits for the current delay, too.
+export async function setCharterChangeDelay(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  changeDelay: anchor.BN;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .setCharterChangeDelay(args.changeDelay)
+    .accounts({
+      charter: charterInfo.publicKey,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    charter: charterInfo.publicKey,
+  };
+}
+
+export async function setCharterReserve(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  reserve: PublicKey;
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+
+  let ix = await args.program.methods
+    .setCharterReserve()
+    .accounts({
+      charter: charterInfo.publicKey,
+      authority: args.program.provider.wallet.publicKey,
+      reserve: args.reserve,
+    })
+    .instruction();
+
+  let instructions = [ix];
+
+  return {
+    instructions,
+    charter: charterInfo.publicKey,
+  };
+}
+
+// Hands the charter to a native governance. From then on, the charter
+// only changes through proposals that pass a vote of its token holders.
+// The signer has to own the charter's reserve, which goes to the
+// governance too.
+//
+// Votes lock charter tokens until they're withdrawn, rather than being
+// weighted by a snapshot of balances when the proposal was made.
+export async function initGovernance(args: {
+  program: any;
+  signer: PublicKey;
+  charter: PublicKey;
+  quorum: anchor.BN;
+
+  // In basis points of the votes cast
+  threshold: anchor.BN;
+
+  // In seconds
+  votingPeriod: anchor.BN;
+}) {
+  const [governance] = await pda.governance(
+    args.program.programId,
+    args.charter
+  );
+  const charter = await args.program.account.charter.fetch(args.charter);
+
+  let ix = await args.program.methods
+    .initGovernance(args.quorum, args.threshold, args.votingPeriod)
+    .accounts({
+      governance,
+      charter: args.charter,
+      reserve: charter.reserve,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    governance,
+  };
+}
+
+// The action is an anchor enum, like
+// { spendReserve: { destination, amount } }
+export async function initProposal(args: {
+  program: any;
+  signer: PublicKey;
+  governance: PublicKey;
+  action: any;
+}) {
+  const governance = await args.program.account.governance.fetch(
+    args.governance
+  );
+  const [proposal] = await pda.proposal(
+    args.program.programId,
+    args.governance,
+    governance.proposalCount
+  );
+
+  let ix = await args.program.methods
+    .initProposal(args.action)
+    .accounts({
+      proposal,
+      governance: args.governance,
+      proposer: args.signer,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    proposal,
+  };
+}
+
+// Locks amount of the signer's charter tokens as a vote until
+// it's withdrawn with withdrawVote.
+export async function castVote(args: {
+  program: any;
+  signer: PublicKey;
+  proposal: PublicKey;
+  amount: anchor.BN;
+  approve: boolean;
+}) {
+  const proposal = await args.program.account.proposal.fetch(args.proposal);
+  const governance = await args.program.account.governance.fetch(
+    proposal.governance
+  );
+  const charterInfo = await asCharterInfo(args.program, governance.charter);
+  const [vote] = await pda.vote(
+    args.program.programId,
+    args.proposal,
+    args.signer
+  );
+
+  const escrow = Keypair.generate();
+  const [escrowAuthority, escrowAuthorityBump] = await pda.token_authority(
+    args.program.programId,
+    escrow.publicKey
+  );
+  const source = await getAssociatedTokenAddress(
+    charterInfo.account.mint,
+    args.signer
+  );
+
+  let ix = await args.program.methods
+    .castVote(escrowAuthorityBump, args.amount, args.approve)
+    .accounts({
+      vote,
+      proposal: args.proposal,
+      governance: proposal.governance,
+      charter: charterInfo.publicKey,
+      mint: charterInfo.account.mint,
+      source,
+      escrow: escrow.publicKey,
+      escrowAuthority,
+      voter: args.signer,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    signers: [escrow],
+    vote,
+  };
+}
+
+// Returns a vote's tokens. Before voting ends, this also retracts the vote.
+export async function withdrawVote(args: {
+  program: any;
+  signer: PublicKey;
+  proposal: PublicKey;
+}) {
+  const [vote] = await pda.vote(
+    args.program.programId,
+    args.proposal,
+    args.signer
+  );
+  const voteRecord = await args.program.account.voteRecord.fetch(vote);
+  const escrow = await splToken.getAccount(
+    args.program.provider.connection,
+    voteRecord.escrow
+  );
+  const [escrowAuthority, escrowAuthorityBump] = await pda.token_authority(
+    args.program.programId,
+    voteRecord.escrow
+  );
+  const destination = await getAssociatedTokenAddress(
+    escrow.mint,
+    args.signer
+  );
+
+  let ix = await args.program.methods
+    .withdrawVote(escrowAuthorityBump)
+    .accounts({
+      vote,
+      proposal: args.proposal,
+      escrow: voteRecord.escrow,
+      escrowAuthority,
+      destination,
+      voter: args.signer,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+  };
+}
+
+// Carries out a proposal that passed. Anyone can run this once voting ends.
+export async function executeProposal(args: {
+  program: any;
+
+  // Pays the rent of any account the proposal creates
+  signer: PublicKey;
+  proposal: PublicKey;
+}) {
+  const proposal = await args.program.account.proposal.fetch(args.proposal);
+  const governance = await args.program.account.governance.fetch(
+    proposal.governance
+  );
+  const charterInfo = await asCharterInfo(args.program, governance.charter);
+  const [_, governanceBump] = await pda.governance(
+    args.program.programId,
+    charterInfo.publicKey
+  );
+
+  // The accounts the action changes
+  let remainingAccounts = [];
+  if (proposal.action.setListingSuspension) {
+    remainingAccounts.push({
+      pubkey: proposal.action.setListingSuspension.listing,
+      isWritable: true,
+      isSigner: false,
+    });
+  }
+  if (proposal.action.spendReserve) {
+    remainingAccounts.push(
+      {
+        pubkey: charterInfo.account.reserve,
+        isWritable: true,
+        isSigner: false,
+      },
+      {
+        pubkey: proposal.action.spendReserve.destination,
+        isWritable: true,
+        isSigner: false,
+      }
+    );
+  }
+
+  if (proposal.action.initGrant) {
+    const { treasury, nonce } = proposal.action.initGrant;
+    const treasuryAccount = await args.program.account.charterTreasury.fetch(
+      treasury
+    );
+    const [grant] = await pda.grant(
+      args.program.programId,
+      charterInfo.publicKey,
+      nonce
+    );
+    remainingAccounts.push(
+      { pubkey: treasury, isWritable: false, isSigner: false },
+      { pubkey: treasuryAccount.deposit, isWritable: false, isSigner: false },
+      { pubkey: grant, isWritable: true, isSigner: false }
+    );
+  }
+  if (proposal.action.cancelGrant) {
+    const { grant } = proposal.action.cancelGrant;
+    const grantAccount = await args.program.account.grant.fetch(grant);
+    remainingAccounts.push(
+      { pubkey: grant, isWritable: true, isSigner: false },
+      { pubkey: grantAccount.payer, isWritable: true, isSigner: false }
+    );
+  }
+
+  let ix = await args.program.methods
+    .executeProposal(governanceBump)
+    .accounts({
+      proposal: args.proposal,
+      governance: proposal.governance,
+      charter: charterInfo.publicKey,
+      payer: args.signer,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return {
+    instructions: [ix],
+  };
+}
+
+// Grants amount of a charter treasury's mint to recipient, optionally
+// vesting linearly between two unix timestamps. The treasury's deposit
+// has to be owned by pda.grant_authority(treasury).
+export async function initGrant(args: {
+  program: any;
+  signer: PublicKey;
+  charter: AccountInfo<Charter> | PublicKey;
+  mint: PublicKey;
+  recipient: PublicKey;
+  amount: anchor.BN;
+
+  // Any number that hasn't been used for another of the charter's grants
+  nonce: anchor.BN;
+  vesting?: { startsAt: anchor.BN; endsAt: anchor.BN };
+}) {
+  const charterInfo = await asCharterInfo(args.program, args.charter);
+  const treasuryInfo = await asCharterTreasuryInfo(
+    args.program,
+    charterInfo.publicKey,
+    args.mint
+  );
+  const [grant] = await pda.grant(
+    args.program.programId,
+    charterInfo.publicKey,
+    args.nonce
+  );
+  const [grantAuthority, grantAuthorityBump] = await pda.grant_authority(
+    args.program.programId,
+    treasuryInfo.publicKey
+  );
+
+  let ix = await args.program.methods
+    .initGrant(
+      grantAuthorityBump,
+      args.nonce,
+      args.amount,
+      args.vesting || null
+    )
+    .accounts({
+      grant,
+      charter: charterInfo.publicKey,
+      treasury: treasuryInfo.publicKey,
+      deposit: treasuryInfo.account.deposit,
+      grantAuthority,
+      recipient: args.recipient,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+    grant,
+  };
+}
+
+// Pays what's vested to the recipient's associated token account.
+// Anyone can run this. Once the grant is fully claimed, it closes.
+export async function claimGrant(args: { program: any; grant: PublicKey }) {
+  const grant = await args.program.account.grant.fetch(args.grant);
+  const treasury = await args.program.account.charterTreasury.fetch(
+    grant.treasury
+  );
+  const [grantAuthority, grantAuthorityBump] = await pda.grant_authority(
+    args.program.programId,
+    grant.treasury
+  );
+  const destination = await getAssociatedTokenAddress(
+    grant.mint,
+    grant.recipient
+  );
+
+  let ix = await args.program.methods
+    .claimGrant(grantAuthorityBump)
+    .accounts({
+      grant: args.grant,
+      payer: grant.payer,
+      treasury: grant.treasury,
+      deposit: treasury.deposit,
+      grantAuthority,
+      destination,
+      clock: SYSVAR_CLOCK_PUBKEY,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
+  };
+}
+
+// Stops a grant from paying out more than what's vested; for a grant
+// without a vesting schedule, more than what's been claimed. What's
+// vested can still be claimed.
+export async function cancelGrant(args: {
+  program: any;
+  signer: PublicKey;
+  grant: PublicKey;
+}) {
+  const grant = await args.program.account.grant.fetch(args.grant);
+
+  let ix = await args.program.methods
+    .cancelGrant()
+    .accounts({
+      grant: args.grant,
+      payer: grant.payer,
+      charter: grant.charter,
+      authority: args.signer,
+    })
+    .instruction();
+
+  return {
+    instructions: [ix],
   };
 }
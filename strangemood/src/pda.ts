@@ -29,6 +29,127 @@ export const pda = {
     );
   },
 
+  purchase_receipt: async (
+    strangemoodProgramId: PublicKey,
+    listing: PublicKey,
+    purchaser: PublicKey,
+    nonce: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("purchase_receipt"),
+        listing.toBuffer(),
+        purchaser.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  wallet_purchases: async (
+    strangemoodProgramId: PublicKey,
+    listing: PublicKey,
+    purchaser: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("wallet_purchases"),
+        listing.toBuffer(),
+        purchaser.toBuffer(),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  listing_splits: async (strangemoodProgramId: PublicKey, listing: PublicKey) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("listing_splits"), listing.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  bundle: async (
+    strangemoodProgramId: PublicKey,
+    authority: PublicKey,
+    nonce: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("bundle"),
+        authority.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  subscription: async (strangemoodProgramId: PublicKey, inventory: PublicKey) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("subscription"), inventory.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  gift: async (
+    strangemoodProgramId: PublicKey,
+    purchaser: PublicKey,
+    nonce: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("gift"),
+        purchaser.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  resale_offer: async (
+    strangemoodProgramId: PublicKey,
+    inventory: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("resale_offer"), inventory.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  auto_renewal: async (
+    strangemoodProgramId: PublicKey,
+    subscription: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("auto_renewal"), subscription.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  auto_renewal_payment: async (
+    strangemoodProgramId: PublicKey,
+    payment: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("auto_renewal_payment"), payment.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  coupon: async (
+    strangemoodProgramId: PublicKey,
+    listing: PublicKey,
+    nonce: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("coupon"),
+        listing.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
   listing: async (strangemoodProgramId: PublicKey, mint: PublicKey) => {
     return web3.PublicKey.findProgramAddress(
       [Buffer.from("listing"), mint.toBuffer()],
@@ -43,6 +164,64 @@ export const pda = {
     );
   },
 
+  grant: async (
+    strangemoodProgramId: PublicKey,
+    charter: PublicKey,
+    nonce: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("grant"),
+        charter.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  grant_authority: async (
+    strangemoodProgramId: PublicKey,
+    treasury: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("grant_authority"), treasury.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  governance: async (strangemoodProgramId: PublicKey, charter: PublicKey) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("governance"), charter.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
+  proposal: async (
+    strangemoodProgramId: PublicKey,
+    governance: PublicKey,
+    index: anchor.BN
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("proposal"),
+        governance.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      strangemoodProgramId
+    );
+  },
+
+  vote: async (
+    strangemoodProgramId: PublicKey,
+    proposal: PublicKey,
+    voter: PublicKey
+  ) => {
+    return web3.PublicKey.findProgramAddress(
+      [Buffer.from("vote"), proposal.toBuffer(), voter.toBuffer()],
+      strangemoodProgramId
+    );
+  },
+
   cashier: async (strangemoodProgramId: PublicKey, stake: PublicKey) => {
     return web3.PublicKey.findProgramAddress(
       [Buffer.from("cashier"), stake.toBuffer()],
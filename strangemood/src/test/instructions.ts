@@ -15,9 +15,9 @@ const { SystemProgram, Keypair, SYSVAR_CLOCK_PUBKEY, Transaction } =
 
 export async function initCharter(
   program: Program<Strangemood>,
-  expansionRate: number,
-  paymentContribution: number,
-  voteContribution: number,
+  expansionRate: anchor.BN,
+  paymentContribution: anchor.BN,
+  voteContribution: anchor.BN,
   withdrawPeriod: anchor.BN,
   stakeWithdrawAmount: anchor.BN,
   uri: string
@@ -80,7 +80,7 @@ export async function createCharterTreasury(
   const deposit = await createTokenAccount(program, mint);
 
   await program.methods
-    .initCharterTreasury(scalar.toNumber())
+    .initCharterTreasury(scalar)
     .accounts({
       treasury: treasury_pda,
       mint: mint,
@@ -150,8 +150,11 @@ export async function initListing(
   isRefundable = true,
   isConsumable = false,
   isAvailable = true,
-  cashierSplit = 0.1,
-  uri = "ipfs://cid"
+  cashierSplit = 1_000,
+  uri = "ipfs://cid",
+  refundWindow = 60 * 60,
+  maxSupply = 0,
+  maxPerWallet = 0
 ) {
   const listingMint = Keypair.generate();
 
@@ -175,7 +178,10 @@ export async function initListing(
       isRefundable,
       isConsumable,
       isAvailable,
-      cashierSplit,
+      new anchor.BN(cashierSplit),
+      new anchor.BN(refundWindow),
+      new anchor.BN(maxSupply),
+      new anchor.BN(maxPerWallet),
       uri
     )
     .accounts({
@@ -240,15 +246,108 @@ export async function initCashier(
   };
 }
 
+// If the listing requires another listing, that listing and the
+// purchaser's inventories of it are passed after the other accounts.
+async function requirementAccounts(
+  program: Program<Strangemood>,
+  listing: { account: any; publicKey: PublicKey }
+) {
+  if (!listing.account.requires) {
+    return [];
+  }
+  const parent = await program.account.listing.fetch(listing.account.requires);
+  const inventories =
+    await program.provider.connection.getTokenAccountsByOwner(
+      program.provider.wallet.publicKey,
+      { mint: parent.mint }
+    );
+  return [listing.account.requires, ...inventories.value.map((a) => a.pubkey)].map(
+    (pubkey) => ({ pubkey, isWritable: false, isSigner: false })
+  );
+}
+
 export async function purchase(
   program: Program<Strangemood>,
   charter: { account: any; publicKey: PublicKey },
   charterTreasury: { account: any; publicKey: PublicKey },
   listing: { account: any; publicKey: PublicKey },
   payment: PublicKey,
-  quantity: number
+  quantity: number,
+  receiptNonce: anchor.BN | null = null,
+  coupon: PublicKey | null = null,
+  existingInventory: { publicKey: PublicKey } | null = null
 ) {
-  const inventory = await createTokenAccount(program, listing.account.mint);
+  const inventory =
+    existingInventory ||
+    (await createTokenAccount(program, listing.account.mint));
+
+  // If we want a receipt, it's passed after the other accounts
+  let receipt: PublicKey | null = null;
+  let remainingAccounts = [];
+  if (receiptNonce) {
+    [receipt] = await pda.purchase_receipt(
+      program.programId,
+      listing.publicKey,
+      program.provider.wallet.publicKey,
+      receiptNonce
+    );
+    remainingAccounts.push({
+      pubkey: receipt,
+      isWritable: true,
+      isSigner: false,
+    });
+  }
+
+  // If the listing limits purchases per wallet, the purchaser's count is passed too
+  if (!listing.account.maxPerWallet.isZero()) {
+    const [walletPurchases] = await pda.wallet_purchases(
+      program.programId,
+      listing.publicKey,
+      program.provider.wallet.publicKey
+    );
+    remainingAccounts.push({
+      pubkey: walletPurchases,
+      isWritable: true,
+      isSigner: false,
+    });
+  }
+
+  // If the listing splits its revenue, the splits and every recipient are passed too
+  if (listing.account.hasSplits) {
+    const [listingSplits] = await pda.listing_splits(
+      program.programId,
+      listing.publicKey
+    );
+    const splits = await program.account.listingSplits.fetch(listingSplits);
+    remainingAccounts.push(
+      ...[listingSplits, ...splits.recipients.map((r) => r.deposit)].map(
+        (pubkey) => ({ pubkey, isWritable: true, isSigner: false })
+      )
+    );
+  }
+
+  remainingAccounts.push(...(await requirementAccounts(program, listing)));
+
+  // If the listing is a subscription, the inventory's subscription is passed too
+  if (!listing.account.subscriptionPeriod.isZero()) {
+    const [subscription] = await pda.subscription(
+      program.programId,
+      inventory.publicKey
+    );
+    remainingAccounts.push({
+      pubkey: subscription,
+      isWritable: true,
+      isSigner: false,
+    });
+  }
+
+  if (coupon) {
+    remainingAccounts.push({
+      pubkey: coupon,
+      isWritable: true,
+      isSigner: false,
+    });
+  }
 
   const [listing_mint_authority, listing_mint_authority_bump] =
     await pda.mint_authority(program.programId, listing.account.mint);
@@ -265,7 +364,11 @@ export async function purchase(
       listing_mint_authority_bump,
       charter_mint_authority_bump,
       inventory_delegate_bump,
-      new anchor.BN(quantity)
+      new anchor.BN(quantity),
+      listing.account.price.mul(new anchor.BN(quantity)),
+      null,
+      receiptNonce,
+      coupon
     )
     .accounts({
       payment: payment,
@@ -284,10 +387,94 @@ export async function purchase(
       charterMintAuthority: charter_mint_authority,
       purchaser: program.provider.wallet.publicKey,
     })
+    .remainingAccounts(remainingAccounts)
     .rpc();
 
   return {
     inventory,
+    receipt,
+  };
+}
+
+export async function startTrial(
+  program: Program<Strangemood>,
+  listing: { account: any; publicKey: PublicKey },
+  paymentMint: PublicKey,
+  payment: PublicKey,
+  quantity: number,
+  cashier: PublicKey | null = null
+) {
+  const inventory = await createTokenAccount(program, listing.account.mint);
+  const escrow = Keypair.generate();
+
+  const [listing_mint_authority, listing_mint_authority_bump] =
+    await pda.mint_authority(program.programId, listing.account.mint);
+  const [escrow_authority, escrow_authority_bump] = await pda.token_authority(
+    program.programId,
+    escrow.publicKey
+  );
+  const [inventory_delegate, inventory_delegate_bump] =
+    await pda.token_authority(program.programId, inventory.publicKey);
+  const [receipt] = await pda.receipt(program.programId, escrow.publicKey);
+  const [walletPurchases] = await pda.wallet_purchases(
+    program.programId,
+    listing.publicKey,
+    program.provider.wallet.publicKey
+  );
+  const remainingAccounts = [
+    ...(listing.account.maxPerWallet.isZero()
+      ? []
+      : [{ pubkey: walletPurchases, isWritable: true, isSigner: false }]),
+    ...(await requirementAccounts(program, listing)),
+  ];
+
+  const accounts = {
+    payment: payment,
+    listing: listing.publicKey,
+    listingPaymentDeposit: listing.account.paymentDeposit,
+    listingPaymentDepositMint: paymentMint,
+    listingMint: listing.account.mint,
+    listingMintAuthority: listing_mint_authority,
+    inventory: inventory.publicKey,
+    inventoryDelegate: inventory_delegate,
+    receipt: receipt,
+    escrow: escrow.publicKey,
+    escrowAuthority: escrow_authority,
+    purchaser: program.provider.wallet.publicKey,
+  };
+
+  if (cashier) {
+    await program.methods
+      .startTrialWithCashier(
+        listing_mint_authority_bump,
+        escrow_authority_bump,
+        inventory_delegate_bump,
+        new anchor.BN(quantity),
+        listing.account.price.mul(new anchor.BN(quantity))
+      )
+      .accounts({ ...accounts, cashier })
+      .remainingAccounts(remainingAccounts)
+      .signers([escrow])
+      .rpc();
+  } else {
+    await program.methods
+      .startTrial(
+        listing_mint_authority_bump,
+        escrow_authority_bump,
+        inventory_delegate_bump,
+        new anchor.BN(quantity),
+        listing.account.price.mul(new anchor.BN(quantity))
+      )
+      .accounts(accounts)
+      .remainingAccounts(remainingAccounts)
+      .signers([escrow])
+      .rpc();
+  }
+
+  return {
+    inventory,
+    receipt,
+    escrow: escrow.publicKey,
   };
 }
 
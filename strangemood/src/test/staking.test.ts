@@ -47,9 +47,9 @@ describe("Staking Cashiers", () => {
     );
     await program.methods
       .initCharter(
-        10,
-        0.01,
-        0.2,
+        new anchor.BN(100_000),
+        new anchor.BN(100),
+        new anchor.BN(2_000),
         new anchor.BN(10),
         new anchor.BN(100),
         "ipfs://charter"
@@ -3,7 +3,11 @@ import * as splToken from "@solana/spl-token";
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
 import { Strangemood } from "../../target/types/strangemood";
-import { createMint, createTokenAccount } from "./utils";
+import {
+  createAssociatedTokenAccountForKeypair,
+  createMint,
+  createTokenAccount,
+} from "./utils";
 import { pda } from "../pda";
 import {
   initCharter,
@@ -12,6 +16,7 @@ import {
   initListing,
   mintTo,
   purchase,
+  startTrial,
   createCashierTreasury,
 } from "./instructions";
 
@@ -35,9 +40,9 @@ describe("Strangemood", () => {
 
     await program.methods
       .initCharter(
-        10,
-        0.01,
-        0.2,
+        new anchor.BN(100_000),
+        new anchor.BN(100),
+        new anchor.BN(2_000),
         new anchor.BN(1),
         new anchor.BN(1),
         "https://strangemood.org"
@@ -59,20 +64,520 @@ describe("Strangemood", () => {
     );
     assert.equal(charter.mint.toString(), mint.publicKey.toString());
     assert.equal(charter.reserve.toString(), reserve.publicKey.toString());
-    assert.equal(charter.expansionRate, 10);
-    assert.equal(charter.paymentContribution, 0.01);
-    assert.equal(charter.voteContribution, 0.2);
+    assert.equal(charter.expansionRate.toNumber(), 100_000);
+    assert.equal(charter.paymentContribution.toNumber(), 100);
+    assert.equal(charter.voteContribution.toNumber(), 2_000);
     assert.equal(charter.uri, "https://strangemood.org");
     assert.equal(charter.withdrawPeriod.toNumber(), 1);
     assert.equal(charter.stakeWithdrawAmount.toNumber(), 1);
   });
 
+  it("can update a charter's uri and withdraw schedule", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const accounts = {
+      charter: charter.publicKey,
+      authority: program.provider.wallet.publicKey,
+    };
+
+    await program.methods
+      .setCharterUri("https://api.strangemood.org")
+      .accounts(accounts)
+      .rpc();
+    await program.methods
+      .setCharterWithdrawPeriod(new anchor.BN(10))
+      .accounts(accounts)
+      .rpc();
+    await program.methods
+      .setCharterStakeWithdrawAmount(new anchor.BN(500))
+      .accounts(accounts)
+      .rpc();
+
+    const after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(after.uri, "https://api.strangemood.org");
+    assert.equal(after.withdrawPeriod.toNumber(), 10);
+    assert.equal(after.stakeWithdrawAmount.toNumber(), 500);
+
+    try {
+      await program.methods
+        .setCharterWithdrawPeriod(new anchor.BN(0))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the withdraw period to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "WithdrawPeriodIsInvalid");
+    }
+
+    try {
+      await program.methods
+        .setCharterUri("https://" + "a".repeat(256))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the uri to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "UriIsTooLong");
+    }
+
+    const cashier = await initCashier(program, charter);
+    await program.methods
+      .setCashierUri("ipfs://moved")
+      .accounts({
+        cashier: cashier.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    const cashierAfter = await program.account.cashier.fetch(
+      cashier.publicKey
+    );
+    assert.equal(cashierAfter.uri, "ipfs://moved");
+  });
+
+  it("queues charter rate changes behind the change delay", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const accounts = {
+      charter: charter.publicKey,
+      authority: program.provider.wallet.publicKey,
+    };
+
+    // Without a delay, the new delay applies right away
+    await program.methods
+      .setCharterChangeDelay(new anchor.BN(60 * 60 * 24 * 7))
+      .accounts(accounts)
+      .rpc();
+    let after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(after.changeDelay.toNumber(), 60 * 60 * 24 * 7);
+    assert.equal(after.pendingChange, null);
+
+    // But a fee hike now waits a week
+    await program.methods
+      .setCharterContributionRate(new anchor.BN(500), new anchor.BN(2_000))
+      .accounts(accounts)
+      .rpc();
+    after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(after.paymentContribution.toNumber(), 100);
+    assert.equal(after.pendingChange.paymentContribution.toNumber(), 500);
+    assert.ok(
+      after.pendingChange.effectiveAt.toNumber() >=
+        Date.now() / 1000 + 60 * 60 * 24 * 6
+    );
+
+    // And so does lowering the delay
+    await program.methods
+      .setCharterChangeDelay(new anchor.BN(0))
+      .accounts(accounts)
+      .rpc();
+    after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(after.changeDelay.toNumber(), 60 * 60 * 24 * 7);
+    assert.equal(after.pendingChange.changeDelay.toNumber(), 0);
+  });
+
+  it("hands a charter to a new authority only once it accepts", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const newAuthority = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const propose = () =>
+      program.methods
+        .proposeCharterAuthority()
+        .accounts({
+          charter: charter.publicKey,
+          newAuthority: newAuthority.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+    const accept = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .acceptCharterAuthority()
+        .accounts({
+          charter: charter.publicKey,
+          newAuthority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+
+    await propose();
+    let after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(
+      after.authority.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(
+      after.pendingAuthority.toString(),
+      newAuthority.publicKey.toString()
+    );
+
+    try {
+      await accept(stranger);
+      assert.fail("expected a stranger to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "SignerIsNotPendingAuthority");
+    }
+
+    // The current authority can take the proposal back
+    await program.methods
+      .cancelCharterAuthorityTransfer()
+      .accounts({
+        charter: charter.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    try {
+      await accept(newAuthority);
+      assert.fail("expected the cancelled transfer to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "SignerIsNotPendingAuthority");
+    }
+
+    await propose();
+    await accept(newAuthority);
+    after = await program.account.charter.fetch(charter.publicKey);
+    assert.equal(after.authority.toString(), newAuthority.publicKey.toString());
+    assert.equal(after.pendingAuthority, null);
+
+    // The old authority no longer controls the charter
+    try {
+      await program.methods
+        .setCharterUri("https://example.com")
+        .accounts({
+          charter: charter.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the old authority to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "CharterHasUnexpectedAuthority");
+    }
+  });
+
+  it("hands a listing and a cashier to a new authority only once it accepts", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const cashier = await initCashier(program, charter);
+    const newAuthority = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    // The listing
+    await program.methods
+      .proposeListingAuthority()
+      .accounts({
+        listing: listing.publicKey,
+        newAuthority: newAuthority.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    let listingAfter = await program.account.listing.fetch(listing.publicKey);
+    assert.equal(
+      listingAfter.authority.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(
+      listingAfter.pendingAuthority.toString(),
+      newAuthority.publicKey.toString()
+    );
+
+    const acceptListing = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .acceptListingAuthority()
+        .accounts({
+          listing: listing.publicKey,
+          newAuthority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    try {
+      await acceptListing(stranger);
+      assert.fail("expected a stranger to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "SignerIsNotPendingAuthority");
+    }
+
+    await acceptListing(newAuthority);
+    listingAfter = await program.account.listing.fetch(listing.publicKey);
+    assert.equal(
+      listingAfter.authority.toString(),
+      newAuthority.publicKey.toString()
+    );
+    assert.equal(listingAfter.pendingAuthority, null);
+
+    // The old authority no longer controls the listing
+    try {
+      await program.methods
+        .setListingUri("https://example.com")
+        .accounts({
+          listing: listing.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the old authority to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingHasUnexpectedAuthority");
+    }
+
+    // The cashier
+    await program.methods
+      .proposeCashierAuthority()
+      .accounts({
+        cashier: cashier.publicKey,
+        newAuthority: newAuthority.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    let cashierAfter = await program.account.cashier.fetch(cashier.publicKey);
+    assert.equal(
+      cashierAfter.authority.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(
+      cashierAfter.pendingAuthority.toString(),
+      newAuthority.publicKey.toString()
+    );
+
+    const acceptCashier = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .acceptCashierAuthority()
+        .accounts({
+          cashier: cashier.publicKey,
+          newAuthority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    try {
+      await acceptCashier(stranger);
+      assert.fail("expected a stranger to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "SignerIsNotPendingAuthority");
+    }
+
+    await acceptCashier(newAuthority);
+    cashierAfter = await program.account.cashier.fetch(cashier.publicKey);
+    assert.equal(
+      cashierAfter.authority.toString(),
+      newAuthority.publicKey.toString()
+    );
+    assert.equal(cashierAfter.pendingAuthority, null);
+
+    // The old authority no longer controls the cashier
+    try {
+      await program.methods
+        .setCashierUri("ipfs://moved")
+        .accounts({
+          cashier: cashier.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the old authority to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "CashierHasUnexpectedAuthority");
+    }
+  });
+
+  it("spends the reserve when a governance proposal passes", async () => {
+    // The charter's mint stays with the wallet, so the test can mint votes
+    const mint = await createMint(program);
+    const reserve = await createTokenAccount(program, mint.publicKey);
+    const [charter] = await pda.charter(program.programId, mint.publicKey);
+    await program.methods
+      .initCharter(
+        new anchor.BN(100_000),
+        new anchor.BN(100),
+        new anchor.BN(2_000),
+        new anchor.BN(1),
+        new anchor.BN(1),
+        "https://strangemood.org"
+      )
+      .accounts({
+        charter,
+        mint: mint.publicKey,
+        authority: program.provider.wallet.publicKey,
+        reserve: reserve.publicKey,
+        user: program.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const [governance, governanceBump] = await pda.governance(
+      program.programId,
+      charter
+    );
+    await program.methods
+      .initGovernance(new anchor.BN(10), new anchor.BN(5_000), new anchor.BN(2))
+      .accounts({
+        governance,
+        charter,
+        reserve: reserve.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    const charterAfter = await program.account.charter.fetch(charter);
+    assert.equal(charterAfter.authority.toString(), governance.toString());
+
+    // The governance owns the reserve
+    const reserveAfter = await splToken.getAccount(
+      program.provider.connection,
+      reserve.publicKey
+    );
+    assert.equal(reserveAfter.owner.toString(), governance.toString());
+    await mintTo(program, mint.publicKey, reserve.publicKey, 50);
+
+    const voter = Keypair.generate();
+    const voterTokens = await createAssociatedTokenAccountForKeypair(
+      program,
+      voter,
+      mint.publicKey
+    );
+    await mintTo(program, mint.publicKey, voterTokens, 100);
+
+    const [proposal] = await pda.proposal(
+      program.programId,
+      governance,
+      new anchor.BN(0)
+    );
+    await program.methods
+      .initProposal({
+        spendReserve: { destination: voterTokens, amount: new anchor.BN(50) },
+      })
+      .accounts({
+        proposal,
+        governance,
+        proposer: program.provider.wallet.publicKey,
+        clock: SYSVAR_CLOCK_PUBKEY,
+      })
+      .rpc();
+
+    const escrow = Keypair.generate();
+    const [escrowAuthority, escrowAuthorityBump] = await pda.token_authority(
+      program.programId,
+      escrow.publicKey
+    );
+    const [vote] = await pda.vote(program.programId, proposal, voter.publicKey);
+    await program.methods
+      .castVote(escrowAuthorityBump, new anchor.BN(100), true)
+      .accounts({
+        vote,
+        proposal,
+        governance,
+        charter,
+        mint: mint.publicKey,
+        source: voterTokens,
+        escrow: escrow.publicKey,
+        escrowAuthority,
+        voter: voter.publicKey,
+        clock: SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([voter, escrow])
+      .rpc();
+
+    const execute = () =>
+      program.methods
+        .executeProposal(governanceBump)
+        .accounts({
+          proposal,
+          governance,
+          charter,
+          payer: program.provider.wallet.publicKey,
+          clock: SYSVAR_CLOCK_PUBKEY,
+        })
+        .remainingAccounts([
+          { pubkey: reserve.publicKey, isWritable: true, isSigner: false },
+          { pubkey: voterTokens, isWritable: true, isSigner: false },
+        ])
+        .rpc();
+
+    try {
+      await execute();
+      assert.fail("expected the proposal to still be open");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "VotingHasNotEnded");
+    }
+
+    // Wait for voting to end
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+
+    await execute();
+    let voterAccount = await splToken.getAccount(
+      program.provider.connection,
+      voterTokens
+    );
+    assert.equal(voterAccount.amount, 50);
+
+    try {
+      await execute();
+      assert.fail("expected the proposal to only run once");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ProposalHasBeenExecuted");
+    }
+
+    // The vote's tokens come back once voting is over
+    await program.methods
+      .withdrawVote(escrowAuthorityBump)
+      .accounts({
+        vote,
+        proposal,
+        escrow: escrow.publicKey,
+        escrowAuthority,
+        destination: voterTokens,
+        voter: voter.publicKey,
+        clock: SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([voter])
+      .rpc();
+    voterAccount = await splToken.getAccount(
+      program.provider.connection,
+      voterTokens
+    );
+    assert.equal(voterAccount.amount, 150);
+  });
+
   it("init_charter_treasury", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.01,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -88,7 +593,7 @@ describe("Strangemood", () => {
     );
 
     await program.methods
-      .initCharterTreasury(1.0)
+      .initCharterTreasury(new anchor.BN(10_000))
       .accounts({
         treasury: treasury_pda,
         mint: mint.publicKey,
@@ -101,15 +606,15 @@ describe("Strangemood", () => {
     assert.equal(treasury.charter.toString(), charter.publicKey.toString());
     assert.equal(treasury.deposit.toString(), deposit.publicKey.toString());
     assert.equal(treasury.mint.toString(), mint.publicKey.toString());
-    assert.equal(treasury.scalar, 1.0);
+    assert.equal(treasury.scalar.toNumber(), 10_000);
   });
 
   it("can't create two charter treasuries of the same type", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.01,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -125,7 +630,7 @@ describe("Strangemood", () => {
     );
 
     await program.methods
-      .initCharterTreasury(1.0)
+      .initCharterTreasury(new anchor.BN(10_000))
       .accounts({
         treasury: treasury_pda,
         mint: mint.publicKey,
@@ -138,7 +643,7 @@ describe("Strangemood", () => {
     let errored = false;
     try {
       await program.methods
-        .initCharterTreasury(1.0)
+        .initCharterTreasury(new anchor.BN(10_000))
         .accounts({
           treasury: treasury_pda,
           mint: mint.publicKey,
@@ -152,12 +657,337 @@ describe("Strangemood", () => {
     assert(errored);
   });
 
+  it("rejects out of range charter rates and treasury scalars", async () => {
+    const mint = await createMint(program);
+    const reserve = await createTokenAccount(program, mint.publicKey);
+    const [charter_pda] = await pda.charter(program.programId, mint.publicKey);
+    const initCharterWithRates = (
+      paymentContribution: number,
+      voteContribution: number
+    ) =>
+      program.methods
+        .initCharter(
+          new anchor.BN(100_000),
+          new anchor.BN(paymentContribution),
+          new anchor.BN(voteContribution),
+          new anchor.BN(1),
+          new anchor.BN(1),
+          "https://strangemood.org"
+        )
+        .accounts({
+          charter: charter_pda,
+          mint: mint.publicKey,
+          authority: program.provider.wallet.publicKey,
+          reserve: reserve.publicKey,
+          user: program.provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    // Contributions above 100%
+    try {
+      await initCharterWithRates(10_001, 2_000);
+      assert.fail("expected the payment contribution to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "PaymentContributionIsInvalid");
+    }
+    try {
+      await initCharterWithRates(100, 10_001);
+      assert.fail("expected the vote contribution to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "VoteContributionIsInvalid");
+    }
+
+    await initCharterWithRates(100, 2_000);
+    const accounts = {
+      charter: charter_pda,
+      authority: program.provider.wallet.publicKey,
+    };
+
+    // Contributions above the charter's caps
+    await program.methods
+      .setCharterContributionCaps(new anchor.BN(500), new anchor.BN(3_000))
+      .accounts(accounts)
+      .rpc();
+    try {
+      await program.methods
+        .setCharterContributionRate(new anchor.BN(501), new anchor.BN(2_000))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the payment contribution to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "PaymentContributionIsInvalid");
+    }
+    try {
+      await program.methods
+        .setCharterContributionRate(new anchor.BN(100), new anchor.BN(3_001))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the vote contribution to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "VoteContributionIsInvalid");
+    }
+
+    // Caps can't be raised again
+    try {
+      await program.methods
+        .setCharterContributionCaps(new anchor.BN(600), new anchor.BN(3_000))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the cap to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ContributionCapIsInvalid");
+    }
+
+    // A zero scalar
+    const treasuryMint = await createMint(program);
+    const deposit = await createTokenAccount(program, treasuryMint.publicKey);
+    const [treasury_pda] = await pda.treasury(
+      program.programId,
+      charter_pda,
+      treasuryMint.publicKey
+    );
+    const treasuryAccounts = {
+      treasury: treasury_pda,
+      mint: treasuryMint.publicKey,
+      deposit: deposit.publicKey,
+      charter: charter_pda,
+    };
+    try {
+      await program.methods
+        .initCharterTreasury(new anchor.BN(0))
+        .accounts(treasuryAccounts)
+        .rpc();
+      assert.fail("expected the scalar to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ScalarIsInvalid");
+    }
+
+    await program.methods
+      .initCharterTreasury(new anchor.BN(10_000))
+      .accounts(treasuryAccounts)
+      .rpc();
+    try {
+      await program.methods
+        .setCharterTreasuryScalar(new anchor.BN(0))
+        .accounts({
+          treasury: treasury_pda,
+          charter: charter_pda,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the scalar to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ScalarIsInvalid");
+    }
+  });
+
+  it("pays a grant from a program controlled treasury deposit", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+
+    // The treasury's deposit is owned by its grant authority
+    const mint = await createMint(program);
+    const [treasury] = await pda.treasury(
+      program.programId,
+      charter.publicKey,
+      mint.publicKey
+    );
+    const [grantAuthority, grantAuthorityBump] = await pda.grant_authority(
+      program.programId,
+      treasury
+    );
+    const deposit = await createTokenAccount(program, mint.publicKey);
+    await mintTo(program, mint.publicKey, deposit.publicKey, 1_000);
+    await program.provider.send(
+      new Transaction().add(
+        splToken.createSetAuthorityInstruction(
+          deposit.publicKey,
+          program.provider.wallet.publicKey,
+          splToken.AuthorityType.AccountOwner,
+          grantAuthority
+        )
+      )
+    );
+    await program.methods
+      .initCharterTreasury(new anchor.BN(10_000))
+      .accounts({
+        treasury,
+        mint: mint.publicKey,
+        deposit: deposit.publicKey,
+        charter: charter.publicKey,
+      })
+      .rpc();
+
+    const recipient = Keypair.generate();
+    const destination = await createAssociatedTokenAccountForKeypair(
+      program,
+      recipient,
+      mint.publicKey
+    );
+
+    const nonce = new anchor.BN(0);
+    const [grant] = await pda.grant(
+      program.programId,
+      charter.publicKey,
+      nonce
+    );
+    await program.methods
+      .initGrant(grantAuthorityBump, nonce, new anchor.BN(600), null)
+      .accounts({
+        grant,
+        charter: charter.publicKey,
+        treasury,
+        deposit: deposit.publicKey,
+        grantAuthority,
+        recipient: recipient.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const claim = () =>
+      program.methods
+        .claimGrant(grantAuthorityBump)
+        .accounts({
+          grant,
+          payer: program.provider.wallet.publicKey,
+          treasury,
+          deposit: deposit.publicKey,
+          grantAuthority,
+          destination,
+          clock: SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+
+    await claim();
+    const destinationAccount = await splToken.getAccount(
+      program.provider.connection,
+      destination
+    );
+    assert.equal(destinationAccount.amount, 600);
+
+    // Fully claimed grants close
+    assert.equal(
+      await program.provider.connection.getAccountInfo(grant),
+      null
+    );
+
+    // Cancelling a grant without a vesting schedule revokes it
+    const revokedNonce = new anchor.BN(1);
+    const [revoked] = await pda.grant(
+      program.programId,
+      charter.publicKey,
+      revokedNonce
+    );
+    await program.methods
+      .initGrant(grantAuthorityBump, revokedNonce, new anchor.BN(100), null)
+      .accounts({
+        grant: revoked,
+        charter: charter.publicKey,
+        treasury,
+        deposit: deposit.publicKey,
+        grantAuthority,
+        recipient: recipient.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .cancelGrant()
+      .accounts({
+        grant: revoked,
+        payer: program.provider.wallet.publicKey,
+        charter: charter.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.equal(
+      await program.provider.connection.getAccountInfo(revoked),
+      null
+    );
+
+    // A grant can't pay out of a deposit its treasury's grant authority
+    // doesn't own, like an escrow owned by a token_authority
+    const escrowNonce = new anchor.BN(2);
+    const [escrowGrant] = await pda.grant(
+      program.programId,
+      charter.publicKey,
+      escrowNonce
+    );
+    await program.methods
+      .initGrant(grantAuthorityBump, escrowNonce, new anchor.BN(100), null)
+      .accounts({
+        grant: escrowGrant,
+        charter: charter.publicKey,
+        treasury,
+        deposit: deposit.publicKey,
+        grantAuthority,
+        recipient: recipient.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const escrow = await createTokenAccount(program, mint.publicKey);
+    await mintTo(program, mint.publicKey, escrow.publicKey, 100);
+    const [escrowAuthority] = await pda.token_authority(
+      program.programId,
+      escrow.publicKey
+    );
+    await program.provider.send(
+      new Transaction().add(
+        splToken.createSetAuthorityInstruction(
+          escrow.publicKey,
+          program.provider.wallet.publicKey,
+          splToken.AuthorityType.AccountOwner,
+          escrowAuthority
+        )
+      )
+    );
+    await program.methods
+      .setCharterTreasuryDeposit()
+      .accounts({
+        treasury,
+        charter: charter.publicKey,
+        deposit: escrow.publicKey,
+        mint: mint.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .claimGrant(grantAuthorityBump)
+        .accounts({
+          grant: escrowGrant,
+          payer: program.provider.wallet.publicKey,
+          treasury,
+          deposit: escrow.publicKey,
+          grantAuthority,
+          destination,
+          clock: SYSVAR_CLOCK_PUBKEY,
+        })
+        .rpc();
+      assert.fail("expected the escrow to be rejected");
+    } catch (e) {
+      assert.equal(
+        e.error?.errorCode?.code,
+        "TokenAccountHasUnexpectedOwner"
+      );
+    }
+  });
+
   it("init_listing", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.01,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -169,7 +999,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
 
     const paymentDeposit = await createTokenAccount(
@@ -195,7 +1025,10 @@ describe("Strangemood", () => {
         false,
         false,
         true,
-        0.1,
+        new anchor.BN(1_000),
+        new anchor.BN(60),
+        new anchor.BN(0),
+        new anchor.BN(0),
         "ipfs://somecid"
       )
       .accounts({
@@ -225,152 +1058,1957 @@ describe("Strangemood", () => {
       listing.voteDeposit.toString(),
       voteDeposit.publicKey.toString()
     );
-    assert.equal(listing.mint.toString(), listingMint.publicKey.toString());
-    assert.equal(listing.isRefundable, false);
-    assert.equal(listing.isConsumable, false);
-    assert.equal(listing.isAvailable, true);
-    assert.equal(listing.cashierSplit, 0.1);
-    assert.equal(listing.uri, "ipfs://somecid");
-    assert.equal(listing.price.toNumber(), 10);
+    assert.equal(listing.mint.toString(), listingMint.publicKey.toString());
+    assert.equal(listing.isRefundable, false);
+    assert.equal(listing.isConsumable, false);
+    assert.equal(listing.isAvailable, true);
+    assert.equal(listing.cashierSplit.toNumber(), 1_000);
+    assert.equal(listing.refundWindow.toNumber(), 60);
+    assert.equal(listing.uri, "ipfs://somecid");
+    assert.equal(listing.price.toNumber(), 10);
+  });
+
+  it("init_cashier", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+
+    const stake = Keypair.generate();
+    const [cashier_pda, cashier_bump] = await pda.cashier(
+      program.programId,
+      stake.publicKey
+    );
+    const [stakeAuthority, stake_authority_bump] = await pda.token_authority(
+      program.programId,
+      stake.publicKey
+    );
+
+    await program.methods
+      .initCashier(stake_authority_bump, "ipfs://cashier")
+      .accounts({
+        cashier: cashier_pda,
+        stake: stake.publicKey,
+        stakeAuthority,
+        charter: charter.publicKey,
+        charterMint: charter.account.mint,
+        authority: program.provider.wallet.publicKey,
+        clock: SYSVAR_CLOCK_PUBKEY,
+      })
+      .signers([stake])
+      .rpc();
+
+    const cashier = await program.account.cashier.fetch(cashier_pda);
+    assert.equal(
+      cashier.authority.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(cashier.charter.toString(), charter.publicKey.toString());
+    assert.equal(cashier.stake.toString(), stake.publicKey.toString());
+    assert.equal(cashier.lastWithdrawAt, 0);
+    assert.equal(cashier.uri, "ipfs://cashier");
+  });
+
+  it("init_cashier_treasury", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+
+    assert.equal(charter.account.uri, "https://strangemood.org");
+    if (!charter) {
+      throw new Error("charter not initialized");
+    }
+
+    const cashier = await initCashier(program, charter);
+    const mint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      mint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const deposit = await createTokenAccount(program, mint.publicKey);
+
+    const escrow = Keypair.generate();
+    const [escrow_authority, bump] = await pda.token_authority(
+      program.programId,
+      escrow.publicKey
+    );
+    const [cashier_treasury_pda, _] = await pda.treasury(
+      program.programId,
+      cashier.publicKey,
+      mint.publicKey
+    );
+
+    await program.methods
+      .initCashierTreasury(bump)
+      .accounts({
+        cashierTreasury: cashier_treasury_pda,
+        cashier: cashier.publicKey,
+        charterTreasury: charterTreasury.publicKey,
+        charter: charter.publicKey,
+        deposit: deposit.publicKey,
+        escrow: escrow.publicKey,
+        escrowAuthority: escrow_authority,
+        mint: mint.publicKey,
+        clock: SYSVAR_CLOCK_PUBKEY,
+        authority: program.provider.wallet.publicKey,
+      })
+      .signers([escrow])
+      .rpc();
+
+    const cashierTreasury = await program.account.cashierTreasury.fetch(
+      cashier_treasury_pda
+    );
+
+    assert.equal(
+      cashierTreasury.cashier.toString(),
+      cashier.publicKey.toString(),
+      "cashier does not match"
+    );
+    assert.equal(
+      cashierTreasury.deposit.toString(),
+      deposit.publicKey.toString(),
+      "deposit does not match"
+    );
+    assert.equal(
+      cashierTreasury.escrow.toString(),
+      escrow.publicKey.toString(),
+      "escrow does not match"
+    );
+    assert.equal(
+      cashierTreasury.mint.toString(),
+      mint.publicKey.toString(),
+      "mint does not match"
+    );
+    assert.equal(
+      cashierTreasury.lastWithdrawAt,
+      0,
+      "lastWithdrawAt does not match"
+    );
+  });
+
+  it("can purchase a listing without a cashier", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+
+    const inventory = await createTokenAccount(program, listing.account.mint);
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+
+    const [listing_mint_authority, listing_mint_authority_bump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+
+    const [charter_mint_authority, charter_mint_authority_bump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+
+    const [inventory_delegate, inventory_delegate_bump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+
+    // Mint payment tokens into the payment account
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // Check we have the funds to pay for the listing
+    let before = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(before.amount, 100);
+
+    // purchase the listing
+    await program.methods
+      .purchase(
+        listing_mint_authority_bump,
+        charter_mint_authority_bump,
+        inventory_delegate_bump,
+        new anchor.BN(1),
+        new anchor.BN(10),
+        null,
+        null,
+        null
+      )
+      .accounts({
+        payment: payment.publicKey,
+        inventory: inventory.publicKey,
+        inventoryDelegate: inventory_delegate,
+        listingsPaymentDeposit: listing.account.paymentDeposit,
+        listingsVoteDeposit: listing.account.voteDeposit,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority: listing_mint_authority,
+        charter: charter.publicKey,
+        charterTreasury: charterTreasury.publicKey,
+        charterTreasuryDeposit: charterTreasury.account.deposit,
+        charterReserve: charter.account.reserve,
+        charterMint: charter.account.mint,
+        charterMintAuthority: charter_mint_authority,
+        purchaser: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // check the account was charged
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 90);
+
+    // Check that the inventory has the token
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 1);
+
+    // Check that the listing payment deposit was paid
+    let listingDeposit = await splToken.getAccount(
+      program.provider.connection,
+      listing.account.paymentDeposit
+    );
+    assert.equal(listingDeposit.amount, 6);
+
+    // Check that the charter deposit was paid
+    let charterDeposit = await splToken.getAccount(
+      program.provider.connection,
+      charterTreasury.account.deposit
+    );
+    assert.equal(charterDeposit.amount, 4);
+  });
+
+  it("can purchase a listing and keep a receipt", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    const { inventory, receipt } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      2,
+      new anchor.BN(7)
+    );
+
+    const account = await program.account.purchaseReceipt.fetch(receipt);
+    assert.equal(account.isInitialized, true);
+    assert.equal(account.listing.toString(), listing.publicKey.toString());
+    assert.equal(account.inventory.toString(), inventory.publicKey.toString());
+    assert.equal(
+      account.purchaser.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(account.cashier, null);
+    assert.equal(account.quantity.toNumber(), 2);
+    assert.equal(account.price.toNumber(), 10);
+    assert.equal(account.nonce.toNumber(), 7);
+    assert.ok(account.timestamp.toNumber() > 0);
+
+    // Reusing a nonce would overwrite an existing receipt, so it fails
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        1,
+        new anchor.BN(7)
+      );
+      assert.fail("expected reusing a receipt nonce to fail");
+    } catch (e) {
+      assert.notEqual(e.message, "expected reusing a receipt nonce to fail");
+    }
+  });
+
+  it("can purchase a listing with SOL, without wrapping it first", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      splToken.NATIVE_MINT,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      splToken.NATIVE_MINT,
+      1_000
+    );
+
+    // An empty wrapped SOL account
+    const payment = await createTokenAccount(program, splToken.NATIVE_MINT);
+
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      2
+    );
+
+    // The purchaser's SOL was wrapped and spent
+    let paymentAccount = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(paymentAccount.amount, 0);
+
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 2);
+
+    let listingDeposit = await splToken.getAccount(
+      program.provider.connection,
+      listing.account.paymentDeposit
+    );
+    assert.equal(listingDeposit.amount, 1_980);
+  });
+
+  it("can sell a limited edition", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+
+    // 3 in total, and at most 2 per wallet
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      1,
+      0,
+      true,
+      false,
+      true,
+      1_000,
+      "ipfs://cid",
+      0,
+      3,
+      2
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      2
+    );
+
+    let listingAccount = await program.account.listing.fetch(listing.publicKey);
+    assert.equal(listingAccount.supply.toNumber(), 2);
+
+    const [walletPurchases] = await pda.wallet_purchases(
+      program.programId,
+      listing.publicKey,
+      program.provider.wallet.publicKey
+    );
+    const walletAccount = await program.account.walletPurchases.fetch(
+      walletPurchases
+    );
+    assert.equal(walletAccount.quantity.toNumber(), 2);
+
+    // This wallet has bought all it can
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the purchase to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "WalletLimitExceeded");
+    }
+
+    // Lift the wallet limit, but keep the supply
+    await program.methods
+      .setListingSupplyLimits(new anchor.BN(3), new anchor.BN(0))
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // Only 1 is left
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        2
+      );
+      assert.fail("expected the purchase to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingIsSoldOut");
+    }
+  });
+
+  it("can purchase a listing during a scheduled sale", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // Half off, for the next hour
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .setListingSale(
+        new anchor.BN(5),
+        new anchor.BN(now - 60),
+        new anchor.BN(now + 60 * 60)
+      )
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 95);
+
+    // A sale can't end before it starts
+    try {
+      await program.methods
+        .setListingSale(
+          new anchor.BN(5),
+          new anchor.BN(now + 60),
+          new anchor.BN(now)
+        )
+        .accounts({
+          listing: listing.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the sale to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "SaleScheduleIsInvalid");
+    }
+  });
+
+  it("can purchase a listing with a coupon", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // 30% off, once, for this wallet only
+    const nonce = new anchor.BN(1);
+    const [coupon] = await pda.coupon(
+      program.programId,
+      listing.publicKey,
+      nonce
+    );
+    await program.methods
+      .initCoupon(
+        nonce,
+        new anchor.BN(0),
+        new anchor.BN(3_000),
+        new anchor.BN(1),
+        new anchor.BN(0),
+        program.provider.wallet.publicKey
+      )
+      .accounts({
+        coupon,
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // A single use can't discount more than 1 listing token
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        2,
+        null,
+        coupon
+      );
+      assert.fail("expected the coupon to be used up");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "CouponIsUsedUp");
+    }
+
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1,
+      null,
+      coupon
+    );
+
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 93);
+
+    const couponAccount = await program.account.coupon.fetch(coupon);
+    assert.equal(couponAccount.uses.toNumber(), 1);
+
+    // The coupon only had one use
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        1,
+        null,
+        coupon
+      );
+      assert.fail("expected the coupon to be used up");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "CouponIsUsedUp");
+    }
+  });
+
+  it("can split a listing's revenue between several recipients", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(1_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    let listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      100
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // A studio, its publisher, and a composer
+    const studio = await createTokenAccount(program, paymentMint.publicKey);
+    const publisher = await createTokenAccount(program, paymentMint.publicKey);
+    const composer = await createTokenAccount(program, paymentMint.publicKey);
+    const recipients = [
+      { deposit: studio.publicKey, share: new anchor.BN(5_000) },
+      { deposit: publisher.publicKey, share: new anchor.BN(3_333) },
+      { deposit: composer.publicKey, share: new anchor.BN(1_667) },
+    ];
+    const [listingSplits] = await pda.listing_splits(
+      program.programId,
+      listing.publicKey
+    );
+    const remainingAccounts = recipients.map((r) => ({
+      pubkey: r.deposit,
+      isWritable: false,
+      isSigner: false,
+    }));
+
+    // The shares have to add up to 100%
+    try {
+      await program.methods
+        .initListingSplits(recipients.slice(0, 2))
+        .accounts({
+          listingSplits,
+          listing: listing.publicKey,
+          paymentDeposit: listing.account.paymentDeposit,
+          authority: program.provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(remainingAccounts)
+        .rpc();
+      assert.fail("expected the splits to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingSplitsAreInvalid");
+    }
+
+    await program.methods
+      .initListingSplits(recipients)
+      .accounts({
+        listingSplits,
+        listing: listing.publicKey,
+        paymentDeposit: listing.account.paymentDeposit,
+        authority: program.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    listing = {
+      account: await program.account.listing.fetch(listing.publicKey),
+      publicKey: listing.publicKey,
+    };
+    assert.equal(listing.account.hasSplits, true);
+
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+
+    // The charter takes 10 of the 100, and the lister's 90 is split
+    // 45 / 29 / 16, with the last recipient taking the rounding remainder.
+    const balance = async (account) =>
+      (await splToken.getAccount(program.provider.connection, account)).amount;
+    assert.equal(await balance(studio.publicKey), 45);
+    assert.equal(await balance(publisher.publicKey), 29);
+    assert.equal(await balance(composer.publicKey), 16);
+    assert.equal(await balance(listing.account.paymentDeposit), 0);
+
+    // Closing the listing closes its splits too
+    await program.methods
+      .closeListing()
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .remainingAccounts([
+        { pubkey: listingSplits, isWritable: true, isSigner: false },
+      ])
+      .rpc();
+    assert.equal(
+      await program.provider.connection.getAccountInfo(listingSplits),
+      null
+    );
+  });
+
+  it("can purchase a bundle of listings", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+
+    // A game and its soundtrack
+    const game = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      20
+    );
+    const soundtrack = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    const nonce = new anchor.BN(1);
+    const [bundle] = await pda.bundle(
+      program.programId,
+      program.provider.wallet.publicKey,
+      nonce
+    );
+    await program.methods
+      .initBundle(
+        nonce,
+        [game.publicKey, soundtrack.publicKey],
+        [new anchor.BN(20), new anchor.BN(10)],
+        new anchor.BN(15),
+        "ipfs://bundle"
+      )
+      .accounts({
+        bundle,
+        charter: charter.publicKey,
+        authority: program.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        [game, soundtrack].map((l) => ({
+          pubkey: l.publicKey,
+          isWritable: false,
+          isSigner: false,
+        }))
+      )
+      .rpc();
+
+    // Raising one listing's price doesn't change its part of the bundle
+    await program.methods
+      .setListingPrice(new anchor.BN(1_000))
+      .accounts({
+        listing: game.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const [charter_mint_authority, charter_mint_authority_bump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+
+    let listingMintAuthorityBumps = [];
+    let inventoryDelegateBumps = [];
+    let inventories = [];
+    let remainingAccounts = [];
+    for (const listing of [game, soundtrack]) {
+      const inventory = await createTokenAccount(program, listing.account.mint);
+      const [listing_mint_authority, listing_mint_authority_bump] =
+        await pda.mint_authority(program.programId, listing.account.mint);
+      const [inventory_delegate, inventory_delegate_bump] =
+        await pda.token_authority(program.programId, inventory.publicKey);
+
+      inventories.push(inventory.publicKey);
+      listingMintAuthorityBumps.push(listing_mint_authority_bump);
+      inventoryDelegateBumps.push(inventory_delegate_bump);
+      remainingAccounts.push(
+        ...[
+          listing.publicKey,
+          listing.account.mint,
+          listing_mint_authority,
+          listing.account.paymentDeposit,
+          listing.account.voteDeposit,
+          inventory.publicKey,
+          inventory_delegate,
+        ].map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+      );
+    }
+
+    await program.methods
+      .purchaseBundle(
+        charter_mint_authority_bump,
+        Buffer.from(listingMintAuthorityBumps),
+        Buffer.from(inventoryDelegateBumps),
+        new anchor.BN(1),
+        new anchor.BN(15),
+        null
+      )
+      .accounts({
+        bundle,
+        payment: payment.publicKey,
+        charter: charter.publicKey,
+        charterTreasury: charterTreasury.publicKey,
+        charterTreasuryDeposit: charterTreasury.account.deposit,
+        charterReserve: charter.account.reserve,
+        charterMint: charter.account.mint,
+        charterMintAuthority: charter_mint_authority,
+        purchaser: program.provider.wallet.publicKey,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    const balance = async (account) =>
+      (await splToken.getAccount(program.provider.connection, account)).amount;
+    assert.equal(await balance(payment.publicKey), 85);
+
+    // One of each listing
+    assert.equal(await balance(inventories[0]), 1);
+    assert.equal(await balance(inventories[1]), 1);
+
+    // The 15 is split 10 / 5, in proportion to the bundle's weights,
+    // and then the charter takes its 1% (rounded up) of each part.
+    assert.equal(await balance(game.account.paymentDeposit), 9);
+    assert.equal(await balance(soundtrack.account.paymentDeposit), 4);
+    assert.equal(await balance(charterTreasury.account.deposit), 2);
+  });
+
+  it("can only purchase DLC after purchasing its base game", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const game = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      20
+    );
+    let dlc = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      5
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // A listing can't require itself
+    try {
+      await program.methods
+        .setListingRequirement(dlc.publicKey)
+        .accounts({
+          listing: dlc.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("expected the requirement to be rejected");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingRequirementIsInvalid");
+    }
+
+    await program.methods
+      .setListingRequirement(game.publicKey)
+      .accounts({
+        listing: dlc.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    dlc = {
+      account: await program.account.listing.fetch(dlc.publicKey),
+      publicKey: dlc.publicKey,
+    };
+
+    // Without the game
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        dlc,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the purchase to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "RequiredListingIsNotOwned");
+    }
+
+    // With the game
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      game,
+      payment.publicKey,
+      1
+    );
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      dlc,
+      payment.publicKey,
+      1
+    );
+
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 1);
+  });
+
+  it("can purchase and renew a subscription", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    let listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // 30 days, and renewals are cheaper
+    const period = 30 * 24 * 60 * 60;
+    await program.methods
+      .setListingSubscription(new anchor.BN(period), new anchor.BN(8))
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    listing = {
+      account: await program.account.listing.fetch(listing.publicKey),
+      publicKey: listing.publicKey,
+    };
+
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+    const [subscription] = await pda.subscription(
+      program.programId,
+      inventory.publicKey
+    );
+    const first = await program.account.subscription.fetch(subscription);
+
+    // Active
+    await program.methods
+      .checkSubscription()
+      .accounts({
+        subscription,
+        listing: listing.publicKey,
+        inventory: inventory.publicKey,
+      })
+      .rpc();
+
+    // Renewing early adds another period on top of the first
+    await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1,
+      null,
+      null,
+      inventory
+    );
+    const renewed = await program.account.subscription.fetch(subscription);
+    assert.equal(
+      renewed.expiresAt.toNumber(),
+      first.expiresAt.toNumber() + period
+    );
+
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 82);
+
+    // Subscriptions can't be trialed
+    try {
+      await startTrial(
+        program,
+        listing,
+        paymentMint.publicKey,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the trial to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingIsSubscription");
+    }
+  });
+
+  it("can auto-renew a subscription from a delegated payment account", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    let listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // A one second period, so the subscription expires during the test
+    await program.methods
+      .setListingSubscription(new anchor.BN(1), new anchor.BN(8))
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    listing = {
+      account: await program.account.listing.fetch(listing.publicKey),
+      publicKey: listing.publicKey,
+    };
+
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+    const [subscription] = await pda.subscription(
+      program.programId,
+      inventory.publicKey
+    );
+    const [autoRenewal] = await pda.auto_renewal(
+      program.programId,
+      subscription
+    );
+    const [paymentDelegate, paymentDelegateBump] = await pda.token_authority(
+      program.programId,
+      payment.publicKey
+    );
+    const [autoRenewalPayment] = await pda.auto_renewal_payment(
+      program.programId,
+      payment.publicKey
+    );
+    const [charterMintAuthority, charterMintAuthorityBump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+
+    // Enough for one renewal, but not two
+    await program.methods
+      .enableAutoRenew(paymentDelegateBump, new anchor.BN(10))
+      .accounts({
+        autoRenewal,
+        subscription,
+        payment: payment.publicKey,
+        paymentDelegate,
+        autoRenewalPayment,
+        purchaser: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const renew = () =>
+      program.methods
+        .renewSubscription(charterMintAuthorityBump, paymentDelegateBump)
+        .accounts({
+          subscription,
+          autoRenewal,
+          payment: payment.publicKey,
+          paymentDelegate,
+          listingsPaymentDeposit: listing.account.paymentDeposit,
+          listingsVoteDeposit: listing.account.voteDeposit,
+          listing: listing.publicKey,
+          charter: charter.publicKey,
+          charterTreasury: charterTreasury.publicKey,
+          charterTreasuryDeposit: charterTreasury.account.deposit,
+          charterReserve: charter.account.reserve,
+          charterMint: charter.account.mint,
+          charterMintAuthority,
+        })
+        .rpc();
+
+    const first = await program.account.subscription.fetch(subscription);
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await renew();
+
+    // Charged the renewal price, without minting another listing token
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 82);
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 1);
+    const renewed = await program.account.subscription.fetch(subscription);
+    assert.ok(renewed.expiresAt.toNumber() > first.expiresAt.toNumber());
+
+    // The allowance can't cover a second renewal, so nothing is charged,
+    // but auto-renew stays on
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await renew();
+    after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 82);
+    assert.ok(await program.provider.connection.getAccountInfo(autoRenewal));
+
+    // Neither does an unavailable listing
+    const approve = (amount: number) =>
+      program.provider.send(
+        new Transaction().add(
+          splToken.createApproveInstruction(
+            payment.publicKey,
+            paymentDelegate,
+            program.provider.wallet.publicKey,
+            amount
+          )
+        )
+      );
+    const setAvailability = (isAvailable: boolean) =>
+      program.methods
+        .setListingAvailability(isAvailable)
+        .accounts({
+          listing: listing.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+    await approve(80);
+    await setAvailability(false);
+    await renew();
+    after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 82);
+    assert.ok(await program.provider.connection.getAccountInfo(autoRenewal));
+
+    // Once the listing's available again, the crank renews it
+    await setAvailability(true);
+    await renew();
+    after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 74);
+
+    // A second subscription that renews from the same payment account
+    let other = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    await program.methods
+      .setListingSubscription(new anchor.BN(1), new anchor.BN(8))
+      .accounts({
+        listing: other.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    other = {
+      account: await program.account.listing.fetch(other.publicKey),
+      publicKey: other.publicKey,
+    };
+    const { inventory: otherInventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      other,
+      payment.publicKey,
+      1
+    );
+    const [otherSubscription] = await pda.subscription(
+      program.programId,
+      otherInventory.publicKey
+    );
+    const [otherAutoRenewal] = await pda.auto_renewal(
+      program.programId,
+      otherSubscription
+    );
+    await program.methods
+      .enableAutoRenew(paymentDelegateBump, new anchor.BN(80))
+      .accounts({
+        autoRenewal: otherAutoRenewal,
+        subscription: otherSubscription,
+        payment: payment.publicKey,
+        paymentDelegate,
+        autoRenewalPayment,
+        purchaser: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    let counted = await program.account.autoRenewalPayment.fetch(
+      autoRenewalPayment
+    );
+    assert.equal(counted.renewals.toNumber(), 2);
+
+    const cancel = (
+      autoRenewal: anchor.web3.PublicKey,
+      subscription: anchor.web3.PublicKey
+    ) =>
+      program.methods
+        .cancelAutoRenew()
+        .accounts({
+          autoRenewal,
+          autoRenewalPayment,
+          subscription,
+          payment: payment.publicKey,
+          purchaser: program.provider.wallet.publicKey,
+        })
+        .rpc();
+
+    // Cancelling one keeps the delegate for the other
+    await cancel(autoRenewal, subscription);
+    after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.ok(after.delegate.equals(paymentDelegate));
+    counted = await program.account.autoRenewalPayment.fetch(
+      autoRenewalPayment
+    );
+    assert.equal(counted.renewals.toNumber(), 1);
+
+    // Cancelling the last one revokes it
+    await cancel(otherAutoRenewal, otherSubscription);
+    after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.delegate, null);
+    assert.equal(
+      await program.provider.connection.getAccountInfo(autoRenewalPayment),
+      null
+    );
+  });
+
+  it("can resell a listing, paying the lister a royalty", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    let listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+
+    const [resaleOffer] = await pda.resale_offer(
+      program.programId,
+      inventory.publicKey
+    );
+    const initResaleOffer = () =>
+      program.methods
+        .initResaleOffer(new anchor.BN(20), new anchor.BN(1))
+        .accounts({
+          resaleOffer,
+          listing: listing.publicKey,
+          inventory: inventory.publicKey,
+          deposit: payment.publicKey,
+          seller: program.provider.wallet.publicKey,
+        })
+        .rpc();
+
+    // Listings aren't resellable by default
+    try {
+      await initResaleOffer();
+      assert.fail("expected the offer to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingIsNotResellable");
+    }
+
+    // A 25% royalty
+    await program.methods
+      .setListingResale(true, new anchor.BN(2_500))
+      .accounts({
+        listing: listing.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+    await initResaleOffer();
+
+    const buyer = Keypair.generate();
+    const buyerPayment = await createAssociatedTokenAccountForKeypair(
+      program,
+      buyer,
+      paymentMint.publicKey
+    );
+    const buyerInventory = await createAssociatedTokenAccountForKeypair(
+      program,
+      buyer,
+      listing.account.mint
+    );
+    await mintTo(program, paymentMint.publicKey, buyerPayment, 100);
+
+    const [listingMintAuthority, listingMintAuthorityBump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [charterMintAuthority, charterMintAuthorityBump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+    const [sellerInventoryDelegate, sellerInventoryDelegateBump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+    const [inventoryDelegate, inventoryDelegateBump] =
+      await pda.token_authority(program.programId, buyerInventory);
+
+    await program.methods
+      .purchaseResale(
+        listingMintAuthorityBump,
+        charterMintAuthorityBump,
+        sellerInventoryDelegateBump,
+        inventoryDelegateBump,
+        new anchor.BN(1),
+        new anchor.BN(20),
+        null
+      )
+      .accounts({
+        resaleOffer,
+        seller: program.provider.wallet.publicKey,
+        sellerInventory: inventory.publicKey,
+        sellerInventoryDelegate,
+        sellerDeposit: payment.publicKey,
+        payment: buyerPayment,
+        inventory: buyerInventory,
+        inventoryDelegate,
+        listingsPaymentDeposit: listing.account.paymentDeposit,
+        listingsVoteDeposit: listing.account.voteDeposit,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority,
+        charter: charter.publicKey,
+        charterTreasury: charterTreasury.publicKey,
+        charterTreasuryDeposit: charterTreasury.account.deposit,
+        charterReserve: charter.account.reserve,
+        charterMint: charter.account.mint,
+        charterMintAuthority,
+        purchaser: buyer.publicKey,
+      })
+      .signers([buyer])
+      .rpc();
+
+    // The token moved, and both inventories are frozen again
+    let sellerInventory = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(sellerInventory.amount, 0);
+    assert.equal(sellerInventory.isFrozen, true);
+    let buyerInventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      buyerInventory
+    );
+    assert.equal(buyerInventoryAccount.amount, 1);
+    assert.equal(buyerInventoryAccount.isFrozen, true);
+
+    // The charter takes 40% of 20, and the lister takes 25% of the rest
+    let buyerPaymentAccount = await splToken.getAccount(
+      program.provider.connection,
+      buyerPayment
+    );
+    assert.equal(buyerPaymentAccount.amount, 80);
+    let charterDeposit = await splToken.getAccount(
+      program.provider.connection,
+      charterTreasury.account.deposit
+    );
+    assert.equal(charterDeposit.amount, 4 + 8);
+    let listingDeposit = await splToken.getAccount(
+      program.provider.connection,
+      listing.account.paymentDeposit
+    );
+    assert.equal(listingDeposit.amount, 6 + 3);
+    let sellerPayment = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(sellerPayment.amount, 90 + 9);
+
+    // The filled offer was closed
+    assert.equal(
+      await program.provider.connection.getAccountInfo(resaleOffer),
+      null
+    );
+  });
+
+  it("can gift a listing, and reclaim a gift that isn't claimed", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    const [charterMintAuthority, charterMintAuthorityBump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+    const [listingMintAuthority, listingMintAuthorityBump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+
+    const purchaseGift = async (
+      nonce: anchor.BN,
+      recipient: anchor.web3.PublicKey | null,
+      secretKey: anchor.web3.PublicKey | null,
+      expiresIn: number
+    ) => {
+      const [gift] = await pda.gift(
+        program.programId,
+        program.provider.wallet.publicKey,
+        nonce
+      );
+      await program.methods
+        .purchaseGift(
+          charterMintAuthorityBump,
+          nonce,
+          recipient,
+          secretKey,
+          new anchor.BN(Math.floor(Date.now() / 1000) + expiresIn),
+          new anchor.BN(1),
+          new anchor.BN(10),
+          null
+        )
+        .accounts({
+          gift,
+          payment: payment.publicKey,
+          listingsPaymentDeposit: listing.account.paymentDeposit,
+          listingsVoteDeposit: listing.account.voteDeposit,
+          listing: listing.publicKey,
+          charter: charter.publicKey,
+          charterTreasury: charterTreasury.publicKey,
+          charterTreasuryDeposit: charterTreasury.account.deposit,
+          charterReserve: charter.account.reserve,
+          charterMint: charter.account.mint,
+          charterMintAuthority,
+          purchaser: program.provider.wallet.publicKey,
+        })
+        .rpc();
+      return gift;
+    };
+
+    // A gift for a friend, who claims it without having an inventory,
+    // so claimGift creates one
+    const friend = Keypair.generate();
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(
+        friend.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      )
+    );
+    const friendInventory = await splToken.getAssociatedTokenAddress(
+      listing.account.mint,
+      friend.publicKey
+    );
+    assert.equal(
+      await program.provider.connection.getAccountInfo(friendInventory),
+      null
+    );
+    const [friendInventoryDelegate, friendInventoryDelegateBump] =
+      await pda.token_authority(program.programId, friendInventory);
+    const gift = await purchaseGift(new anchor.BN(1), friend.publicKey, null, 60);
+
+    // The gift is paid for up front
+    let after = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(after.amount, 90);
+
+    await program.methods
+      .claimGift(listingMintAuthorityBump, friendInventoryDelegateBump)
+      .accounts({
+        gift,
+        purchaser: program.provider.wallet.publicKey,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority,
+        inventory: friendInventory,
+        inventoryDelegate: friendInventoryDelegate,
+        recipient: friend.publicKey,
+      })
+      .signers([friend])
+      .rpc();
+
+    let friendInventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      friendInventory
+    );
+    assert.equal(friendInventoryAccount.amount, 1);
+    assert.equal(friendInventoryAccount.isFrozen, true);
+    assert.equal(await program.provider.connection.getAccountInfo(gift), null);
+
+    // A gift locked with a secret key that no one claims in time
+    const secret = Keypair.generate();
+    const unclaimed = await purchaseGift(
+      new anchor.BN(2),
+      null,
+      secret.publicKey,
+      2
+    );
+    const inventory = await createTokenAccount(program, listing.account.mint);
+    const [inventoryDelegate, inventoryDelegateBump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+
+    // The wrong secret key can't claim it
+    const wrongSecret = Keypair.generate();
+    try {
+      await program.methods
+        .claimGift(listingMintAuthorityBump, inventoryDelegateBump)
+        .accounts({
+          gift: unclaimed,
+          purchaser: program.provider.wallet.publicKey,
+          listing: listing.publicKey,
+          listingMint: listing.account.mint,
+          listingMintAuthority,
+          inventory: inventory.publicKey,
+          inventoryDelegate,
+          recipient: program.provider.wallet.publicKey,
+        })
+        .remainingAccounts([
+          { pubkey: wrongSecret.publicKey, isWritable: false, isSigner: true },
+        ])
+        .signers([wrongSecret])
+        .rpc();
+      assert.fail("expected the claim to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "GiftSecretIsInvalid");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await program.methods
+      .reclaimGift(listingMintAuthorityBump, inventoryDelegateBump)
+      .accounts({
+        gift: unclaimed,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority,
+        inventory: inventory.publicKey,
+        inventoryDelegate,
+        purchaser: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 1);
   });
 
-  it("init_cashier", async () => {
+  it("can close a listing once its trials are settled", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.01,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
     );
-
-    const stake = Keypair.generate();
-    const [cashier_pda, cashier_bump] = await pda.cashier(
-      program.programId,
-      stake.publicKey
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
     );
-    const [stakeAuthority, stake_authority_bump] = await pda.token_authority(
-      program.programId,
-      stake.publicKey
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      1
+    );
+    const trial = await startTrial(
+      program,
+      listing,
+      paymentMint.publicKey,
+      payment.publicKey,
+      1
     );
 
+    const closeListing = () =>
+      program.methods
+        .closeListing()
+        .accounts({
+          listing: listing.publicKey,
+          authority: program.provider.wallet.publicKey,
+        })
+        .rpc();
+
+    try {
+      await closeListing();
+      assert.fail("expected the close to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingHasOpenTrials");
+    }
+
+    const [listingMintAuthority, listingMintAuthorityBump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [escrowAuthority, escrowAuthorityBump] = await pda.token_authority(
+      program.programId,
+      trial.escrow
+    );
+    const [inventoryDelegate, inventoryDelegateBump] =
+      await pda.token_authority(program.programId, trial.inventory.publicKey);
     await program.methods
-      .initCashier(stake_authority_bump, "ipfs://cashier")
+      .refundTrial(
+        listingMintAuthorityBump,
+        inventoryDelegateBump,
+        escrowAuthorityBump
+      )
       .accounts({
-        cashier: cashier_pda,
-        stake: stake.publicKey,
-        stakeAuthority,
-        charter: charter.publicKey,
-        charterMint: charter.account.mint,
-        authority: program.provider.wallet.publicKey,
-        clock: SYSVAR_CLOCK_PUBKEY,
+        receipt: trial.receipt,
+        returnDeposit: payment.publicKey,
+        purchaser: program.provider.wallet.publicKey,
+        escrow: trial.escrow,
+        escrowAuthority,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority,
+        inventory: trial.inventory.publicKey,
+        inventoryDelegate,
       })
-      .signers([stake])
       .rpc();
 
-    const cashier = await program.account.cashier.fetch(cashier_pda);
+    await closeListing();
     assert.equal(
-      cashier.authority.toString(),
-      program.provider.wallet.publicKey.toString()
+      await program.provider.connection.getAccountInfo(listing.publicKey),
+      null
     );
-    assert.equal(cashier.charter.toString(), charter.publicKey.toString());
-    assert.equal(cashier.stake.toString(), stake.publicKey.toString());
-    assert.equal(cashier.lastWithdrawAt, 0);
-    assert.equal(cashier.uri, "ipfs://cashier");
+
+    // Licenses that were already sold stay valid
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 1);
+    assert.equal(inventoryAccount.isFrozen, true);
   });
 
-  it("init_cashier_treasury", async () => {
+  it("emits events when a listing is purchased and a trial is refunded", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.01,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(1_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
     );
-
-    assert.equal(charter.account.uri, "https://strangemood.org");
-    if (!charter) {
-      throw new Error("charter not initialized");
-    }
-
-    const cashier = await initCashier(program, charter);
-    const mint = await createMint(program);
+    const paymentMint = await createMint(program);
     const charterTreasury = await createCharterTreasury(
       program,
       charter.publicKey,
-      mint.publicKey,
-      new anchor.BN(1)
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
     );
-    const deposit = await createTokenAccount(program, mint.publicKey);
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      10
+    );
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
 
-    const escrow = Keypair.generate();
-    const [escrow_authority, bump] = await pda.token_authority(
-      program.programId,
-      escrow.publicKey
+    // Resolves with the first event called "name" for this listing
+    const nextEvent = (name: string) => {
+      let listener: number;
+      const event = new Promise<any>((resolve) => {
+        listener = program.addEventListener(name, (event) => {
+          if (event.listing.equals(listing.publicKey)) {
+            resolve(event);
+          }
+        });
+      });
+      return event.then(async (event) => {
+        await program.removeEventListener(listener);
+        return event;
+      });
+    };
+
+    const purchased = nextEvent("Purchased");
+    const { inventory } = await purchase(
+      program,
+      charter,
+      charterTreasury,
+      listing,
+      payment.publicKey,
+      2
     );
-    const [cashier_treasury_pda, _] = await pda.treasury(
+    let event = await purchased;
+    assert.equal(event.charter.toString(), charter.publicKey.toString());
+    assert.equal(
+      event.purchaser.toString(),
+      program.provider.wallet.publicKey.toString()
+    );
+    assert.equal(event.inventory.toString(), inventory.publicKey.toString());
+    assert.equal(event.receipt, null);
+    assert.equal(event.quantity.toNumber(), 2);
+    assert.equal(event.price.toNumber(), 10);
+
+    // 10% of the 20 paid goes to the charter
+    assert.equal(event.splits.toCharterAmount.toNumber(), 2);
+    assert.equal(event.splits.toListerAmount.toNumber(), 18);
+
+    const trial = await startTrial(
+      program,
+      listing,
+      paymentMint.publicKey,
+      payment.publicKey,
+      1
+    );
+    const [listingMintAuthority, listingMintAuthorityBump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [escrowAuthority, escrowAuthorityBump] = await pda.token_authority(
       program.programId,
-      cashier.publicKey,
-      mint.publicKey
+      trial.escrow
     );
+    const [inventoryDelegate, inventoryDelegateBump] =
+      await pda.token_authority(program.programId, trial.inventory.publicKey);
 
+    const refunded = nextEvent("TrialRefunded");
     await program.methods
-      .initCashierTreasury(bump)
+      .refundTrial(
+        listingMintAuthorityBump,
+        inventoryDelegateBump,
+        escrowAuthorityBump
+      )
       .accounts({
-        cashierTreasury: cashier_treasury_pda,
-        cashier: cashier.publicKey,
-        charterTreasury: charterTreasury.publicKey,
-        charter: charter.publicKey,
-        deposit: deposit.publicKey,
-        escrow: escrow.publicKey,
-        escrowAuthority: escrow_authority,
-        mint: mint.publicKey,
-        clock: SYSVAR_CLOCK_PUBKEY,
-        authority: program.provider.wallet.publicKey,
+        receipt: trial.receipt,
+        returnDeposit: payment.publicKey,
+        purchaser: program.provider.wallet.publicKey,
+        escrow: trial.escrow,
+        escrowAuthority,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority,
+        inventory: trial.inventory.publicKey,
+        inventoryDelegate,
       })
-      .signers([escrow])
       .rpc();
-
-    const cashierTreasury = await program.account.cashierTreasury.fetch(
-      cashier_treasury_pda
-    );
-
-    assert.equal(
-      cashierTreasury.cashier.toString(),
-      cashier.publicKey.toString(),
-      "cashier does not match"
-    );
-    assert.equal(
-      cashierTreasury.deposit.toString(),
-      deposit.publicKey.toString(),
-      "deposit does not match"
-    );
-    assert.equal(
-      cashierTreasury.escrow.toString(),
-      escrow.publicKey.toString(),
-      "escrow does not match"
-    );
+    event = await refunded;
+    assert.equal(event.receipt.toString(), trial.receipt.toString());
+    assert.equal(event.cashier, null);
     assert.equal(
-      cashierTreasury.mint.toString(),
-      mint.publicKey.toString(),
-      "mint does not match"
+      event.purchaser.toString(),
+      program.provider.wallet.publicKey.toString()
     );
     assert.equal(
-      cashierTreasury.lastWithdrawAt,
-      0,
-      "lastWithdrawAt does not match"
+      event.inventory.toString(),
+      trial.inventory.publicKey.toString()
     );
+    assert.equal(event.quantity.toNumber(), 1);
+    assert.equal(event.amount.toNumber(), 10);
   });
 
-  it("can purchase a listing without a cashier", async () => {
+  it("won't purchase a listing for more than the buyer expected", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(100),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -380,7 +3018,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -389,91 +3027,47 @@ describe("Strangemood", () => {
       paymentMint.publicKey,
       10
     );
-
-    const inventory = await createTokenAccount(program, listing.account.mint);
     const payment = await createTokenAccount(program, paymentMint.publicKey);
-
-    const [listing_mint_authority, listing_mint_authority_bump] =
-      await pda.mint_authority(program.programId, listing.account.mint);
-
-    const [charter_mint_authority, charter_mint_authority_bump] =
-      await pda.mint_authority(program.programId, charter.account.mint);
-
-    const [inventory_delegate, inventory_delegate_bump] =
-      await pda.token_authority(program.programId, inventory.publicKey);
-
-    // Mint payment tokens into the payment account
     await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
 
-    // Check we have the funds to pay for the listing
-    let before = await splToken.getAccount(
-      program.provider.connection,
-      payment.publicKey
-    );
-    assert.equal(before.amount, 100);
-
-    // purchase the listing
+    // The lister raises the price after the buyer saw it
     await program.methods
-      .purchase(
-        listing_mint_authority_bump,
-        charter_mint_authority_bump,
-        inventory_delegate_bump,
-        new anchor.BN(1)
-      )
+      .setListingPrice(new anchor.BN(20))
       .accounts({
-        payment: payment.publicKey,
-        inventory: inventory.publicKey,
-        inventoryDelegate: inventory_delegate,
-        listingsPaymentDeposit: listing.account.paymentDeposit,
-        listingsVoteDeposit: listing.account.voteDeposit,
         listing: listing.publicKey,
-        listingMint: listing.account.mint,
-        listingMintAuthority: listing_mint_authority,
-        charter: charter.publicKey,
-        charterTreasury: charterTreasury.publicKey,
-        charterTreasuryDeposit: charterTreasury.account.deposit,
-        charterReserve: charter.account.reserve,
-        charterMint: charter.account.mint,
-        charterMintAuthority: charter_mint_authority,
-        purchaser: program.provider.wallet.publicKey,
+        authority: program.provider.wallet.publicKey,
       })
       .rpc();
 
-    // check the account was charged
+    try {
+      // Expects to pay the old price
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the purchase to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "PriceExceedsMaximum");
+    }
+
+    // Nothing was charged
     let after = await splToken.getAccount(
       program.provider.connection,
       payment.publicKey
     );
-    assert.equal(after.amount, 90);
-
-    // Check that the inventory has the token
-    let inventoryAccount = await splToken.getAccount(
-      program.provider.connection,
-      inventory.publicKey
-    );
-    assert.equal(inventoryAccount.amount, 1);
-
-    // Check that the listing payment deposit was paid
-    let listingDeposit = await splToken.getAccount(
-      program.provider.connection,
-      listing.account.paymentDeposit
-    );
-    assert.equal(listingDeposit.amount, 6);
-
-    // Check that the charter deposit was paid
-    let charterDeposit = await splToken.getAccount(
-      program.provider.connection,
-      charterTreasury.account.deposit
-    );
-    assert.equal(charterDeposit.amount, 4);
+    assert.equal(after.amount, 100);
   });
 
   it("can purchase a listing with a cashier", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -483,7 +3077,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -495,7 +3089,7 @@ describe("Strangemood", () => {
       true,
       false,
       true,
-      0.5
+      5_000
     );
     const cashier = await initCashier(program, charter);
     const cashierTreasury = await createCashierTreasury(
@@ -534,7 +3128,11 @@ describe("Strangemood", () => {
         listing_mint_authority_bump,
         charter_mint_authority_bump,
         inventory_delegate_bump,
-        new anchor.BN(1)
+        new anchor.BN(1),
+        new anchor.BN(10),
+        null,
+        null,
+        null
       )
       .accounts({
         cashier: cashier.publicKey,
@@ -597,9 +3195,9 @@ describe("Strangemood", () => {
   it("can purchase a listing, and then consume that listing", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -609,7 +3207,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -673,9 +3271,9 @@ describe("Strangemood", () => {
   it("can start start_trial with a cashier, and then finish_trial with cashier", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -685,7 +3283,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -729,6 +3327,7 @@ describe("Strangemood", () => {
         listing_mint_authority_bump,
         escrow_authority_bump,
         inventory_delegate_bump,
+        new anchor.BN(10),
         new anchor.BN(10)
       )
       .accounts({
@@ -844,9 +3443,9 @@ describe("Strangemood", () => {
   it("can start_trial, and then finish_trial", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -856,7 +3455,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -892,6 +3491,7 @@ describe("Strangemood", () => {
         listing_mint_authority_bump,
         escrow_authority_bump,
         inventory_delegate_bump,
+        new anchor.BN(10),
         new anchor.BN(10)
       )
       .accounts({
@@ -989,9 +3589,9 @@ describe("Strangemood", () => {
   it("can start_trial, and then finish_trial", async () => {
     const charter = await initCharter(
       program,
-      10,
-      0.4,
-      0.2,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
       new anchor.BN(1),
       new anchor.BN(1),
       "https://strangemood.org"
@@ -1001,7 +3601,7 @@ describe("Strangemood", () => {
       program,
       charter.publicKey,
       paymentMint.publicKey,
-      new anchor.BN(1)
+      new anchor.BN(10_000)
     );
     const listing = await initListing(
       program,
@@ -1037,6 +3637,7 @@ describe("Strangemood", () => {
         listing_mint_authority_bump,
         escrow_authority_bump,
         inventory_delegate_bump,
+        new anchor.BN(10),
         new anchor.BN(10)
       )
       .accounts({
@@ -1129,4 +3730,404 @@ describe("Strangemood", () => {
     );
     assert.equal(inventoryAccount.amount, 0);
   });
+
+  it("can start_trial_with_cashier, and then refund_trial_with_cashier", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      1,
+      0,
+      true,
+      true
+    );
+    const cashier = await initCashier(program, charter);
+
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    const { inventory, receipt, escrow } = await startTrial(
+      program,
+      listing,
+      paymentMint.publicKey,
+      payment.publicKey,
+      10,
+      cashier.publicKey
+    );
+
+    // The inventory delegate can burn the trial's tokens on refund
+    let inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 10);
+    assert.equal(inventoryAccount.delegatedAmount, 10);
+
+    const [listing_mint_authority, listing_mint_authority_bump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [escrow_authority, escrow_authority_bump] = await pda.token_authority(
+      program.programId,
+      escrow
+    );
+    const [inventory_delegate, inventory_delegate_bump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+    const accounts = {
+      receipt,
+      returnDeposit: payment.publicKey,
+      purchaser: program.provider.wallet.publicKey,
+      escrow: escrow,
+      escrowAuthority: escrow_authority,
+      listing: listing.publicKey,
+      listingMint: listing.account.mint,
+      listingMintAuthority: listing_mint_authority,
+      inventory: inventory.publicKey,
+      inventoryDelegate: inventory_delegate,
+    };
+
+    // A cashier's receipt can't be refunded without the cashier
+    try {
+      await program.methods
+        .refundTrial(
+          listing_mint_authority_bump,
+          inventory_delegate_bump,
+          escrow_authority_bump
+        )
+        .accounts(accounts)
+        .rpc();
+      assert.fail("expected the refund to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ReceiptHasCashier");
+    }
+
+    await program.methods
+      .refundTrialWithCashier(
+        listing_mint_authority_bump,
+        inventory_delegate_bump,
+        escrow_authority_bump
+      )
+      .accounts({ ...accounts, cashier: cashier.publicKey })
+      .rpc();
+
+    // The purchaser gets all their funds back
+    let paymentAccount = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(paymentAccount.amount, 100);
+
+    // The trial's tokens were burned
+    inventoryAccount = await splToken.getAccount(
+      program.provider.connection,
+      inventory.publicKey
+    );
+    assert.equal(inventoryAccount.amount, 0);
+
+    // The receipt was closed
+    assert.equal(
+      await program.provider.connection.getAccountInfo(receipt),
+      null
+    );
+  });
+
+  it("can't refund a trial after its refund window, but anyone can finish it", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      1,
+      0,
+      true,
+      false,
+      true,
+      1_000,
+      "ipfs://cid",
+      1
+    );
+
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    // Trials need a refund window
+    const windowless = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      1,
+      0,
+      true,
+      false,
+      true,
+      1_000,
+      "ipfs://cid",
+      0
+    );
+    try {
+      await startTrial(
+        program,
+        windowless,
+        paymentMint.publicKey,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the trial to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingHasNoRefundWindow");
+    }
+
+    const { inventory, receipt, escrow } = await startTrial(
+      program,
+      listing,
+      paymentMint.publicKey,
+      payment.publicKey,
+      10
+    );
+    const receiptAccount = await program.account.receipt.fetch(receipt);
+    assert.equal(receiptAccount.refundWindow.toNumber(), 1);
+    assert(receiptAccount.startedAt.toNumber() > 0);
+
+    // Wait for the refund window to pass
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+
+    const [listing_mint_authority, listing_mint_authority_bump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [escrow_authority, escrow_authority_bump] = await pda.token_authority(
+      program.programId,
+      escrow
+    );
+    const [inventory_delegate, inventory_delegate_bump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+
+    try {
+      await program.methods
+        .refundTrial(
+          listing_mint_authority_bump,
+          inventory_delegate_bump,
+          escrow_authority_bump
+        )
+        .accounts({
+          receipt,
+          returnDeposit: payment.publicKey,
+          purchaser: program.provider.wallet.publicKey,
+          escrow: escrow,
+          escrowAuthority: escrow_authority,
+          listing: listing.publicKey,
+          listingMint: listing.account.mint,
+          listingMintAuthority: listing_mint_authority,
+          inventory: inventory.publicKey,
+          inventoryDelegate: inventory_delegate,
+        })
+        .rpc();
+      assert.fail("expected the refund to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "RefundWindowHasExpired");
+    }
+
+    // The purchaser doesn't sign the crank
+    const cranker = Keypair.generate();
+    const sig = await program.provider.connection.requestAirdrop(
+      cranker.publicKey,
+      anchor.web3.LAMPORTS_PER_SOL
+    );
+    await program.provider.connection.confirmTransaction(sig);
+
+    const [charter_mint_authority, charter_mint_authority_bump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+    const ix = await program.methods
+      .finishTrial(charter_mint_authority_bump, escrow_authority_bump)
+      .accounts({
+        receipt,
+        purchaser: program.provider.wallet.publicKey,
+        listingsPaymentDeposit: listing.account.paymentDeposit,
+        listingsVoteDeposit: listing.account.voteDeposit,
+        listing: listing.publicKey,
+        charterTreasury: charterTreasury.publicKey,
+        charterTreasuryDeposit: charterTreasury.account.deposit,
+        charterReserve: charter.account.reserve,
+        receiptEscrow: escrow,
+        receiptEscrowAuthority: escrow_authority,
+        charter: charter.publicKey,
+        charterMint: charter.account.mint,
+        charterMintAuthority: charter_mint_authority,
+      })
+      .instruction();
+    await anchor.web3.sendAndConfirmTransaction(
+      program.provider.connection,
+      new Transaction().add(ix),
+      [cranker]
+    );
+
+    let listingDeposit = await splToken.getAccount(
+      program.provider.connection,
+      listing.account.paymentDeposit
+    );
+    assert.equal(listingDeposit.amount, 6);
+  });
+
+  it("stops paying a suspended listing, and lets its trials be refunded", async () => {
+    const charter = await initCharter(
+      program,
+      new anchor.BN(100_000),
+      new anchor.BN(4_000),
+      new anchor.BN(2_000),
+      new anchor.BN(1),
+      new anchor.BN(1),
+      "https://strangemood.org"
+    );
+    const paymentMint = await createMint(program);
+    const charterTreasury = await createCharterTreasury(
+      program,
+      charter.publicKey,
+      paymentMint.publicKey,
+      new anchor.BN(10_000)
+    );
+    const listing = await initListing(
+      program,
+      charter,
+      charterTreasury,
+      paymentMint.publicKey,
+      1,
+      0,
+      true,
+      false,
+      true,
+      1_000,
+      "ipfs://cid",
+      1
+    );
+
+    const payment = await createTokenAccount(program, paymentMint.publicKey);
+    await mintTo(program, paymentMint.publicKey, payment.publicKey, 100);
+
+    const { inventory, receipt, escrow } = await startTrial(
+      program,
+      listing,
+      paymentMint.publicKey,
+      payment.publicKey,
+      10
+    );
+
+    await program.methods
+      .setListingSuspension(true)
+      .accounts({
+        listing: listing.publicKey,
+        charter: charter.publicKey,
+        authority: program.provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // New purchases are rejected
+    try {
+      await purchase(
+        program,
+        charter,
+        charterTreasury,
+        listing,
+        payment.publicKey,
+        1
+      );
+      assert.fail("expected the purchase to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingIsSuspended");
+    }
+
+    // Wait for the refund window to pass
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+
+    // The escrowed trial can't be finished
+    const [charter_mint_authority, charter_mint_authority_bump] =
+      await pda.mint_authority(program.programId, charter.account.mint);
+    const [escrow_authority, escrow_authority_bump] = await pda.token_authority(
+      program.programId,
+      escrow
+    );
+    try {
+      await program.methods
+        .finishTrial(charter_mint_authority_bump, escrow_authority_bump)
+        .accounts({
+          receipt,
+          purchaser: program.provider.wallet.publicKey,
+          listingsPaymentDeposit: listing.account.paymentDeposit,
+          listingsVoteDeposit: listing.account.voteDeposit,
+          listing: listing.publicKey,
+          charterTreasury: charterTreasury.publicKey,
+          charterTreasuryDeposit: charterTreasury.account.deposit,
+          charterReserve: charter.account.reserve,
+          receiptEscrow: escrow,
+          receiptEscrowAuthority: escrow_authority,
+          charter: charter.publicKey,
+          charterMint: charter.account.mint,
+          charterMintAuthority: charter_mint_authority,
+        })
+        .rpc();
+      assert.fail("expected finishing the trial to fail");
+    } catch (e) {
+      assert.equal(e.error?.errorCode?.code, "ListingIsSuspended");
+    }
+
+    // But it can still be refunded, even though the window has passed
+    const [listing_mint_authority, listing_mint_authority_bump] =
+      await pda.mint_authority(program.programId, listing.account.mint);
+    const [inventory_delegate, inventory_delegate_bump] =
+      await pda.token_authority(program.programId, inventory.publicKey);
+    await program.methods
+      .refundTrial(
+        listing_mint_authority_bump,
+        inventory_delegate_bump,
+        escrow_authority_bump
+      )
+      .accounts({
+        receipt,
+        returnDeposit: payment.publicKey,
+        purchaser: program.provider.wallet.publicKey,
+        escrow: escrow,
+        escrowAuthority: escrow_authority,
+        listing: listing.publicKey,
+        listingMint: listing.account.mint,
+        listingMintAuthority: listing_mint_authority,
+        inventory: inventory.publicKey,
+        inventoryDelegate: inventory_delegate,
+      })
+      .rpc();
+
+    let paymentAccount = await splToken.getAccount(
+      program.provider.connection,
+      payment.publicKey
+    );
+    assert.equal(paymentAccount.amount, 100);
+  });
 });